@@ -0,0 +1,197 @@
+//! Site-wide broken link and missing asset checks.
+//!
+//! Link references are recorded while pages are post-processed (see
+//! [`ResolvedDependencies::link_references`]) and validated once the build has
+//! emitted everything, against the files in the output directory and the
+//! element ids of each emitted page.
+pub mod external;
+
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use macro_types::project::{DependencyRelation, FileInput, LinkKind, LinkReference, ProjectContext, ResolvedDependencies};
+
+// ————————————————————————————————————————————————————————————————————————————
+// COLLECTION HELPERS
+// ————————————————————————————————————————————————————————————————————————————
+
+/// Link references for stylesheet `url()`s of `page` (a page with inline
+/// `<style>` blocks, or a linked stylesheet).
+pub fn stylesheet_link_references(
+    page: &Path,
+    urls: &HashSet<DependencyRelation>,
+    project_context: &ProjectContext,
+) -> Vec<LinkReference> {
+    urls.iter()
+        .filter(|relation| !relation.to.trim_start().starts_with('#'))
+        .map(|relation| {
+            if relation.is_external_target() {
+                return LinkReference {
+                    kind: LinkKind::StylesheetUrl,
+                    page: page.to_path_buf(),
+                    origin: PathBuf::from(&relation.from),
+                    include_chain: Vec::default(),
+                    href: relation.to.clone(),
+                    target: None,
+                    fragment: None,
+                }
+            }
+            let target = relation.without_link_suffix().as_file_dependency().resolved_target_path();
+            let target = FileInput { source: path_clean::clean(target), public: None };
+            LinkReference {
+                kind: LinkKind::StylesheetUrl,
+                page: page.to_path_buf(),
+                origin: PathBuf::from(&relation.from),
+                include_chain: Vec::default(),
+                href: relation.to.clone(),
+                target: Some(target.resolved_public_path(project_context)),
+                fragment: None,
+            }
+        })
+        .collect()
+}
+
+/// Fills in the include chain of references that originate from included files.
+pub fn attach_include_chains(
+    link_references: &mut [LinkReference],
+    include_chains: &BTreeMap<PathBuf, Vec<PathBuf>>,
+) {
+    for link_reference in link_references.iter_mut() {
+        if let Some(chain) = include_chains.get(&path_clean::clean(&link_reference.origin)) {
+            link_reference.include_chain = chain.clone();
+        }
+    }
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// CHECKS
+// ————————————————————————————————————————————————————————————————————————————
+
+#[derive(Debug, Clone)]
+pub enum BrokenLinkReason {
+    /// Nothing was emitted at the target path.
+    MissingTarget { output_path: PathBuf },
+    /// The target page has no element with the given id.
+    MissingFragment { target_page: PathBuf, fragment: String },
+}
+
+#[derive(Debug, Clone)]
+pub struct BrokenLink {
+    pub reference: LinkReference,
+    pub reason: BrokenLinkReason,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LinkCheckReport {
+    pub checked: usize,
+    pub broken: Vec<BrokenLink>,
+}
+
+impl LinkCheckReport {
+    pub fn is_ok(&self) -> bool {
+        self.broken.is_empty()
+    }
+    pub fn print(&self) {
+        for broken_link in self.broken.iter() {
+            eprintln!("{broken_link}");
+        }
+        if self.is_ok() {
+            println!("> checked {} links: no broken links", self.checked);
+        } else {
+            eprintln!("❌ checked {} links: {} broken", self.checked, self.broken.len());
+        }
+    }
+}
+
+/// Validates every recorded link reference against the emitted output.
+pub fn check_links(
+    resolved_dependencies: &ResolvedDependencies,
+    project_context: &ProjectContext,
+) -> LinkCheckReport {
+    let mut report = LinkCheckReport::default();
+    let mut seen = HashSet::new();
+    for link_reference in resolved_dependencies.link_references.iter() {
+        if link_reference.is_external() || !seen.insert(link_reference) {
+            continue;
+        }
+        report.checked += 1;
+        if let Some(reason) = check_link(link_reference, resolved_dependencies, project_context) {
+            report.broken.push(BrokenLink { reference: link_reference.clone(), reason });
+        }
+    }
+    report.broken.sort_by(|left, right| {
+        (&left.reference.page, &left.reference.href).cmp(&(&right.reference.page, &right.reference.href))
+    });
+    report
+}
+
+fn check_link(
+    link_reference: &LinkReference,
+    resolved_dependencies: &ResolvedDependencies,
+    project_context: &ProjectContext,
+) -> Option<BrokenLinkReason> {
+    let target_page = match link_reference.target.as_ref() {
+        Some(target) => {
+            let target = path_clean::clean(target);
+            let output_path = project_context.output_dir.join(&target);
+            let is_emitted = resolved_dependencies.emitted_files.contains(&target)
+                || output_path.is_file()
+                || output_path.join("index.html").is_file();
+            if !is_emitted {
+                return Some(BrokenLinkReason::MissingTarget { output_path })
+            }
+            if output_path.is_dir() {
+                target.join("index.html")
+            } else {
+                target
+            }
+        }
+        None => {
+            let page = FileInput { source: link_reference.page.clone(), public: None };
+            path_clean::clean(page.resolved_public_path(project_context))
+        }
+    };
+    let fragment = match link_reference.fragment.as_deref() {
+        Some("") | Some("top") | None => return None,
+        Some(x) => x,
+    };
+    // Only pages compiled by this build have known ids.
+    let element_ids = resolved_dependencies.element_ids.get(&target_page)?;
+    if element_ids.contains(fragment) {
+        return None
+    }
+    Some(BrokenLinkReason::MissingFragment {
+        target_page,
+        fragment: fragment.to_string(),
+    })
+}
+
+impl std::fmt::Display for BrokenLink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let reference = &self.reference;
+        let kind = match reference.kind {
+            LinkKind::Attribute => "link",
+            LinkKind::SrcsetCandidate => "srcset candidate",
+            LinkKind::StylesheetUrl => "url()",
+        };
+        match &self.reason {
+            BrokenLinkReason::MissingTarget { output_path } => {
+                write!(f, "⚠️ {:?} broken {kind} {:?}: nothing emitted at {output_path:?}", reference.page, reference.href)?;
+            }
+            BrokenLinkReason::MissingFragment { target_page, fragment } => {
+                write!(f, "⚠️ {:?} broken {kind} {:?}: no element with id {fragment:?} in {target_page:?}", reference.page, reference.href)?;
+            }
+        }
+        if reference.include_chain.len() > 1 {
+            let chain = reference.include_chain
+                .iter()
+                .map(|x| x.display().to_string())
+                .collect::<Vec<_>>()
+                .join(" → ");
+            write!(f, "\n    via {chain}")?;
+        } else if path_clean::clean(&reference.origin) != path_clean::clean(&reference.page) {
+            write!(f, "\n    in {:?}", reference.origin)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,354 @@
+//! Opt-in verification of external (`http://` / `https://`) links.
+//!
+//! External URLs are collected from the build's link references, de-duplicated,
+//! and requested through an [`HttpClient`] with a concurrency limit and retries
+//! for transient failures. Results are kept in a persistent JSON cache so
+//! repeated builds only re-check stale or failed URLs.
+use std::collections::{BTreeMap, BTreeSet};
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use macro_types::project::ResolvedDependencies;
+
+// ————————————————————————————————————————————————————————————————————————————
+// HTTP BACKEND
+// ————————————————————————————————————————————————————————————————————————————
+
+/// The HTTP layer used by the checker.
+///
+/// Implemented for [`ReqwestHttpClient`]; tests can implement it over a local
+/// stub server (or without any network at all).
+pub trait HttpClient: Send + Sync + 'static {
+    /// Requests `url` and returns the final status code after redirects.
+    fn status(&self, url: &str) -> impl Future<Output = Result<u16, String>> + Send;
+}
+
+#[derive(Debug, Clone)]
+pub struct ReqwestHttpClient {
+    client: reqwest::Client,
+}
+
+impl ReqwestHttpClient {
+    pub fn new(timeout: Duration) -> Result<Self, String> {
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .user_agent(concat!("web-compiler/", env!("CARGO_PKG_VERSION"), " (link checker)"))
+            .build()
+            .map_err(|error| format!("failed to create the HTTP client: {error}"))?;
+        Ok(Self { client })
+    }
+}
+
+impl HttpClient for ReqwestHttpClient {
+    fn status(&self, url: &str) -> impl Future<Output = Result<u16, String>> + Send {
+        let client = self.client.clone();
+        let url = url.to_string();
+        async move {
+            let response = client.head(&url).send().await.map_err(|x| x.to_string())?;
+            let status = response.status().as_u16();
+            // Plenty of servers reject HEAD; only trust a GET for those.
+            if status == 405 || status == 501 || status == 403 {
+                let response = client.get(&url).send().await.map_err(|x| x.to_string())?;
+                return Ok(response.status().as_u16())
+            }
+            Ok(status)
+        }
+    }
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// OPTIONS
+// ————————————————————————————————————————————————————————————————————————————
+
+#[derive(Debug, Clone)]
+pub struct ExternalLinkCheckOptions {
+    /// Maximum number of requests in flight.
+    pub concurrency: usize,
+    /// Additional attempts after a transient failure (network error, 429, 5xx).
+    pub retries: usize,
+    /// Delay before the first retry, doubled on each further attempt.
+    pub retry_delay: Duration,
+    pub timeout: Duration,
+    /// Persistent result cache; `None` disables caching.
+    pub cache_path: Option<PathBuf>,
+    /// How long a successful result is trusted before the URL is re-checked.
+    pub cache_ttl: Duration,
+}
+
+impl Default for ExternalLinkCheckOptions {
+    fn default() -> Self {
+        Self {
+            concurrency: 8,
+            retries: 2,
+            retry_delay: Duration::from_millis(500),
+            timeout: Duration::from_secs(15),
+            cache_path: Some(PathBuf::from(".web-compiler/external-links.json")),
+            cache_ttl: Duration::from_secs(60 * 60 * 24 * 7),
+        }
+    }
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// RESULTS
+// ————————————————————————————————————————————————————————————————————————————
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "status")]
+pub enum ExternalLinkStatus {
+    Ok { code: u16 },
+    /// A definitive client error (e.g. 404, 410).
+    Broken { code: u16 },
+    /// Still failing after all retries (network errors, timeouts, 429, 5xx).
+    Unreachable { reason: String },
+}
+
+impl ExternalLinkStatus {
+    fn from_code(code: u16) -> Self {
+        match code {
+            200..=399 => Self::Ok { code },
+            429 | 500..=599 => Self::Unreachable { reason: format!("HTTP {code}") },
+            _ => Self::Broken { code },
+        }
+    }
+    fn is_transient(&self) -> bool {
+        matches!(self, Self::Unreachable { .. })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ExternalLinkResult {
+    pub url: String,
+    pub status: ExternalLinkStatus,
+    /// Pages (source paths) that link to `url`.
+    pub pages: BTreeSet<PathBuf>,
+    pub cached: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ExternalLinkReport {
+    pub results: Vec<ExternalLinkResult>,
+    /// Failures of the checker itself (no HTTP client or async runtime);
+    /// the links weren't checked.
+    pub errors: Vec<String>,
+}
+
+impl ExternalLinkReport {
+    pub fn broken(&self) -> impl Iterator<Item = &ExternalLinkResult> {
+        self.results.iter().filter(|x| matches!(x.status, ExternalLinkStatus::Broken { .. }))
+    }
+    pub fn unreachable(&self) -> impl Iterator<Item = &ExternalLinkResult> {
+        self.results.iter().filter(|x| matches!(x.status, ExternalLinkStatus::Unreachable { .. }))
+    }
+    /// Unreachable links are reported but do not fail the check, since they
+    /// are as likely to be a flaky network as a dead site.
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty() && self.broken().next().is_none()
+    }
+    pub fn print(&self) {
+        for error in self.errors.iter() {
+            eprintln!("❌ external link check failed: {error}");
+        }
+        for result in self.broken().chain(self.unreachable()) {
+            let status = match &result.status {
+                ExternalLinkStatus::Broken { code } => format!("broken (HTTP {code})"),
+                ExternalLinkStatus::Unreachable { reason } => format!("unreachable ({reason})"),
+                ExternalLinkStatus::Ok { .. } => continue,
+            };
+            eprintln!("⚠️ external link {:?} {status}", result.url);
+            for page in result.pages.iter() {
+                eprintln!("    linked from {page:?}");
+            }
+        }
+        let cached = self.results.iter().filter(|x| x.cached).count();
+        let broken = self.broken().count();
+        let unreachable = self.unreachable().count();
+        let summary = format!(
+            "checked {} external links ({cached} cached): {broken} broken, {unreachable} unreachable",
+            self.results.len(),
+        );
+        if self.is_ok() {
+            println!("> {summary}");
+        } else {
+            eprintln!("❌ {summary}");
+        }
+    }
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// CACHE
+// ————————————————————————————————————————————————————————————————————————————
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct ExternalLinkCache {
+    entries: BTreeMap<String, CachedStatus>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedStatus {
+    #[serde(flatten)]
+    status: ExternalLinkStatus,
+    /// Unix timestamp (seconds).
+    checked_at: u64,
+}
+
+impl ExternalLinkCache {
+    fn load(path: &Path) -> Self {
+        let source = match std::fs::read_to_string(path) {
+            Ok(x) => x,
+            Err(_) => return Self::default(),
+        };
+        serde_json::from_str(&source).unwrap_or_else(|error| {
+            eprintln!("⚠️ ignoring unreadable external link cache {path:?}: {error}");
+            Self::default()
+        })
+    }
+    fn save(&self, path: &Path) {
+        if let Some(parent) = path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        let contents = serde_json::to_string_pretty(self).unwrap();
+        if let Err(error) = std::fs::write(path, contents) {
+            eprintln!("⚠️ failed to write external link cache {path:?}: {error}");
+        }
+    }
+    /// Only successful results are reused; failures are always re-checked.
+    fn lookup(&self, url: &str, now: u64, ttl: Duration) -> Option<&ExternalLinkStatus> {
+        let entry = self.entries.get(url)?;
+        let is_fresh = now.saturating_sub(entry.checked_at) <= ttl.as_secs();
+        match entry.status {
+            ExternalLinkStatus::Ok { .. } if is_fresh => Some(&entry.status),
+            _ => None,
+        }
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|x| x.as_secs())
+        .unwrap_or_default()
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// CHECKER
+// ————————————————————————————————————————————————————————————————————————————
+
+/// Collects the `http(s)` URLs referenced by the build, keyed by URL.
+pub fn collect_external_links(resolved_dependencies: &ResolvedDependencies) -> BTreeMap<String, BTreeSet<PathBuf>> {
+    let mut urls = BTreeMap::<String, BTreeSet<PathBuf>>::new();
+    for link_reference in resolved_dependencies.link_references.iter() {
+        if !link_reference.is_external() {
+            continue;
+        }
+        let href = link_reference.href.trim();
+        let url = if let Some(rest) = href.strip_prefix("//") {
+            format!("https://{rest}")
+        } else {
+            href.to_string()
+        };
+        let lowered = url.to_ascii_lowercase();
+        if !lowered.starts_with("http://") && !lowered.starts_with("https://") {
+            continue;
+        }
+        // The fragment is never sent to the server.
+        let url = url.split_once('#').map(|(x, _)| x.to_string()).unwrap_or(url);
+        urls.entry(url).or_default().insert(link_reference.page.clone());
+    }
+    urls
+}
+
+/// Checks every external link of the build with the default HTTP backend.
+pub fn check_external_links(
+    resolved_dependencies: &ResolvedDependencies,
+    options: &ExternalLinkCheckOptions,
+) -> ExternalLinkReport {
+    let setup = ReqwestHttpClient::new(options.timeout).and_then(|client| {
+        tokio::runtime::Runtime::new()
+            .map(|runtime| (client, runtime))
+            .map_err(|error| format!("failed to start the async runtime: {error}"))
+    });
+    match setup {
+        Ok((client, runtime)) => {
+            runtime.block_on(check_external_links_with(client, resolved_dependencies, options))
+        }
+        Err(error) => ExternalLinkReport { results: Vec::new(), errors: vec![error] },
+    }
+}
+
+pub async fn check_external_links_with<Client: HttpClient>(
+    client: Client,
+    resolved_dependencies: &ResolvedDependencies,
+    options: &ExternalLinkCheckOptions,
+) -> ExternalLinkReport {
+    let urls = collect_external_links(resolved_dependencies);
+    let mut cache = options.cache_path
+        .as_deref()
+        .map(ExternalLinkCache::load)
+        .unwrap_or_default();
+    let now = unix_now();
+    let client = Arc::new(client);
+    let semaphore = Arc::new(tokio::sync::Semaphore::new(options.concurrency.max(1)));
+    let mut results = Vec::<ExternalLinkResult>::with_capacity(urls.len());
+    let mut errors = Vec::<String>::new();
+    let mut tasks = tokio::task::JoinSet::new();
+    for (url, pages) in urls {
+        if let Some(status) = cache.lookup(&url, now, options.cache_ttl) {
+            results.push(ExternalLinkResult { url, status: status.clone(), pages, cached: true });
+            continue;
+        }
+        let client = client.clone();
+        let semaphore = semaphore.clone();
+        let retries = options.retries;
+        let retry_delay = options.retry_delay;
+        tasks.spawn(async move {
+            let status = match semaphore.acquire_owned().await {
+                Ok(_permit) => check_url(client.as_ref(), &url, retries, retry_delay).await,
+                Err(error) => ExternalLinkStatus::Unreachable { reason: error.to_string() },
+            };
+            ExternalLinkResult { url, status, pages, cached: false }
+        });
+    }
+    while let Some(result) = tasks.join_next().await {
+        match result {
+            Ok(result) => results.push(result),
+            Err(error) => errors.push(format!("a link check task failed: {error}")),
+        }
+    }
+    for result in results.iter().filter(|x| !x.cached) {
+        cache.entries.insert(result.url.clone(), CachedStatus {
+            status: result.status.clone(),
+            checked_at: now,
+        });
+    }
+    if let Some(cache_path) = options.cache_path.as_deref() {
+        cache.save(cache_path);
+    }
+    results.sort_by(|left, right| left.url.cmp(&right.url));
+    ExternalLinkReport { results, errors }
+}
+
+async fn check_url<Client: HttpClient>(
+    client: &Client,
+    url: &str,
+    retries: usize,
+    retry_delay: Duration,
+) -> ExternalLinkStatus {
+    let mut delay = retry_delay;
+    let mut attempt = 0;
+    loop {
+        let status = match client.status(url).await {
+            Ok(code) => ExternalLinkStatus::from_code(code),
+            Err(reason) => ExternalLinkStatus::Unreachable { reason },
+        };
+        if !status.is_transient() || attempt >= retries {
+            return status
+        }
+        attempt += 1;
+        tokio::time::sleep(delay).await;
+        delay *= 2;
+    }
+}
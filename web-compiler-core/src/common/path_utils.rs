@@ -61,10 +61,15 @@ pub fn write_output_file_smart(file_path: impl AsRef<Path>, content: impl AsRef<
 
     if file_needs_write {
         if let Some(parent_dir) = file_path.parent() {
-            std::fs::create_dir_all(parent_dir).unwrap();
+            if let Err(error) = std::fs::create_dir_all(parent_dir) {
+                eprintln!("⚠️ failed to create output directory {parent_dir:?}: {error}");
+                return
+            }
         }
         println!("> writing {file_path:?}");
-        std::fs::write(file_path, new_bytes).unwrap();
+        if let Err(error) = std::fs::write(file_path, new_bytes) {
+            eprintln!("⚠️ failed to write {file_path:?}: {error}");
+        }
     }
 }
 
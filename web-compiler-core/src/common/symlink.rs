@@ -45,13 +45,14 @@ impl SymlinkStatus {
 pub fn create_relative_symlink(source_path: impl AsRef<Path>, link_path: impl AsRef<Path>) -> Result<SymlinkStatus, Box<dyn std::error::Error>> {
     let source_path= path_clean::clean(source_path);
     let link_path = path_clean::clean(link_path);
-    let link_dir = link_path.parent().expect("Link must have a parent");
+    let link_dir = link_path.parent().ok_or_else(|| format!("{link_path:?} has no parent directory"))?;
 
     // Ensure link directory exists
-    fs::create_dir_all(link_dir).unwrap();
+    fs::create_dir_all(link_dir)?;
 
     // Compute relative path from symlink location to real target
-    let relative_target = diff_paths(source_path, link_dir).unwrap();
+    let relative_target = diff_paths(&source_path, link_dir)
+        .ok_or_else(|| format!("can't make {source_path:?} relative to {link_dir:?}"))?;
 
     // Check if symlink exists and is correct
     if link_path.exists() {
@@ -81,7 +82,7 @@ pub fn create_relative_symlink(source_path: impl AsRef<Path>, link_path: impl As
             if DEBUG_MODE {
                 println!("⚠️ Symlink exists but points elsewhere. Replacing it.");
             }
-            fs::remove_file(&link_path).unwrap();
+            fs::remove_file(&link_path)?;
         }
     }
 
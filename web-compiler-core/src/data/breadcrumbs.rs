@@ -1,52 +1,34 @@
 use macro_types::breadcrumbs::{BreadcrumbComponentValue, BreadcrumbPathListValue, SystemBreadcrumbComponent, SystemBreadcrumbPath};
-use macro_types::lexical_env::MacroIO;
-use macro_types::project::ProjectContext;
+use macro_types::project::FileInput;
 
 
 // ————————————————————————————————————————————————————————————————————————————
 // API
 // ————————————————————————————————————————————————————————————————————————————
 
-pub fn to_breadcrumb_value_path(system_breadcrumb_path: &SystemBreadcrumbPath, project_context: &ProjectContext) -> MacroIO<BreadcrumbPathListValue> {
-    let (components, deps ) = system_breadcrumb_path.components
+/// The `breadcrumbs` binding. Hrefs are virtual paths from the page to each
+/// component's source, resolved (and tracked) where they're used.
+pub fn to_breadcrumb_value_path(system_breadcrumb_path: &SystemBreadcrumbPath) -> BreadcrumbPathListValue {
+    let components = system_breadcrumb_path.components
         .iter()
         .map(|SystemBreadcrumbComponent { source, title }| {
-            let host_path = system_breadcrumb_path.file_input.resolved_public_path(project_context);
-            let target_path = source.resolved_public_path(project_context);
-            let relative = pathdiff::diff_paths(&target_path, host_path.parent().unwrap()).unwrap();
-            let relative = relative.to_str().unwrap();
-            let dependency = system_breadcrumb_path.file_input.with_dependency_relation(relative);
-            let result = BreadcrumbComponentValue {
-                href: format!("noop://{relative}"),
+            BreadcrumbComponentValue {
+                href: page_href(&system_breadcrumb_path.file_input, source),
                 title: title.to_owned(),
-            };
-            (result, dependency)
+            }
         })
-        .unzip::<_, _, Vec<_>, Vec<_>>();
-        // .collect::<Vec<_>>();
-    MacroIO::wrap(BreadcrumbPathListValue(components)).and_modify_context(|_| {
-        // ctx.dependencies.extend(deps);
-        let _ = deps;
-    })
+        .collect::<Vec<_>>();
+    BreadcrumbPathListValue(components)
 }
 
-// pub fn to_breadcrumb_value_path(system_breadcrumb_path: &SystemBreadcrumbPath, project_context: &ProjectContext) -> MacroIO<BreadcrumbPathListValue> {
-//     let components = system_breadcrumb_path.components
-//         .iter()
-//         .map(|SystemBreadcrumbComponent { source, title }| {
-//             let host_path = system_breadcrumb_path.file_input.resolved_public_path(project_context);
-//             let target_path = source.resolved_public_path(project_context);
-//             let relative = pathdiff::diff_paths(&target_path, host_path.parent().unwrap()).unwrap();
-//             let relative = relative.to_str().unwrap();
-//             let dependency = system_breadcrumb_path.file_input.with_dependency_relation(relative);
-//             let result = BreadcrumbComponentValue {
-//                 href: format!("/noop://{relative}"),
-//                 title: title.to_owned(),
-//             };
-//             result
-//         })
-//         .collect::<Vec<_>>();
-//         // .collect::<Vec<_>>();
-//     MacroIO::wrap(BreadcrumbPathListValue(components))
-// }
-
+/// An encoded virtual path from `host` to the source of `target`.
+///
+/// Attribute rewriting resolves it to the target's output path relative to
+/// the page's, and records it as a dependency (so it is link-checked and
+/// fingerprinted like a hand-written link), wherever the href ends up.
+pub fn page_href(host: &FileInput, target: &FileInput) -> String {
+    let host_dir = host.source_file().parent().unwrap_or("".as_ref());
+    let relative = pathdiff::diff_paths(target.source_file(), host_dir)
+        .unwrap_or_else(|| target.source.clone());
+    host.with_dependency_relation(relative.to_string_lossy()).encode()
+}
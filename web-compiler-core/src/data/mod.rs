@@ -1 +1,2 @@
 pub mod breadcrumbs;
+pub mod navigation;
@@ -0,0 +1,77 @@
+use std::collections::HashSet;
+
+use macro_types::breadcrumbs::{NavigationPageValue, NavigationValue, SiteTreeLayout};
+use macro_types::project::FileInput;
+
+
+// ————————————————————————————————————————————————————————————————————————————
+// API
+// ————————————————————————————————————————————————————————————————————————————
+
+/// The `nav` binding of `file_input`, or `None` for files outside the site
+/// tree (e.g. templates compiled on their own).
+///
+/// Hrefs are virtual paths from the page to each target's source (see
+/// [`page_href`](super::breadcrumbs::page_href)).
+pub fn to_navigation_value(site_tree_layout: &SiteTreeLayout, file_input: &FileInput) -> Option<NavigationValue> {
+    let current = site_tree_layout.page_index(file_input)?;
+    let mut ancestors = site_tree_layout.ancestors_and_self(current);
+    ancestors.pop();
+    let builder = NavigationBuilder {
+        layout: site_tree_layout,
+        file_input,
+        current,
+        ancestors: ancestors.iter().copied().collect(),
+    };
+    let page = site_tree_layout.page(current);
+    let siblings = match page.parent {
+        Some(parent) => site_tree_layout.page(parent).children.clone(),
+        None => site_tree_layout.roots(),
+    };
+    let reading_order = site_tree_layout.reading_order();
+    let position = reading_order.iter().position(|x| *x == current);
+    let previous = position
+        .and_then(|x| x.checked_sub(1))
+        .map(|x| reading_order[x]);
+    let next = position
+        .map(|x| x + 1)
+        .and_then(|x| reading_order.get(x).copied());
+    Some(NavigationValue {
+        current: builder.page_value(current),
+        parent: page.parent.into_iter().map(|x| builder.page_value(x)).collect(),
+        ancestors: ancestors.iter().map(|x| builder.page_value(*x)).collect(),
+        children: page.children.iter().map(|x| builder.page_value(*x)).collect(),
+        siblings: siblings
+            .into_iter()
+            .filter(|x| *x != current)
+            .map(|x| builder.page_value(x))
+            .collect(),
+        previous: previous.into_iter().map(|x| builder.page_value(x)).collect(),
+        next: next.into_iter().map(|x| builder.page_value(x)).collect(),
+        roots: site_tree_layout.roots().into_iter().map(|x| builder.page_value(x)).collect(),
+    })
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// INTERNAL
+// ————————————————————————————————————————————————————————————————————————————
+
+struct NavigationBuilder<'a> {
+    layout: &'a SiteTreeLayout,
+    file_input: &'a FileInput,
+    current: usize,
+    ancestors: HashSet<usize>,
+}
+
+impl<'a> NavigationBuilder<'a> {
+    fn page_value(&self, index: usize) -> NavigationPageValue {
+        let page = self.layout.page(index);
+        NavigationPageValue {
+            href: super::breadcrumbs::page_href(self.file_input, &page.source),
+            title: page.title.clone(),
+            is_current: index == self.current,
+            is_ancestor: self.ancestors.contains(&index),
+            children: page.children.iter().map(|x| self.page_value(*x)).collect(),
+        }
+    }
+}
@@ -0,0 +1,92 @@
+//! Errors that stop a build, or one of its pages, from compiling.
+//!
+//! Problems confined to one element (an unresolved binding, invalid CSS, a
+//! missing asset) are reported as warnings and the page is still emitted;
+//! the errors here are for everything else.
+use std::path::PathBuf;
+
+pub use crate::markup::{PipelineError, PreProcessError};
+
+#[derive(Debug)]
+pub enum CompilerError {
+    /// The manifest couldn't be loaded or doesn't describe a build.
+    Manifest(ManifestError),
+    /// Pages that failed to compile; the rest of the site was still emitted.
+    Pages(Vec<PipelineError>),
+}
+
+impl std::fmt::Display for CompilerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Manifest(error) => write!(f, "invalid manifest: {error}"),
+            Self::Pages(errors) => {
+                let noun = if errors.len() == 1 { "page" } else { "pages" };
+                write!(f, "{} {noun} failed to compile", errors.len())?;
+                for error in errors {
+                    write!(f, "\n  • {error}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for CompilerError {}
+
+impl From<ManifestError> for CompilerError {
+    fn from(error: ManifestError) -> Self {
+        Self::Manifest(error)
+    }
+}
+
+#[derive(Debug)]
+pub enum ManifestError {
+    Read { path: PathBuf, error: std::io::Error },
+    /// Invalid TOML, or TOML that doesn't match the manifest schema; the
+    /// position is the 1-based line and column.
+    Parse { path: PathBuf, position: Option<(usize, usize)>, message: String },
+    TargetNotFound { target: String, available: Vec<String> },
+    DuplicateTarget { target: String },
+    /// A target overrides `base_url` but there's no `[site]` to override.
+    BaseUrlWithoutSite { target: String },
+    InvalidSourcePattern { pattern: String, message: String },
+    /// A source matched by `input` that doesn't start with its `strip_prefix`.
+    SourceOutsidePrefix { source: PathBuf, prefix: String },
+    NoMatchingSources { pattern: String },
+    InvalidSiteTree { path: PathBuf, message: String },
+    /// A file the manifest refers to (`key`) that doesn't exist.
+    MissingFile { key: &'static str, path: PathBuf },
+}
+
+impl std::fmt::Display for ManifestError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Read { path, error } => write!(f, "failed to read {path:?}: {error}"),
+            Self::Parse { path, position: Some((line, column)), message } => {
+                write!(f, "{path:?} line {line}, column {column}: {message}")
+            }
+            Self::Parse { path, position: None, message } => write!(f, "{path:?}: {message}"),
+            Self::TargetNotFound { target, available } if available.is_empty() => {
+                write!(f, "target {target:?} not found (the manifest defines no [[targets]])")
+            }
+            Self::TargetNotFound { target, available } => {
+                write!(f, "target {target:?} not found (available: {})", available.join(", "))
+            }
+            Self::DuplicateTarget { target } => write!(f, "target {target:?} is defined more than once"),
+            Self::BaseUrlWithoutSite { target } => {
+                write!(f, "target {target:?} overrides base_url, but the manifest has no [site] section")
+            }
+            Self::InvalidSourcePattern { pattern, message } => {
+                write!(f, "invalid source pattern {pattern:?}: {message}")
+            }
+            Self::SourceOutsidePrefix { source, prefix } => {
+                write!(f, "source {source:?} doesn't start with its strip_prefix {prefix:?}")
+            }
+            Self::NoMatchingSources { pattern } => write!(f, "source pattern {pattern:?} matches no files"),
+            Self::InvalidSiteTree { path, message } => write!(f, "invalid site tree {path:?}: {message}"),
+            Self::MissingFile { key, path } => write!(f, "{key} {path:?} doesn't exist"),
+        }
+    }
+}
+
+impl std::error::Error for ManifestError {}
@@ -0,0 +1,317 @@
+//! Responsive images.
+//!
+//! Opted-in `<img>` elements (a `responsive` attribute, or a source image
+//! matching `[images] include`) have their local raster image decoded and
+//! resized to the configured widths, encoded as AVIF, WebP and/or the source
+//! format. The variants are written next to the image's output path
+//! (`photo.jpg` → `photo-480w-q75.avif`, `photo-480w-q75.webp`,
+//! `photo-480w-q75.jpg`, …) and the element is rewritten to
+//!
+//! ```html
+//! <picture>
+//!     <source type="image/avif" srcset="photo-480w-q75.avif 480w, …" sizes="100vw">
+//!     <source type="image/webp" srcset="photo-480w-q75.webp 480w, …" sizes="100vw">
+//!     <img src="photo.jpg" srcset="photo-480w-q75.jpg 480w, …, photo.jpg 2400w"
+//!          sizes="100vw" width="2400" height="1600" loading="lazy">
+//! </picture>
+//! ```
+//!
+//! Attributes already present on the `<img>` are kept. Variants are only
+//! re-encoded when the source image is newer than the emitted file, or when
+//! the quality changes (which changes their names).
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+use macro_types::helpers::srcset::SrcsetCandidate;
+use macro_types::image_cache::{ImageCacheKey, ImageVariant, ProcessedImage};
+use macro_types::project::{split_link_suffix, DependencyRelation, FileInput, ProjectContext, ResolvedDependencies};
+use web_compiler_types::{ImageOptions, ResponsiveImageFormat};
+use xml_ast::{AttributeMap, AttributeValueBuf, Element, Node};
+
+pub const RESPONSIVE_ATTRIBUTE: &'static str = "responsive";
+
+/// Source formats that are decoded and resized.
+const SUPPORTED_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "webp"];
+
+// ————————————————————————————————————————————————————————————————————————————
+// REQUESTS
+// ————————————————————————————————————————————————————————————————————————————
+
+/// An opted-in `<img>`, captured before its `src` is resolved.
+#[derive(Debug, Clone)]
+pub struct ResponsiveImageRequest {
+    pub source: PathBuf,
+    pub public: PathBuf,
+}
+
+/// Checks whether the `<img>` opts in (and strips the `responsive`
+/// attribute); must run while `src` is still a virtual path.
+pub fn responsive_image_request(
+    attributes: &mut AttributeMap,
+    options: &ImageOptions,
+    project_context: &ProjectContext,
+) -> Option<ResponsiveImageRequest> {
+    let opt_in = attributes
+        .remove(RESPONSIVE_ATTRIBUTE)
+        .map(|x| !matches!(x.as_str().trim().to_ascii_lowercase().as_str(), "false" | "no"));
+    if opt_in == Some(false) || (opt_in.is_none() && options.include.is_empty()) {
+        return None
+    }
+    let relation = DependencyRelation::decode(attributes.get("src")?.as_str())?;
+    if relation.is_external_target() {
+        return None
+    }
+    let source = path_clean::clean(relation.without_link_suffix().as_file_dependency().resolved_target_path());
+    if opt_in.is_none() && !is_included(&source, options) {
+        return None
+    }
+    let extension = source
+        .extension()
+        .and_then(|x| x.to_str())
+        .map(|x| x.to_ascii_lowercase())
+        .unwrap_or_default();
+    if !SUPPORTED_EXTENSIONS.contains(&extension.as_str()) {
+        if opt_in.is_some() {
+            eprintln!("⚠️ {source:?} responsive images support {SUPPORTED_EXTENSIONS:?} sources only");
+        }
+        return None
+    }
+    let public = FileInput { source: source.clone(), public: None }.resolved_public_path(project_context);
+    Some(ResponsiveImageRequest { source, public })
+}
+
+fn is_included(source: &Path, options: &ImageOptions) -> bool {
+    options.include.iter().any(|pattern| {
+        match glob::Pattern::new(pattern) {
+            Ok(pattern) => pattern.matches_path(source),
+            Err(error) => {
+                eprintln!("⚠️ [images] invalid include pattern {pattern:?}: {error}");
+                false
+            }
+        }
+    })
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// VARIANTS
+// ————————————————————————————————————————————————————————————————————————————
+
+/// Each source image is processed once per build (see
+/// [`ImageCache`](macro_types::image_cache::ImageCache)), however many pages
+/// use it.
+fn process_image(
+    request: &ResponsiveImageRequest,
+    options: &ImageOptions,
+    project_context: &ProjectContext,
+) -> Result<ProcessedImage, String> {
+    let key = ImageCacheKey {
+        source: request.source.clone(),
+        output_dir: project_context.output_dir.clone(),
+        widths: options.widths.clone(),
+        formats: options.formats.clone(),
+        quality: options.quality,
+    };
+    project_context.image_cache.get_or_process(key, || encode_variants(request, options, project_context))
+}
+
+fn encode_variants(
+    request: &ResponsiveImageRequest,
+    options: &ImageOptions,
+    project_context: &ProjectContext,
+) -> Result<ProcessedImage, String> {
+    let (width, height) = image::image_dimensions(&request.source).map_err(|x| x.to_string())?;
+    let source_modified = std::fs::metadata(&request.source).and_then(|x| x.modified()).ok();
+    let mut widths = options.widths
+        .iter()
+        .copied()
+        .filter(|x| *x > 0 && *x < width)
+        .collect::<Vec<_>>();
+    widths.sort();
+    widths.dedup();
+    widths.push(width);
+    let extension = request.source
+        .extension()
+        .and_then(|x| x.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    let stem = request.source
+        .file_stem()
+        .and_then(|x| x.to_str())
+        .unwrap_or_default()
+        .to_string();
+    let mut decoded: Option<image::DynamicImage> = None;
+    let mut variants = Vec::<ImageVariant>::new();
+    for format in options.formats.iter().copied() {
+        for variant_width in widths.iter().copied() {
+            if format == ResponsiveImageFormat::Original && variant_width == width {
+                variants.push(ImageVariant { format, width, public: None });
+                continue;
+            }
+            let variant_extension = match format {
+                ResponsiveImageFormat::Avif => "avif",
+                ResponsiveImageFormat::Webp => "webp",
+                ResponsiveImageFormat::Original => extension.as_str(),
+            };
+            // Everything that shapes the encoded file is in its name (the
+            // format, width and quality), so a variant from a build with other
+            // settings is never mistaken for a fresh one.
+            let public = request.public.with_file_name(format!(
+                "{stem}-{variant_width}w-q{quality}.{variant_extension}",
+                quality = options.quality,
+            ));
+            let output_path = project_context.output_dir.join(&public);
+            let is_fresh = std::fs::metadata(&output_path)
+                .and_then(|x| x.modified())
+                .ok()
+                .zip(source_modified)
+                .map(|(output, source)| output >= source)
+                .unwrap_or(false);
+            if !is_fresh {
+                if decoded.is_none() {
+                    decoded = Some(image::open(&request.source).map_err(|x| x.to_string())?);
+                }
+                let source_image = decoded.as_ref().unwrap();
+                let variant_height = ((height as u64 * variant_width as u64) / width as u64).max(1) as u32;
+                let resized = if variant_width == width {
+                    source_image.clone()
+                } else {
+                    source_image.resize_exact(variant_width, variant_height, image::imageops::FilterType::Lanczos3)
+                };
+                let bytes = encode(&resized, format, &extension, options.quality)?;
+                crate::common::path_utils::write_output_file_smart(&output_path, bytes);
+            }
+            variants.push(ImageVariant { format, width: variant_width, public: Some(public) });
+        }
+    }
+    Ok(ProcessedImage { width, height, variants })
+}
+
+fn encode(
+    image: &image::DynamicImage,
+    format: ResponsiveImageFormat,
+    extension: &str,
+    quality: u8,
+) -> Result<Vec<u8>, String> {
+    let quality = quality.clamp(1, 100);
+    let mut bytes = Vec::<u8>::new();
+    match (format, extension) {
+        (ResponsiveImageFormat::Avif, _) => {
+            let encoder = image::codecs::avif::AvifEncoder::new_with_speed_quality(&mut bytes, 6, quality);
+            image::DynamicImage::ImageRgba8(image.to_rgba8())
+                .write_with_encoder(encoder)
+                .map_err(|x| x.to_string())?;
+        }
+        (ResponsiveImageFormat::Webp, _) | (ResponsiveImageFormat::Original, "webp") => {
+            let rgba = image.to_rgba8();
+            let encoded = webp::Encoder::from_rgba(rgba.as_raw(), rgba.width(), rgba.height())
+                .encode(quality as f32);
+            bytes.extend_from_slice(&encoded);
+        }
+        (ResponsiveImageFormat::Original, "jpg" | "jpeg") => {
+            let encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut bytes, quality);
+            image::DynamicImage::ImageRgb8(image.to_rgb8())
+                .write_with_encoder(encoder)
+                .map_err(|x| x.to_string())?;
+        }
+        (ResponsiveImageFormat::Original, _) => {
+            image
+                .write_to(&mut Cursor::new(&mut bytes), image::ImageFormat::Png)
+                .map_err(|x| x.to_string())?;
+        }
+    }
+    Ok(bytes)
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// MARKUP
+// ————————————————————————————————————————————————————————————————————————————
+
+/// Rewrites a resolved `<img>` (its `src` already relative to the page) into
+/// a `<picture>` with the generated variants.
+pub fn apply_responsive_image(
+    request: &ResponsiveImageRequest,
+    mut img: Element,
+    options: &ImageOptions,
+    project_context: &ProjectContext,
+    page: &Path,
+    resolved_dependencies: &mut ResolvedDependencies,
+) -> Node {
+    let processed = match process_image(request, options, project_context) {
+        Ok(x) => x,
+        Err(error) => {
+            eprintln!("⚠️ {page:?} failed to process responsive image {:?}: {error}", request.source);
+            return Node::Element(img)
+        }
+    };
+    let src = img.attributes
+        .get("src")
+        .map(|x| split_link_suffix(x.as_str()).0.to_string())
+        .unwrap_or_default();
+    let href_for = |variant: &ImageVariant| -> String {
+        match variant.public.as_ref().and_then(|x| x.file_name()) {
+            Some(file_name) => {
+                Path::new(&src).with_file_name(file_name).to_str().unwrap_or_default().to_string()
+            }
+            None => src.clone(),
+        }
+    };
+    let srcset_for = |format: ResponsiveImageFormat| -> String {
+        let candidates = processed.variants
+            .iter()
+            .filter(|x| x.format == format)
+            .map(|variant| SrcsetCandidate {
+                url: href_for(variant),
+                descriptor: Some(format!("{}w", variant.width)),
+            })
+            .collect::<Vec<_>>();
+        SrcsetCandidate::format_srcset(&candidates)
+    };
+    for variant in processed.variants.iter() {
+        if let Some(public) = variant.public.as_ref() {
+            resolved_dependencies.include_emitted_file(public.clone());
+        }
+    }
+    let attributes = &mut img.attributes;
+    let sizes = attributes
+        .get("sizes")
+        .map(|x| x.as_str().to_string())
+        .unwrap_or_else(|| options.sizes.clone());
+    set_if_absent(attributes, "sizes", sizes.clone());
+    if !attributes.contains_key("width") && !attributes.contains_key("height") {
+        attributes.insert("width", AttributeValueBuf::literal(processed.width.to_string()));
+        attributes.insert("height", AttributeValueBuf::literal(processed.height.to_string()));
+    }
+    set_if_absent(attributes, "loading", String::from("lazy"));
+    if options.formats.contains(&ResponsiveImageFormat::Original) {
+        set_if_absent(attributes, "srcset", srcset_for(ResponsiveImageFormat::Original));
+    }
+    let sources = options.formats
+        .iter()
+        .filter_map(|format| {
+            let mime_type = match format {
+                ResponsiveImageFormat::Avif => "image/avif",
+                ResponsiveImageFormat::Webp => "image/webp",
+                ResponsiveImageFormat::Original => return None,
+            };
+            let source = Element::new("source")
+                .with_attribute("type", AttributeValueBuf::literal(mime_type))
+                .with_attribute("srcset", AttributeValueBuf::literal(srcset_for(*format)))
+                .with_attribute("sizes", AttributeValueBuf::literal(sizes.clone()));
+            Some(Node::Element(source))
+        })
+        .collect::<Vec<_>>();
+    if sources.is_empty() {
+        return Node::Element(img)
+    }
+    let children = sources
+        .into_iter()
+        .chain(std::iter::once(Node::Element(img)))
+        .collect::<Vec<_>>();
+    Node::Element(Element::new("picture").with_children(children))
+}
+
+fn set_if_absent(attributes: &mut AttributeMap, key: &str, value: String) {
+    if !attributes.contains_key(key) {
+        attributes.insert(key, AttributeValueBuf::literal(value));
+    }
+}
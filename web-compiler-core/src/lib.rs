@@ -2,9 +2,15 @@ extern crate web_compiler_xml_ast as xml_ast;
 extern crate web_compiler_macro_types as macro_types;
 extern crate web_compiler_io_types as io_types;
 extern crate web_compiler_css as css;
+extern crate web_compiler_js as js;
 
 pub mod common;
+pub mod error;
 pub mod markup;
 pub mod system;
 pub mod data;
+pub mod check;
+pub mod site;
+pub mod images;
+pub mod stylesheets;
 pub mod xml_ai;
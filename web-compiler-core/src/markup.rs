@@ -3,21 +3,26 @@ pub mod post;
 pub mod macros;
 pub mod rewrites;
 pub mod rewrite_dsl;
+pub mod toc;
+pub mod element_ids;
+pub mod outline;
+pub mod scoped_styles;
+pub mod hoisting;
 
 use std::path::PathBuf;
-use macro_types::lexical_env::{AccumulatedEffects, HostInfo, MacroIO, ProcessScope, SourceHostRef, SourcePathResolver};
+use std::sync::Arc;
+use macro_types::lexical_env::{AccumulatedEffects, HostInfo, IncludeChain, MacroIO, ProcessScope, SourceHostRef, SourcePathResolver};
 use macro_types::breadcrumbs::SiteTreeLayout;
 use macro_types::macro_tag::MacroTagSet;
 use macro_types::project::{FileInput, ProjectContext, ResolvedDependencies};
-use macro_types::scope::BinderValue;
+use macro_types::scope::{BinderValue, LazyBinderValue};
 use macro_types::tag_rewrite_rule::TagRewriteRuleSet;
 use xml_ast::Node;
-use io_types::Effectful;
 
 pub use post::PostProcessor;
 pub use pre::{PreProcessError, PreProcessor};
 
-use web_compiler_types::{CompilationMode, CompilerFeatureset, CompilerRuntime};
+use web_compiler_types::{CompilationMode, CompilerFeatureset, CompilerRuntime, HeadingOptions, ImageOptions};
 
 
 #[derive(Clone)]
@@ -27,6 +32,9 @@ pub struct GlobalPipelineSpec {
     pub rules: TagRewriteRuleSet<CompilerRuntime>,
     pub project: ProjectContext,
     pub global_template: Option<PathBuf>,
+    pub max_include_depth: Option<usize>,
+    pub headings: HeadingOptions,
+    pub images: ImageOptions,
 }
 
 /// Individual soruce file pipeline
@@ -36,7 +44,7 @@ pub struct SourcePipeline {
     pub pipeline_spec: GlobalPipelineSpec,
     pub local_template: Option<PathBuf>,
     pub all_input_rules: Vec<FileInput>,
-    pub site_tree_layout: SiteTreeLayout,
+    pub site_tree_layout: Arc<SiteTreeLayout>,
     pub resolved_dependencies: ResolvedDependencies,
     pub output_writer_mode: OutputWriterMode,
 }
@@ -67,8 +75,10 @@ impl SourcePipeline {
     pub fn source_file_input(&self) -> &FileInput {
         &self.file_input
     }
-    pub fn execute(&mut self) -> Result<Node, ()> {
-        let result = self
+    pub fn execute(&mut self) -> Result<Node, PipelineError> {
+        let profiler = self.pipeline_spec.project.profiler.clone();
+        let _page_scope = profiler.page_scope(self.file_input.source_file());
+        self
             .execute_pre_process_phase()
             .map(|payload| {
                 self.execute_post_process_phase(payload)
@@ -77,16 +87,7 @@ impl SourcePipeline {
                 let _ = effects;
                 self.emit_post_processed_file(&finalized);
                 finalized
-            });
-        match result {
-            Ok(node) => {
-                Ok(node)
-            },
-            Err(error) => {
-                eprintln!("{}", error.to_string());
-                Err(())
-            }
-        }
+            })
     }
 }
 
@@ -98,29 +99,25 @@ impl SourcePipeline {
     fn execute_pre_process_phase(&self) -> Result<MacroIO<Node>, PipelineError> {
         let runtime = self.macro_runtime();
         let pre_processor = PreProcessor::new(runtime);
-        let breadcrumb_path_value = self.site_tree_layout.lookup_for(&self.file_input);
-        let (initial_effects, breadcrumb_path_value) = match breadcrumb_path_value {
-            Some(breadcrumb_path) => {
-                let breadcrumb_io =
-                    crate::data::breadcrumbs::to_breadcrumb_value_path(
-                        breadcrumb_path,
-                        &self.pipeline_spec.project,
-                    );
-                let (breadcrumb_path_value, effects) = breadcrumb_io.collapse();
-                let _ = effects; // TODO: CONSIDER THIS
-                // eprintln!(" » {:?} → {breadcrumb_path_value:#?}:\n » {effects:?}", self.file_input.source.as_path());
-                (effects, Some(breadcrumb_path_value))
-            }
-            None => {
-                ( AccumulatedEffects::default(), None )
-            },
+        let breadcrumb_path_value = self.site_tree_layout
+            .lookup_for(&self.file_input)
+            .map(|x| crate::data::breadcrumbs::to_breadcrumb_value_path(&x));
+        let navigation = {
+            let site_tree_layout = self.site_tree_layout.clone();
+            let file_input = self.file_input.clone();
+            LazyBinderValue::new(move || {
+                BinderValue::json(crate::data::navigation::to_navigation_value(&site_tree_layout, &file_input))
+            })
         };
         let content = {
+            let _span = self.pipeline_spec.project.profiler.phase("pre-process");
             let host_info = HostInfo::new(
                 breadcrumb_path_value.clone(),
                 Default::default(),
-            );
-            let mut env = ProcessScope::new(host_info);
+            )
+            .with_navigation(Some(navigation.clone()));
+            let mut env = ProcessScope::new(host_info)
+                .with_include_chain(self.include_chain_root(self.file_input.source_file()));
             match pre_processor.load_compile(&mut env) {
                 Ok(x) => x,
                 Err(error) => {
@@ -129,9 +126,6 @@ impl SourcePipeline {
                 }
             }
         };
-        let content = content.and_modify_context(|ctx| {
-            ctx.extend(initial_effects);
-        });
         let template_path = self.local_template
             .as_ref()
             .or_else(|| self.pipeline_spec.global_template.as_ref());
@@ -139,17 +133,22 @@ impl SourcePipeline {
             source: path.to_path_buf(),
             public: None,
         });
+        let template_chain = template_input
+            .as_ref()
+            .map(|x| self.include_chain_root(x.source_file()))
+            .unwrap_or_default();
         let mut pre_processor = pre_processor;
         if let Some(template_input) = template_input {
             pre_processor = pre_processor.fork(&template_input);
         }
         let finale = content
             .and_then_with_context(| content, ctx| {
-                let host_info = HostInfo::new(
-                    breadcrumb_path_value,
-                    ctx.chained_state().hoisted().to_owned()
-                );
+                let _span = self.pipeline_spec.project.profiler.phase("template application");
+                let host_info = HostInfo::new(breadcrumb_path_value, Default::default())
+                    .with_navigation(Some(navigation))
+                    .with_chained_state(ctx.chained_state());
                 let mut env = ProcessScope::new(host_info)
+                    .with_include_chain(template_chain)
                     .and_insert_binder_value("content", BinderValue::node(content.clone()));
                 // - -
                 match pre_processor.load_compile(&mut env) {
@@ -164,8 +163,25 @@ impl SourcePipeline {
         // return Ok(content)
         return Ok(finale)
     }
+    fn include_chain_root(&self, file: &std::path::Path) -> IncludeChain {
+        let max_depth = self.pipeline_spec.max_include_depth.unwrap_or(IncludeChain::DEFAULT_MAX_DEPTH);
+        IncludeChain::root(file).with_max_depth(max_depth)
+    }
     fn execute_post_process_phase(&mut self, processed: MacroIO<Node>) -> (Node, AccumulatedEffects) {
+        let _span = self.pipeline_spec.project.profiler.phase("post-process");
         let ( processed, effects ) = processed.collapse();
+        let source_path = self.file_input.source_file();
+        let headings = &self.pipeline_spec.headings;
+        for error in effects.stylesheet_errors.iter() {
+            eprintln!("⚠️ {error}");
+        }
+        let processed = hoisting::place_hoisted_elements(processed, &effects);
+        let processed = outline::apply_section_baselines(processed, source_path);
+        outline::DocumentOutline::compute(&processed).report(source_path);
+        let processed = element_ids::apply_heading_ids(processed, headings);
+        let processed = toc::apply_table_of_contents(processed, source_path);
+        let processed = element_ids::apply_heading_anchors(processed, headings);
+        let processed = element_ids::check_unique_ids(processed, source_path, &effects.include_chains);
         let dependencies = effects.dependencies
             .clone()
             .into_iter()
@@ -184,13 +200,21 @@ impl SourcePipeline {
             path_resolver,
             &mut resolved_dependencies,
             self.source_context().clone(),
+            &self.pipeline_spec.images,
         );
         let finalized = post_processor.apply(processed);
+        resolved_dependencies.link_references.extend(crate::check::stylesheet_link_references(
+            self.file_input.source_file(),
+            &effects.stylesheet_urls,
+            &self.pipeline_spec.project,
+        ));
+        crate::check::attach_include_chains(&mut resolved_dependencies.link_references, &effects.include_chains);
         // println!("path_resolver: {path_resolver:#?}");
         self.resolved_dependencies.extend(resolved_dependencies);
         ( finalized, effects )
     }
     fn emit_post_processed_file(&mut self, node: &Node) {
+        let _span = self.pipeline_spec.project.profiler.phase("page emission");
         // let html_string = node.format_document();
         let resolved_public_path = self.file_input.resolved_public_path(&self.pipeline_spec.project);
         if self.output_writer_mode == OutputWriterMode::WriteFile {
@@ -198,6 +222,7 @@ impl SourcePipeline {
             let output_path = self.file_input.to_output_file_path(&self.pipeline_spec.project);
             crate::common::path_utils::write_output_file_smart(&output_path, html_string.as_bytes());
         }
+        self.resolved_dependencies.element_ids.entry(path_clean::clean(&resolved_public_path)).or_default();
         self.resolved_dependencies.emitted_files.insert(resolved_public_path);
     }
 }
@@ -0,0 +1,153 @@
+//! Page-level `id` handling, run once per page after pre-processing (so
+//! includes are expanded and heading levels are final):
+//!
+//! - optional slug ids for every heading (`[headings] ids = true`),
+//! - optional self-link anchors inside headings (`[headings] anchors = true`),
+//! - a check that every `id` is unique within the page.
+//!
+//! To report where a duplicate came from, the pre-processor marks elements
+//! that carry an `id` with the file they were written in ([`mark_origin`]);
+//! the marker is removed again by [`check_unique_ids`].
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use xml_ast::traversal::ElementVisitor;
+use xml_ast::{AttributeMap, AttributeValueBuf, Element, Fragment, Node, TagBuf};
+
+use web_compiler_types::HeadingOptions;
+
+use super::rewrites::headings::HeadingLevel;
+use super::toc::{heading_text, SlugRegistry};
+
+/// Internal attribute recording the source file an element was written in.
+pub const ORIGIN_ATTRIBUTE: &'static str = "data-web-compiler-origin";
+
+pub const ANCHOR_CLASS: &'static str = "heading-anchor";
+
+/// Records `source_file` as the origin of elements with an `id`.
+pub fn mark_origin(attributes: &mut AttributeMap, source_file: &Path) {
+    if attributes.contains_key("id") && !attributes.contains_key(ORIGIN_ATTRIBUTE) {
+        let origin = source_file.to_str().unwrap_or_default().to_string();
+        attributes.insert(ORIGIN_ATTRIBUTE, AttributeValueBuf::literal(origin));
+    }
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// HEADING IDS
+// ————————————————————————————————————————————————————————————————————————————
+
+struct HeadingIds {
+    slugs: SlugRegistry,
+}
+
+impl ElementVisitor for HeadingIds {
+    fn visit_element(&mut self, tag: TagBuf, mut attributes: AttributeMap, children: Fragment) -> Node {
+        if HeadingLevel::try_from_tag(&tag).is_ok() && !attributes.contains_key("id") {
+            let element = Element { tag, attributes: AttributeMap::default(), children };
+            let id = self.slugs.claim(&heading_text(&element));
+            attributes.insert("id", AttributeValueBuf::literal(id));
+            return Node::Element(Element { attributes, ..element })
+        }
+        Node::Element(Element { tag, attributes, children })
+    }
+}
+
+/// Gives every heading without an `id` a slug of its text (unique within the page).
+pub fn apply_heading_ids(page: Node, options: &HeadingOptions) -> Node {
+    if !options.auto_ids {
+        return page
+    }
+    let mut visitor = HeadingIds { slugs: SlugRegistry::from_existing_ids(&page) };
+    xml_ast::traversal::apply_element_visitor(page, &mut visitor)
+}
+
+struct HeadingAnchors;
+
+impl ElementVisitor for HeadingAnchors {
+    fn visit_element(&mut self, tag: TagBuf, attributes: AttributeMap, mut children: Fragment) -> Node {
+        let id = attributes
+            .get("id")
+            .filter(|_| HeadingLevel::try_from_tag(&tag).is_ok())
+            .map(|x| x.as_str().to_string());
+        if let Some(id) = id {
+            let anchor = Element::new("a")
+                .with_attribute("class", AttributeValueBuf::literal(ANCHOR_CLASS))
+                .with_attribute("href", AttributeValueBuf::literal(format!("#{id}")))
+                .with_attribute("aria-hidden", AttributeValueBuf::literal("true"))
+                .with_children(vec![Node::text("#")]);
+            children.push(Node::Element(anchor));
+        }
+        Node::Element(Element { tag, attributes, children })
+    }
+}
+
+/// Appends `<a class="heading-anchor" href="#id">#</a>` to every heading with an `id`.
+///
+/// Runs after `<toc>` so the anchor text is not part of the listed titles.
+pub fn apply_heading_anchors(page: Node, options: &HeadingOptions) -> Node {
+    if !options.anchors {
+        return page
+    }
+    xml_ast::traversal::apply_element_visitor(page, &mut HeadingAnchors)
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// UNIQUENESS
+// ————————————————————————————————————————————————————————————————————————————
+
+struct IdCollector<'a> {
+    page: &'a Path,
+    /// `id` → origin of every element using it, in document order.
+    ids: HashMap<String, Vec<PathBuf>>,
+    order: Vec<String>,
+}
+
+impl<'a> ElementVisitor for IdCollector<'a> {
+    fn visit_element(&mut self, tag: TagBuf, mut attributes: AttributeMap, children: Fragment) -> Node {
+        let origin = attributes
+            .remove(ORIGIN_ATTRIBUTE)
+            .map(|x| PathBuf::from(x.as_str()))
+            .unwrap_or_else(|| self.page.to_path_buf());
+        if let Some(id) = attributes.get("id") {
+            let id = id.as_str().to_string();
+            let origins = self.ids.entry(id.clone()).or_default();
+            if origins.is_empty() {
+                self.order.push(id);
+            }
+            origins.push(origin);
+        }
+        Node::Element(Element { tag, attributes, children })
+    }
+}
+
+/// Reports every `id` used more than once in the page, with the file (and
+/// include chain) each occurrence came from, and strips the origin markers.
+pub fn check_unique_ids(
+    page: Node,
+    source_path: &Path,
+    include_chains: &BTreeMap<PathBuf, Vec<PathBuf>>,
+) -> Node {
+    let mut collector = IdCollector { page: source_path, ids: HashMap::default(), order: Vec::new() };
+    let page = xml_ast::traversal::apply_element_visitor(page, &mut collector);
+    for id in collector.order.iter() {
+        let origins = &collector.ids[id];
+        if origins.len() < 2 {
+            continue;
+        }
+        eprintln!("⚠️ {source_path:?} duplicate id {id:?} ({} elements)", origins.len());
+        for origin in origins {
+            match include_chains.get(&path_clean::clean(origin)) {
+                Some(chain) if chain.len() > 1 => {
+                    let chain = chain
+                        .iter()
+                        .map(|x| x.display().to_string())
+                        .collect::<Vec<_>>()
+                        .join(" → ");
+                    eprintln!("    via {chain}");
+                }
+                _ => eprintln!("    in {origin:?}"),
+            }
+        }
+    }
+    page
+}
@@ -0,0 +1,320 @@
+//! `<style>`, `<link rel="stylesheet">` and `<script>` in included fragments.
+//!
+//! Once an `<include>` is expanded (and its `<style scoped>` scoped), the
+//! fragment's styles and stylesheet links are hoisted like `<hoist>`ed
+//! markup, and its external scripts (and inline ones marked `hoist`) into
+//! `AccumulatedEffects::hoisted_scripts`. Import maps, data blocks and other
+//! inline scripts stay where they are.
+//! `<inject hoisted>` emits the former and `<inject hoisted="scripts">` the
+//! latter, each element once: inline ones are told apart by their content,
+//! linked ones by the URL they resolve to.
+//!
+//! After pre-processing, whatever the template didn't inject is appended to
+//! the page's `<head>` (styles) or the end of its `<body>` (scripts), and
+//! repeats of the hoisted elements are dropped across the whole page.
+//! Elements written directly in the page or template are left alone.
+//!
+//! Elements marked `in-place`, and anything inside `<template>` or
+//! `<noscript>`, are left where they are.
+use std::collections::HashSet;
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use macro_types::lexical_env::{AccumulatedEffects, MacroIO};
+use macro_types::project::DependencyRelation;
+use macro_types::scope::{BinderValue, MarkupBinderValue};
+use xml_ast::{Element, Fragment, Node};
+
+use super::scoped_styles::{SCOPED_ATTRIBUTE, SCOPE_MARKER_ATTRIBUTE};
+
+/// Keeps a style, stylesheet link or script at its position in the fragment.
+pub const IN_PLACE_ATTRIBUTE: &'static str = "in-place";
+
+/// Hoists an inline classic or module script, which otherwise stays in place.
+pub const HOIST_ATTRIBUTE: &'static str = "hoist";
+
+/// `<inject hoisted="scripts">` emits the hoisted scripts.
+pub const SCRIPTS_INJECTION: &'static str = "scripts";
+
+/// Elements whose contents are inert and never hoisted or deduplicated.
+const INERT_TAGS: &[&str] = &["template", "noscript"];
+
+/// `type`s of classic scripts besides none or an empty one (see
+/// <https://mimesniff.spec.whatwg.org/#javascript-mime-type>).
+const JAVASCRIPT_MIME_TYPES: &[&str] = &[
+    "application/ecmascript",
+    "application/javascript",
+    "application/x-ecmascript",
+    "application/x-javascript",
+    "text/ecmascript",
+    "text/javascript",
+    "text/javascript1.0",
+    "text/javascript1.1",
+    "text/javascript1.2",
+    "text/javascript1.3",
+    "text/javascript1.4",
+    "text/javascript1.5",
+    "text/jscript",
+    "text/livescript",
+    "text/x-ecmascript",
+    "text/x-javascript",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Placement {
+    Head,
+    Body,
+}
+
+fn placement(element: &Element) -> Option<Placement> {
+    if element.attributes.contains_key(IN_PLACE_ATTRIBUTE) {
+        return None
+    }
+    match element.tag.as_normalized() {
+        "style" if !element.attributes.contains_key(SCOPED_ATTRIBUTE) => Some(Placement::Head),
+        "link" if is_stylesheet_link(element) => Some(Placement::Head),
+        "script" if is_hoistable_script(element) => Some(Placement::Body),
+        _ => None,
+    }
+}
+
+/// Classic and module scripts with a `src`, or marked `hoist`. Their
+/// position only matters relative to each other, unlike inline scripts
+/// (`document.currentScript`, `document.write`), and import maps and data
+/// blocks (`application/ld+json`, `text/template`) aren't run at all.
+fn is_hoistable_script(element: &Element) -> bool {
+    let is_executable = match element.attributes.get("type") {
+        None => true,
+        Some(kind) => {
+            let kind = kind.as_str().trim().to_ascii_lowercase();
+            kind.is_empty() || kind == "module" || JAVASCRIPT_MIME_TYPES.contains(&kind.as_str())
+        }
+    };
+    let is_linked = element.attributes.contains_key("src") || element.attributes.contains_key(HOIST_ATTRIBUTE);
+    is_executable && is_linked
+}
+
+fn is_stylesheet_link(element: &Element) -> bool {
+    element.attributes
+        .get("rel")
+        .is_some_and(|x| x.as_str().split_ascii_whitespace().any(|x| x.eq_ignore_ascii_case("stylesheet")))
+}
+
+/// Identifies a style, stylesheet link or script regardless of where it was
+/// written: scoped styles by component, inline elements by content, linked
+/// ones by the URL they resolve to.
+pub fn hoist_key(node: &Node) -> Option<String> {
+    node.as_element().and_then(element_key)
+}
+
+fn element_key(element: &Element) -> Option<String> {
+    let media = element.attributes.get("media").map(|x| x.as_str()).unwrap_or_default();
+    match element.tag.as_normalized() {
+        "style" => match element.attributes.get(SCOPE_MARKER_ATTRIBUTE) {
+            Some(scope) => Some(format!("style[scope={}]", scope.as_str())),
+            None => Some(format!("style[media={media}]:{}", content_hash(element))),
+        },
+        "link" if is_stylesheet_link(element) => {
+            let href = element.attributes.get("href")?;
+            Some(format!("link[media={media}]:{}", resolved_url(href.as_str())))
+        }
+        "script" => {
+            let kind = element.attributes.get("type").map(|x| x.as_str()).unwrap_or_default();
+            match element.attributes.get("src") {
+                Some(src) => Some(format!("script[type={kind}]:{}", resolved_url(src.as_str()))),
+                None => Some(format!("script[type={kind}]:{}", content_hash(element))),
+            }
+        }
+        _ => None,
+    }
+}
+
+fn content_hash(element: &Element) -> String {
+    let mut hasher = DefaultHasher::new();
+    element.children.text_contents().join("").trim().hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+/// Attribute paths are virtual (`from|to`) until post-processing; two
+/// fragments in different directories may link the same file.
+fn resolved_url(href: &str) -> String {
+    let href = href.trim();
+    match DependencyRelation::decode(href) {
+        Some(relation) if relation.is_external_target() => relation.to,
+        Some(relation) => {
+            let target = path_clean::clean(relation.as_file_dependency().resolved_target_path());
+            target.to_string_lossy().into_owned()
+        }
+        None => href.to_string(),
+    }
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// COLLECTION
+// ————————————————————————————————————————————————————————————————————————————
+
+#[derive(Default)]
+struct HoistedElements {
+    head: Vec<Node>,
+    body: Vec<Node>,
+}
+
+impl HoistedElements {
+    fn extract(&mut self, node: Node) -> Node {
+        match node {
+            Node::Element(element) => match placement(&element) {
+                Some(Placement::Head) => {
+                    self.head.push(Node::Element(element));
+                    Node::empty()
+                }
+                Some(Placement::Body) => {
+                    self.body.push(Node::Element(element));
+                    Node::empty()
+                }
+                None if INERT_TAGS.contains(&element.tag.as_normalized()) => Node::Element(element),
+                None => {
+                    let Element { tag, attributes, children } = element;
+                    let children = children.into_iter().map(|x| self.extract(x)).collect();
+                    Node::Element(Element { tag, attributes, children })
+                }
+            },
+            Node::Fragment(fragment) => {
+                Node::Fragment(fragment.into_iter().map(|x| self.extract(x)).collect())
+            }
+            text => text,
+        }
+    }
+}
+
+/// Takes the styles, stylesheet links and scripts out of an expanded
+/// fragment and hoists them.
+pub fn hoist_fragment_elements(node: Node) -> MacroIO<Node> {
+    let mut hoisted = HoistedElements::default();
+    let node = hoisted.extract(node);
+    if hoisted.head.is_empty() && hoisted.body.is_empty() {
+        return MacroIO::wrap(node)
+    }
+    MacroIO::wrap(node).and_modify_context(|ctx| {
+        let markup = |x| BinderValue::Markup(MarkupBinderValue(x));
+        ctx.hoisted.extend(hoisted.head.into_iter().map(markup));
+        ctx.hoisted_scripts.extend(hoisted.body.into_iter().map(markup));
+    })
+}
+
+/// The hoisted nodes, with repeated styles, stylesheet links and scripts
+/// dropped (keeping the first). Other hoisted markup passes through.
+pub fn deduplicate_hoisted(hoisted: &[BinderValue]) -> Vec<Node> {
+    let mut seen = HashSet::new();
+    hoisted
+        .iter()
+        .filter_map(|x| x.as_node())
+        .flat_map(|x| x.clone().flatten())
+        .filter(|x| hoist_key(x).is_none_or(|key| seen.insert(key)))
+        .filter(|x| x.as_element().is_some())
+        .collect()
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// PAGE PLACEMENT
+// ————————————————————————————————————————————————————————————————————————————
+
+/// Places the hoisted styles and scripts the template didn't inject, drops
+/// repeats of them and strips the `in-place` and `hoist` markers.
+pub fn place_hoisted_elements(page: Node, effects: &AccumulatedEffects) -> Node {
+    let mut present = HashSet::new();
+    collect_keys(&page, &mut present);
+    let hoisted_keys = effects.hoisted
+        .iter()
+        .chain(effects.hoisted_scripts.iter())
+        .filter_map(|x| x.as_node())
+        .flat_map(|x| x.clone().flatten())
+        .filter(|x| x.as_element().and_then(placement).is_some())
+        .filter_map(|x| hoist_key(&x))
+        .collect::<HashSet<_>>();
+    let mut missing = |hoisted: &[BinderValue]| {
+        deduplicate_hoisted(hoisted)
+            .into_iter()
+            .filter(|x| {
+                let is_hoistable = x.as_element().and_then(placement).is_some();
+                is_hoistable && hoist_key(x).is_some_and(|key| present.insert(key))
+            })
+            .map(|mut x| {
+                if let Node::Element(element) = &mut x {
+                    remove_hoist_marker(element);
+                }
+                x
+            })
+            .collect::<Vec<_>>()
+    };
+    let mut placer = PagePlacer {
+        head: missing(&effects.hoisted),
+        body: missing(&effects.hoisted_scripts),
+        hoisted_keys,
+        seen: HashSet::new(),
+    };
+    let page = placer.place(page);
+    let mut nodes = std::mem::take(&mut placer.head);
+    nodes.extend(page.flatten());
+    nodes.extend(std::mem::take(&mut placer.body));
+    Node::Fragment(Fragment::from_nodes(nodes))
+}
+
+fn remove_hoist_marker(element: &mut Element) {
+    if element.tag.as_normalized() == "script" {
+        element.attributes.remove(HOIST_ATTRIBUTE);
+    }
+}
+
+fn collect_keys(node: &Node, keys: &mut HashSet<String>) {
+    match node {
+        Node::Element(element) if INERT_TAGS.contains(&element.tag.as_normalized()) => (),
+        Node::Element(element) => {
+            if let Some(key) = hoist_key(node) {
+                keys.insert(key);
+            }
+            element.children.iter().for_each(|x| collect_keys(x, keys));
+        }
+        Node::Fragment(fragment) => fragment.iter().for_each(|x| collect_keys(x, keys)),
+        Node::Text(_) => (),
+    }
+}
+
+/// Appends `head` to `<head>` and `body` to `<body>`; what remains unplaced
+/// (pages without them) goes to the start or end of the page.
+struct PagePlacer {
+    head: Vec<Node>,
+    body: Vec<Node>,
+    /// Keys of the elements hoisted out of fragments, the only ones whose
+    /// repeats are dropped.
+    hoisted_keys: HashSet<String>,
+    seen: HashSet<String>,
+}
+
+impl PagePlacer {
+    fn place(&mut self, node: Node) -> Node {
+        match node {
+            Node::Element(mut element) => {
+                let in_place = element.attributes.remove(IN_PLACE_ATTRIBUTE).is_some();
+                if INERT_TAGS.contains(&element.tag.as_normalized()) {
+                    return Node::Element(element)
+                }
+                remove_hoist_marker(&mut element);
+                let is_repeated = element_key(&element)
+                    .filter(|key| self.hoisted_keys.contains(key))
+                    .is_some_and(|key| !self.seen.insert(key));
+                if is_repeated && !in_place {
+                    return Node::empty()
+                }
+                let Element { tag, attributes, children } = element;
+                let mut children = children.into_iter().map(|x| self.place(x)).collect::<Fragment>();
+                match tag.as_normalized() {
+                    "head" => children.extend(std::mem::take(&mut self.head)),
+                    "body" => children.extend(std::mem::take(&mut self.body)),
+                    _ => (),
+                }
+                Node::Element(Element { tag, attributes, children })
+            }
+            Node::Fragment(fragment) => Node::Fragment(fragment.into_iter().map(|x| self.place(x)).collect()),
+            text => text,
+        }
+    }
+}
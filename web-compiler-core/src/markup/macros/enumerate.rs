@@ -91,16 +91,22 @@ impl MacroTag for EnumerateMacroTag {
         let binder_key = attributes.get("path");
         let binder_value = binder_key
             .and_then(|target| {
-                let result = scope.binding_scope.lookup(target.as_str());
-                match result {
-                    Some(x) => Some(x.to_owned()),
-                    None => {
+                let path_expr = match macro_types::path_expr::PathExpression::parse(target.as_str()) {
+                    Ok(x) => x,
+                    Err(error) => {
                         runtime.with_source_file_path(|file| {
-                            eprintln!("⚠️ {file:?} <enumerate> failed to resolve binding for {target:?}\n\t{:?}", scope.binding_scope);
+                            eprintln!("⚠️ {file:?} <enumerate> failed to parse path expression `{}`: {error}", target.as_str());
                         });
-                        None
+                        return None
                     }
+                };
+                let result = path_expr.evaluate(&scope.binding_scope);
+                if result.is_none() {
+                    runtime.with_source_file_path(|file| {
+                        eprintln!("⚠️ {file:?} <enumerate> failed to resolve binding for {target:?}\n\t{:?}", scope.binding_scope);
+                    });
                 }
+                result
             });
         let as_ident = match attributes.get("as") {
             Some(x) => x,
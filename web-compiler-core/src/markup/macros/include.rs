@@ -1,5 +1,8 @@
+use std::collections::BTreeMap;
+
 use macro_types::macro_tag::MacroTag;
-use macro_types::lexical_env::{MacroIO, SourceHost};
+use macro_types::lexical_env::{MacroIO, ProcessScope, SourceHost};
+use macro_types::path_expr::PathExpression;
 use macro_types::project::FileInput;
 use macro_types::scope::{BinderValue, JsonBinderValue};
 use xml_ast::Node;
@@ -7,10 +10,18 @@ use xml_ast::Node;
 use web_compiler_types::CompilerRuntime;
 
 use super::super::pre::{PreProcessError, PreProcessor};
+use super::params::IncludeContract;
 
 #[derive(Debug, Clone, Copy, Default)]
 pub struct IncludeMacroTag;
 
+impl IncludeMacroTag {
+    /// Prefix for explicitly passed bindings, e.g. `bind:title="page.title"`.
+    pub const BIND_PREFIX: &'static str = "bind:";
+    /// Attributes consumed by `<include>` itself rather than passed as `host`.
+    pub const RESERVED_ATTRIBUTES: &'static [&'static str] = &["src", "hygienic", "recursive"];
+}
+
 impl MacroTag for IncludeMacroTag {
     type Runtime = CompilerRuntime;
     fn tag_name(&self) -> &'static str { "include" }
@@ -22,7 +33,6 @@ impl MacroTag for IncludeMacroTag {
         runtime: &Self::Runtime,
     ) -> MacroIO<xml_ast::Node> {
         let mut child_scope = scope.to_owned();
-        let mut embedded_scope = scope.to_owned();
         let pre_processor = PreProcessor::new(runtime.clone());
         pre_processor
             .process_sequence(children.to_vec(), &mut child_scope)
@@ -30,10 +40,13 @@ impl MacroTag for IncludeMacroTag {
                 if let Some(src_value) = attributes.get("src").cloned() {
                     let dependency = runtime.source_context().file_input().with_dependency_relation(src_value.as_str());
                     // - SCOPE -
-                    let host_object = attributes
+                    let host_attributes = attributes
                         .clone()
                         .into_iter()
-                        .filter(|(key, _)| key.as_str() != "src")
+                        .filter(|(key, _)| {
+                            let key = key.as_str();
+                            !Self::RESERVED_ATTRIBUTES.contains(&key) && !key.starts_with(Self::BIND_PREFIX)
+                        })
                         .map(|(key, value)| {
                             let mut value = value.as_str().to_string();
                             crate::markup::rewrites::attributes::resolve_string_expression(
@@ -41,23 +54,39 @@ impl MacroTag for IncludeMacroTag {
                                 scope,
                                 runtime
                             );
-                            (key.as_str().to_owned(), JsonBinderValue::json_string(value))
+                            (key.as_str().to_owned(), value)
                         })
+                        .collect::<BTreeMap<_, _>>();
+                    let host_object = host_attributes
+                        .iter()
+                        .map(|(key, value)| (key.to_owned(), JsonBinderValue::json_string(value)))
                         .collect::<Vec<_>>();
                     let host_object = BinderValue::object(host_object);
-                    embedded_scope.binding_scope.insert("content", BinderValue::fragment(children));
-                    embedded_scope.binding_scope.insert("host", host_object);
-                    // - LOAD - 
+                    let mut bindings = resolve_explicit_bindings(&attributes, scope, runtime);
+                    // - LOAD -
                     let embedded_path = runtime.source_context().file_input().source_dir().join(src_value.as_str());
                     let new_input_file = FileInput {
                         source: embedded_path.clone(),
                         public: None,
                     };
+                    let caller_runtime = runtime;
+                    let include_chain = scope.include_chain.enter(&embedded_path, attributes.contains_key("recursive"));
+                    let include_chain = match include_chain {
+                        Ok(x) => x,
+                        Err(error) => {
+                            caller_runtime.with_source_file_path(|file| {
+                                eprintln!("⚠️ {file:?} <include src={:?}>: {error}", src_value.as_str());
+                            });
+                            return MacroIO::wrap(Node::empty()).and_modify_context(|ctx| {
+                                ctx.dependencies.insert(dependency);
+                            })
+                        }
+                    };
                     let runtime = runtime.fork(&new_input_file);
                     let pre_processor = PreProcessor::new(runtime.clone());
-                    return pre_processor
-                        .load_compile(&mut embedded_scope)
-                        .unwrap_or_else(|error| {
+                    let source_tree = match pre_processor.load_source_tree() {
+                        Ok(x) => x,
+                        Err(error) => {
                             let source_context = runtime.source_context();
                             let source_path = source_context.file_input().source.as_path();
                             match error {
@@ -72,13 +101,87 @@ impl MacroTag for IncludeMacroTag {
                                     }
                                 }
                             }
-                            MacroIO::wrap(Node::empty())
-                        })
+                            return MacroIO::wrap(Node::empty()).and_modify_context(|ctx| {
+                                ctx.dependencies.insert(dependency);
+                            })
+                        }
+                    };
+                    // - CONTRACT -
+                    let contract = IncludeContract::from_source_tree(&source_tree);
+                    if let Some(contract) = contract.as_ref() {
+                        let violations = contract.check_call_site(&host_attributes, &mut bindings);
+                        caller_runtime.with_source_file_path(|file| {
+                            for violation in violations {
+                                eprintln!("⚠️ {file:?} <include src={:?}>: {violation}", src_value.as_str());
+                            }
+                        });
+                    }
+                    let hygienic = attributes.contains_key("hygienic") || contract
+                        .as_ref()
+                        .map(|x| x.hygienic)
+                        .unwrap_or(false);
+                    let mut embedded_scope = if hygienic {
+                        scope.hygienic()
+                    } else {
+                        scope.to_owned()
+                    };
+                    let chain_files = include_chain.files().to_vec();
+                    embedded_scope.include_chain = include_chain;
+                    embedded_scope.binding_scope.insert("content", BinderValue::fragment(children));
+                    embedded_scope.binding_scope.insert("host", host_object);
+                    for (key, value) in bindings {
+                        embedded_scope.binding_scope.insert(key, value);
+                    }
+                    return pre_processor
+                        .compile_source_tree(source_tree, &mut embedded_scope)
+                        .and_then(|node| crate::markup::scoped_styles::apply_scoped_styles(node, &embedded_path, runtime.source_context().project_context()))
+                        .and_then(crate::markup::hoisting::hoist_fragment_elements)
                         .and_modify_context(|ctx| {
                             ctx.dependencies.insert(dependency);
+                            ctx.include_chains
+                                .entry(path_clean::clean(&embedded_path))
+                                .or_insert(chain_files);
                         })
                 }
                 MacroIO::wrap(Node::empty())
             })
     }
 }
+
+/// Evaluates every `bind:name="path.expr"` attribute against the caller's scope.
+fn resolve_explicit_bindings(
+    attributes: &xml_ast::AttributeMap,
+    scope: &ProcessScope,
+    runtime: &CompilerRuntime,
+) -> BTreeMap<String, BinderValue> {
+    attributes
+        .iter()
+        .filter_map(|(key, value)| {
+            let name = key.as_str().strip_prefix(IncludeMacroTag::BIND_PREFIX)?;
+            let target = value.as_str().trim();
+            let target = target
+                .strip_prefix("{{")
+                .and_then(|x| x.strip_suffix("}}"))
+                .unwrap_or(target)
+                .trim();
+            let path_expr = match PathExpression::parse(target) {
+                Ok(x) => x,
+                Err(error) => {
+                    runtime.with_source_file_path(|file| {
+                        eprintln!("⚠️ {file:?} <include> failed to parse `{}` path expression `{target}`: {error}", key.as_str());
+                    });
+                    return None
+                }
+            };
+            match path_expr.evaluate(&scope.binding_scope) {
+                Some(value) => Some((name.to_string(), value)),
+                None => {
+                    runtime.with_source_file_path(|file| {
+                        eprintln!("⚠️ {file:?} <include> failed to resolve `{}` binding `{target}`", key.as_str());
+                    });
+                    None
+                }
+            }
+        })
+        .collect()
+}
@@ -5,6 +5,8 @@ use xml_ast::{Fragment, Node};
 
 use web_compiler_types::CompilerRuntime;
 
+use crate::markup::hoisting::{deduplicate_hoisted, SCRIPTS_INJECTION};
+
 #[derive(Debug, Clone, Copy, Default)]
 pub struct InjectMacroTag;
 
@@ -18,16 +20,12 @@ impl MacroTag for InjectMacroTag {
         scope: &mut macro_types::lexical_env::ProcessScope,
         runtime: &Self::Runtime,
     ) -> MacroIO<xml_ast::Node> {
-        if attributes.contains_key("hoisted") {
-            let nodes = scope
-                .host_info()
-                .hoisted()
-                .into_iter()
-                .filter_map(|x| x.as_node())
-                .flat_map(|x| x.clone().flatten())
-                .filter_map(|x| x.to_element())
-                .map(|x| Node::Element(x))
-                .collect::<Vec<_>>();
+        if let Some(hoisted) = attributes.get("hoisted") {
+            let hoisted = match hoisted.as_str() {
+                SCRIPTS_INJECTION => scope.host_info().hoisted_scripts(),
+                _ => scope.host_info().hoisted(),
+            };
+            let nodes = deduplicate_hoisted(hoisted);
             return MacroIO::wrap(Node::Fragment(Fragment::from_nodes(nodes)))
         }
         if attributes.contains_key("self") {
@@ -41,7 +39,15 @@ impl MacroTag for InjectMacroTag {
         let injection = attributes
             .get("path")
             .and_then(|target| {
-                let path_expr = macro_types::path_expr::PathExpression::parse(target.as_str()).unwrap();
+                let path_expr = match macro_types::path_expr::PathExpression::parse(target.as_str()) {
+                    Ok(x) => x,
+                    Err(error) => {
+                        runtime.with_source_file_path(|file| {
+                            eprintln!("⚠️ {file:?} `<inject>` failed to parse path expression `{}`: {error}", target.as_str());
+                        });
+                        return None
+                    }
+                };
                 let path_value = path_expr.evaluate(&scope.binding_scope);
                 if path_value.is_none() {
                     runtime.with_source_file_path(|file| {
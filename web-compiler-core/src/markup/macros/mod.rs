@@ -10,6 +10,8 @@ mod define;
 mod hoist;
 mod define_title;
 mod rewrite;
+mod params;
+mod page_meta;
 
 use std::rc::Rc;
 
@@ -25,6 +27,8 @@ pub use define::*;
 pub use hoist::*;
 pub use define_title::*;
 pub use rewrite::*;
+pub use params::*;
+pub use page_meta::*;
 
 use macro_types::macro_tag::{MacroTag, MacroTagSet};
 
@@ -43,6 +47,8 @@ pub fn standard_macro_tags() -> Vec<Rc<dyn MacroTag<Runtime = CompilerRuntime>>>
         Rc::new(DefineMacroTag),
         Rc::new(HoistMacroTag),
         Rc::new(DefineTitleMacroTag),
+        Rc::new(ParamsMacroTag),
+        Rc::new(PageMetaMacroTag),
         // Rc::new(RewriteRuleMacroTag),
     ]
 }
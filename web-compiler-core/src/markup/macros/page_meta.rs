@@ -0,0 +1,25 @@
+use macro_types::macro_tag::MacroTag;
+use macro_types::lexical_env::MacroIO;
+use macro_types::page_meta::PageMetadata;
+use xml_ast::Node;
+
+use web_compiler_types::CompilerRuntime;
+
+/// Page metadata is read statically (see [`PageMetadata`]); the element itself
+/// renders to nothing.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PageMetaMacroTag;
+
+impl MacroTag for PageMetaMacroTag {
+    type Runtime = CompilerRuntime;
+    fn tag_name(&self) -> &'static str { PageMetadata::TAG_NAME }
+    fn apply(
+        &self,
+        _: xml_ast::AttributeMap,
+        _: xml_ast::Fragment,
+        _: &mut macro_types::lexical_env::ProcessScope,
+        _: &Self::Runtime,
+    ) -> MacroIO<xml_ast::Node> {
+        MacroIO::wrap(Node::empty())
+    }
+}
@@ -0,0 +1,228 @@
+use std::collections::BTreeMap;
+
+use macro_types::macro_tag::MacroTag;
+use macro_types::lexical_env::MacroIO;
+use macro_types::scope::{BinderType, BinderValue, JsonBinderValue, MarkupBinderValue};
+use xml_ast::{AttributeMap, Node, TagBuf};
+
+use web_compiler_types::CompilerRuntime;
+
+// ————————————————————————————————————————————————————————————————————————————
+// MACRO
+// ————————————————————————————————————————————————————————————————————————————
+
+/// Declares the parameters a fragment expects from its `<include>` call sites.
+///
+/// ```html
+/// <params hygienic>
+///     <param name="title" type="string"></param>
+///     <param name="show" type="boolean" optional></param>
+///     <param name="label" default="Read more"></param>
+/// </params>
+/// ```
+///
+/// Validation happens at the call site (see [`IncludeContract`]); when the
+/// fragment itself is evaluated this only fills in defaults for optional
+/// parameters that were not passed.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ParamsMacroTag;
+
+impl MacroTag for ParamsMacroTag {
+    type Runtime = CompilerRuntime;
+    fn tag_name(&self) -> &'static str { "params" }
+    fn apply(
+        &self,
+        attributes: xml_ast::AttributeMap,
+        children: xml_ast::Fragment,
+        scope: &mut macro_types::lexical_env::ProcessScope,
+        _: &Self::Runtime,
+    ) -> MacroIO<xml_ast::Node> {
+        let contract = IncludeContract::from_element_parts(&attributes, &children);
+        for param in contract.params {
+            if scope.binding_scope.lookup(&param.name).is_some() {
+                continue;
+            }
+            if let Some(default) = param.default {
+                scope.binding_scope.insert(param.name, BinderValue::json_string(default));
+            }
+        }
+        MacroIO::wrap(Node::empty())
+    }
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// CONTRACT
+// ————————————————————————————————————————————————————————————————————————————
+
+#[derive(Debug, Clone, Default)]
+pub struct IncludeContract {
+    /// Included file only sees `host`, `content` and the declared bindings.
+    pub hygienic: bool,
+    pub params: Vec<ParamSpec>,
+    /// Annotations that failed to parse, reported at every call site.
+    pub invalid_annotations: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ParamSpec {
+    pub name: String,
+    /// `None` accepts any value.
+    pub kind: Option<BinderType>,
+    pub required: bool,
+    pub default: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub enum ContractViolation {
+    MissingParam { name: String, kind: Option<BinderType> },
+    TypeMismatch { name: String, expected: BinderType, found: &'static str },
+    UndeclaredBinding { name: String },
+    InvalidAnnotation { name: String, annotation: String },
+}
+
+impl IncludeContract {
+    pub const TAG_NAME: &'static str = "params";
+    /// Finds the first `<params>` declaration in a parsed (unevaluated) fragment.
+    pub fn from_source_tree(source_tree: &Node) -> Option<Self> {
+        let params = source_tree.find_first(&TagBuf::from(Self::TAG_NAME))?;
+        let params = params.as_element()?;
+        Some(Self::from_element_parts(&params.attributes, &params.children))
+    }
+    fn from_element_parts(attributes: &AttributeMap, children: &xml_ast::Fragment) -> Self {
+        let mut contract = IncludeContract {
+            hygienic: attributes.contains_key("hygienic"),
+            ..Default::default()
+        };
+        let param_tag = TagBuf::from("param");
+        for element in children.clone().extract_elements() {
+            if !element.tag.matches(&param_tag) {
+                continue;
+            }
+            let name = match element.attributes.get("name") {
+                Some(x) => x.as_str().trim().to_string(),
+                None => continue,
+            };
+            let kind = match element.attributes.get("type").map(|x| x.as_str().trim()) {
+                None | Some("any") | Some("") => None,
+                Some(annotation) => match BinderType::parse(annotation) {
+                    Some(kind) => Some(kind),
+                    None => {
+                        contract.invalid_annotations.push((name.clone(), annotation.to_string()));
+                        None
+                    }
+                },
+            };
+            let default = element.attributes.get("default").map(|x| x.as_str().to_string());
+            let required = !element.attributes.contains_key("optional") && default.is_none();
+            contract.params.push(ParamSpec { name, kind, required, default });
+        }
+        contract
+    }
+    pub fn lookup(&self, name: &str) -> Option<&ParamSpec> {
+        self.params.iter().find(|x| x.name == name)
+    }
+    /// Checks the bindings of a single call site.
+    ///
+    /// Declared parameters that were not passed via `bind:` are taken from plain
+    /// attributes of the same name (as strings) and added to `bindings`.
+    pub fn check_call_site(
+        &self,
+        attributes: &BTreeMap<String, String>,
+        bindings: &mut BTreeMap<String, BinderValue>,
+    ) -> Vec<ContractViolation> {
+        let mut violations = self.invalid_annotations
+            .iter()
+            .map(|(name, annotation)| ContractViolation::InvalidAnnotation {
+                name: name.clone(),
+                annotation: annotation.clone(),
+            })
+            .collect::<Vec<_>>();
+        for name in bindings.keys() {
+            if self.lookup(name).is_none() {
+                violations.push(ContractViolation::UndeclaredBinding { name: name.clone() });
+            }
+        }
+        for param in self.params.iter() {
+            if !bindings.contains_key(&param.name) {
+                if let Some(value) = attributes.get(&param.name) {
+                    bindings.insert(param.name.clone(), BinderValue::json_string(value));
+                }
+            }
+            let value = match bindings.get(&param.name) {
+                Some(x) => x,
+                None => {
+                    if param.required {
+                        violations.push(ContractViolation::MissingParam {
+                            name: param.name.clone(),
+                            kind: param.kind.clone(),
+                        });
+                    }
+                    continue
+                }
+            };
+            let kind = match param.kind.as_ref() {
+                Some(x) => x,
+                None => continue,
+            };
+            if !kind.admits(value) && !admits_attribute_literal(kind, value) {
+                violations.push(ContractViolation::TypeMismatch {
+                    name: param.name.clone(),
+                    expected: kind.clone(),
+                    found: describe_value(value),
+                });
+            }
+        }
+        violations
+    }
+}
+
+/// Plain attributes are always strings, so `flag="true"` or `count="3"` still
+/// satisfy `boolean` and `number` parameters.
+fn admits_attribute_literal(kind: &BinderType, value: &BinderValue) -> bool {
+    use macro_types::scope::JsonBinderType;
+    let literal = match value {
+        BinderValue::Json(JsonBinderValue::String(x)) => x.trim(),
+        _ => return false,
+    };
+    match kind {
+        BinderType::Json(JsonBinderType::Bool) => literal == "true" || literal == "false",
+        BinderType::Json(JsonBinderType::Number) => literal.parse::<f64>().is_ok(),
+        _ => false,
+    }
+}
+
+fn describe_value(value: &BinderValue) -> &'static str {
+    match value {
+        BinderValue::Markup(MarkupBinderValue(Node::Text(_))) => "text",
+        BinderValue::Markup(MarkupBinderValue(Node::Element(_))) => "element",
+        BinderValue::Markup(MarkupBinderValue(Node::Fragment(_))) => "fragment",
+        BinderValue::Json(JsonBinderValue::Null) => "null",
+        BinderValue::Json(JsonBinderValue::Bool(_)) => "boolean",
+        BinderValue::Json(JsonBinderValue::Number(_)) => "number",
+        BinderValue::Json(JsonBinderValue::String(_)) => "string",
+        BinderValue::Json(JsonBinderValue::Array(_)) => "array",
+        BinderValue::Json(JsonBinderValue::Object(_)) => "object",
+    }
+}
+
+impl std::fmt::Display for ContractViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MissingParam { name, kind: Some(kind) } => {
+                write!(f, "missing required parameter `{name}` ({kind})")
+            }
+            Self::MissingParam { name, kind: None } => {
+                write!(f, "missing required parameter `{name}`")
+            }
+            Self::TypeMismatch { name, expected, found } => {
+                write!(f, "parameter `{name}` expects {expected} but was given {found}")
+            }
+            Self::UndeclaredBinding { name } => {
+                write!(f, "`bind:{name}` is not declared by the fragment's <params>")
+            }
+            Self::InvalidAnnotation { name, annotation } => {
+                write!(f, "parameter `{name}` has an unknown type `{annotation}`")
+            }
+        }
+    }
+}
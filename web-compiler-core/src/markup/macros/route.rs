@@ -2,7 +2,7 @@ use macro_types::macro_tag::MacroTag;
 use macro_types::lexical_env::MacroIO;
 
 use web_compiler_types::CompilerRuntime;
-use xml_ast::{AttributeKeyBuf, AttributeValueBuf, Element, Node};
+use xml_ast::{AttributeKeyBuf, AttributeValueBuf, Element, Fragment, Node};
 
 use crate::markup::PreProcessor;
 
@@ -23,8 +23,16 @@ impl MacroTag for RouteMacroTag {
             let mut child_scope = scope.to_owned();
             let source_context = runtime.source_context();
             let file_input = source_context.file_input();
-            let file_path = file_input.source_file().file_name().unwrap();
-            let file_path = file_path.to_str().unwrap();
+            let file_path = file_input.source_file().file_name().and_then(|x| x.to_str());
+            let file_path = match file_path {
+                Some(x) => x,
+                None => {
+                    eprintln!("⚠️ {:?} <route href:self>: the file name isn't valid UTF-8", file_input.source_file());
+                    return PreProcessor::new(runtime.clone())
+                        .process_sequence(children.to_vec(), &mut child_scope)
+                        .map(|children| Node::Fragment(Fragment::from_nodes(children)))
+                }
+            };
             let dependency = file_input.with_dependency_relation(file_path);
 
             return PreProcessor::new(runtime.clone())
@@ -0,0 +1,181 @@
+//! Document outline: `<section baseline>` and heading level sanity checks.
+//!
+//! Runs once per page after pre-processing, so the outline covers included
+//! fragments and the template.
+//!
+//! ```html
+//! <section baseline="auto">
+//!     <h1>Installation</h1>   <!-- h2, or h3 within another <section> -->
+//! </section>
+//! <section baseline="h4">
+//!     <h1>Details</h1>        <!-- h4 -->
+//! </section>
+//! ```
+//!
+//! A section's `baseline` shifts the headings inside it the same way the
+//! `baseline` attribute of `<include>` does. With `baseline="auto"` the level
+//! is inferred from the number of enclosing `<section>` elements: `h2` at the
+//! top level, `h3` one section deeper, and so on.
+use std::path::Path;
+use std::str::FromStr;
+
+use xml_ast::{Element, Fragment, Node, TagBuf};
+
+use super::rewrites::headings::{Baseline, HeadingLevel};
+use super::toc::heading_text;
+
+pub const SECTION_TAG: &'static str = "section";
+pub const AUTO_BASELINE: &'static str = "auto";
+
+// ————————————————————————————————————————————————————————————————————————————
+// SECTION BASELINES
+// ————————————————————————————————————————————————————————————————————————————
+
+/// Resolves `<section baseline>` elements and drops the attribute.
+pub fn apply_section_baselines(page: Node, source_path: &Path) -> Node {
+    apply_node(page, None, 0, source_path)
+}
+
+fn apply_node(node: Node, baseline: Option<Baseline>, depth: usize, source_path: &Path) -> Node {
+    match node {
+        Node::Text(text) => Node::Text(text),
+        Node::Fragment(fragment) => Node::Fragment(apply_fragment(fragment, baseline, depth, source_path)),
+        Node::Element(element) => Node::Element(apply_element(element, baseline, depth, source_path)),
+    }
+}
+
+fn apply_fragment(fragment: Fragment, baseline: Option<Baseline>, depth: usize, source_path: &Path) -> Fragment {
+    let nodes = fragment
+        .into_iter()
+        .map(|node| apply_node(node, baseline, depth, source_path))
+        .collect::<Vec<_>>();
+    Fragment::from_nodes(nodes)
+}
+
+fn apply_element(element: Element, baseline: Option<Baseline>, depth: usize, source_path: &Path) -> Element {
+    let Element { mut tag, mut attributes, children } = element;
+    let mut baseline = baseline;
+    let mut depth = depth;
+    if tag.as_normalized() == SECTION_TAG {
+        if let Some(value) = attributes.remove("baseline") {
+            let value = value.as_str().trim();
+            if value.eq_ignore_ascii_case(AUTO_BASELINE) {
+                baseline = Some(Baseline::from_level(depth + 2));
+            } else {
+                match Baseline::from_str(value) {
+                    Ok(x) => baseline = Some(x),
+                    Err(()) => eprintln!("⚠️ {source_path:?} <section> ignoring invalid baseline {value:?}"),
+                }
+            }
+        }
+        depth += 1;
+    }
+    if let (Some(baseline), Ok(heading_level)) = (baseline, HeadingLevel::try_from_tag(&tag)) {
+        let unclamped_level = baseline.unclamped_level(heading_level);
+        if unclamped_level > 6 {
+            eprintln!(
+                "⚠️ {source_path:?} <{}> clamped to <h6> by <section baseline={:?}> (would be h{unclamped_level})",
+                heading_level.as_ref(),
+                baseline.as_ref(),
+            );
+        }
+        tag = TagBuf::new(baseline.apply(heading_level).as_ref());
+    }
+    let children = apply_fragment(children, baseline, depth, source_path);
+    Element { tag, attributes, children }
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// OUTLINE
+// ————————————————————————————————————————————————————————————————————————————
+
+#[derive(Debug, Clone)]
+pub struct OutlineHeading {
+    pub level: usize,
+    pub text: String,
+}
+
+/// The page's headings in document order.
+#[derive(Debug, Clone, Default)]
+pub struct DocumentOutline {
+    pub headings: Vec<OutlineHeading>,
+}
+
+#[derive(Debug, Clone)]
+pub enum OutlineWarning {
+    MultipleH1 { titles: Vec<String> },
+    SkippedLevel { previous: OutlineHeading, heading: OutlineHeading },
+}
+
+impl std::fmt::Display for OutlineWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MultipleH1 { titles } => {
+                write!(f, "{} <h1> headings: {titles:?}", titles.len())
+            }
+            Self::SkippedLevel { previous, heading } => {
+                write!(
+                    f,
+                    "<h{}> {:?} follows <h{}> {:?} (skips a level)",
+                    heading.level,
+                    heading.text,
+                    previous.level,
+                    previous.text,
+                )
+            }
+        }
+    }
+}
+
+impl DocumentOutline {
+    pub fn compute(page: &Node) -> Self {
+        let mut outline = Self::default();
+        outline.collect(page);
+        outline
+    }
+    fn collect(&mut self, node: &Node) {
+        match node {
+            Node::Element(element) => {
+                if let Ok(level) = HeadingLevel::try_from_tag(&element.tag).map(HeadingLevel::level) {
+                    self.headings.push(OutlineHeading { level, text: heading_text(element) });
+                    return
+                }
+                for child in element.children.iter() {
+                    self.collect(child);
+                }
+            }
+            Node::Fragment(fragment) => {
+                for child in fragment.iter() {
+                    self.collect(child);
+                }
+            }
+            Node::Text(_) => (),
+        }
+    }
+    pub fn warnings(&self) -> Vec<OutlineWarning> {
+        let mut warnings = Vec::new();
+        let titles = self.headings
+            .iter()
+            .filter(|x| x.level == 1)
+            .map(|x| x.text.clone())
+            .collect::<Vec<_>>();
+        if titles.len() > 1 {
+            warnings.push(OutlineWarning::MultipleH1 { titles });
+        }
+        for pair in self.headings.windows(2) {
+            let (previous, heading) = (&pair[0], &pair[1]);
+            if heading.level > previous.level + 1 {
+                warnings.push(OutlineWarning::SkippedLevel {
+                    previous: previous.clone(),
+                    heading: heading.clone(),
+                });
+            }
+        }
+        warnings
+    }
+    pub fn report(&self, source_path: &Path) {
+        for warning in self.warnings() {
+            eprintln!("⚠️ {source_path:?} outline: {warning}");
+        }
+    }
+}
@@ -2,13 +2,14 @@ use macro_types::{lexical_env::{SourceHostRef, SourcePathResolver}, tag_rewrite_
 use xml_ast::{traversal::ElementVisitor, Element, Node};
 
 use crate::macro_types::project::ResolvedDependencies;
-use web_compiler_types::CompilerRuntime;
+use web_compiler_types::{CompilerRuntime, ImageOptions};
 
 pub struct PostProcessor<'a> {
     pub rules: &'a TagRewriteRuleSet<CompilerRuntime>,
     pub path_resolver: SourcePathResolver<'a>,
     pub resolved_dependencies: &'a mut ResolvedDependencies,
     pub source_host: SourceHostRef<'a>,
+    pub images: &'a ImageOptions,
 }
 
 impl<'a> PostProcessor<'a> {
@@ -17,12 +18,26 @@ impl<'a> PostProcessor<'a> {
         path_resolver: SourcePathResolver<'a>,
         resolved_dependencies: &'a mut ResolvedDependencies,
         source_host: SourceHostRef<'a>,
+        images: &'a ImageOptions,
     ) -> Self {
-        Self { rules, path_resolver, resolved_dependencies, source_host }
+        Self { rules, path_resolver, resolved_dependencies, source_host, images }
     }
     pub fn apply(&mut self, node: Node) -> Node {
         xml_ast::traversal::apply_element_visitor(node, self)
     }
+    /// Records fragment anchor targets (`id`, and `name` on `<a>`) for link checking.
+    fn record_element_id(&mut self, tag: &xml_ast::TagBuf, attributes: &xml_ast::AttributeMap) {
+        let id = attributes
+            .get("id")
+            .or_else(|| if tag.as_normalized() == "a" { attributes.get("name") } else { None });
+        if let Some(id) = id {
+            let page = self.source_host.file_input().resolved_public_path(self.source_host.project_context());
+            self.resolved_dependencies.element_ids
+                .entry(path_clean::clean(page))
+                .or_default()
+                .insert(id.as_str().to_string());
+        }
+    }
 }
 
 impl<'a> ElementVisitor for PostProcessor<'a> {
@@ -32,12 +47,28 @@ impl<'a> ElementVisitor for PostProcessor<'a> {
         mut attributes: xml_ast::AttributeMap,
         children: xml_ast::Fragment,
     ) -> Node {
+        self.record_element_id(&tag, &attributes);
+        let responsive_image = if tag.as_normalized() == "img" {
+            crate::images::responsive_image_request(&mut attributes, self.images, self.source_host.project_context())
+        } else {
+            None
+        };
         super::rewrites::attributes::resolve_virtual_path_attributes(
             &tag,
             &mut attributes,
             self.path_resolver,
             &mut self.resolved_dependencies,
         );
+        if let Some(request) = responsive_image {
+            return crate::images::apply_responsive_image(
+                &request,
+                Element { tag, attributes, children },
+                self.images,
+                self.source_host.project_context(),
+                self.source_host.file_input().source_file(),
+                &mut self.resolved_dependencies,
+            )
+        }
         self.rules.try_apply_post_processors(Element { tag, attributes, children }, &self.source_host)
     }
 }
@@ -3,6 +3,7 @@ use io_types::Effectful;
 // use lightningcss::error;
 use macro_types::lexical_env::{AccumulatedEffects, Featureset, ProcessScope, MacroIO, SourceHostRef, SourceHost};
 use macro_types::macro_tag::MacroTagSet;
+use macro_types::profile::ProfileCategory;
 use macro_types::project::FileInput;
 use xml_ast::{transform::{EffectfulMarkupTransformer, ProcessMode}, AttributeMap, Element, Fragment, Node, TagBuf};
 
@@ -23,25 +24,27 @@ impl PreProcessor {
         xml_ast::transform::apply_effectful_markup_transformer_node_vec(nodes, self, scope)
     }
     pub fn load_compile(&self, scope: &mut ProcessScope) -> Result<MacroIO<Node>, PreProcessError> {
-        let source = self.runtime
-            .source_context()
+        let source_tree = self.load_source_tree()?;
+        Ok(self.compile_source_tree(source_tree, scope))
+    }
+    /// Reads and parses the runtime's source file without evaluating it.
+    pub fn load_source_tree(&self) -> Result<Node, PreProcessError> {
+        let source_context = self.runtime.source_context();
+        let parsed = source_context
             .file_input()
-            .load_source_file()
+            .load_source_tree(source_context.project_context())
             .map_err(PreProcessError::StdIo)?;
-        let source_tree = {
-            let output = xml_ast::parse_str_auto(&source);
-            if !output.errors.is_empty() {
-                let path = self.runtime.source_context();
-                let path = path.file_input().source_file().to_str().unwrap();
-                for error in output.errors {
-                    eprintln!("Error while processing '{path}': {error}");
-                }
+        let errors = parsed.unreported_errors();
+        if !errors.is_empty() {
+            let path = source_context.file_input().source_file().to_str().unwrap();
+            for error in errors.iter() {
+                eprintln!("Error while processing '{path}': {error}");
             }
-            output.output
-        };
-        // let source_tree = Node::Fragment(source_tree);
-        let output = xml_ast::transform::apply_effectful_markup_transformer(source_tree, self, scope);
-        Ok(output)
+        }
+        Ok(parsed.tree.clone())
+    }
+    pub fn compile_source_tree(&self, source_tree: Node, scope: &mut ProcessScope) -> MacroIO<Node> {
+        xml_ast::transform::apply_effectful_markup_transformer(source_tree, self, scope)
     }
 }
 
@@ -91,12 +94,20 @@ impl EffectfulMarkupTransformer for PreProcessor {
     ) -> MacroIO<Node> {
         let mut effects = AccumulatedEffects::default();
         super::rewrites::attributes::resolve_attribute_path_expressions(&mut attributes, scope, &self.runtime);
+        if let Some(inlined) = super::rewrites::attributes::inline_attribute_assets(
+            &tag,
+            &mut attributes,
+            self.runtime.source_context(),
+        ) {
+            return MacroIO::wrap(inlined)
+        }
         super::rewrites::attributes::virtualize_attribute_paths(
             &tag,
             &mut attributes,
             &mut effects,
             self.runtime.source_context(),
         );
+        super::element_ids::mark_origin(&mut attributes, self.runtime.source_file.source_file());
         let attribute_command = super::rewrites::attributes::AttributeCommand::from_attributes(
             &mut attributes,
             scope,
@@ -104,6 +115,11 @@ impl EffectfulMarkupTransformer for PreProcessor {
         );
         let children = Fragment::from_nodes(children);
         let element = Element { tag, attributes, children };
+        let _span = self.runtime.rules().macros
+            .get(element.tag.as_normalized())
+            .map(|rule| {
+                self.runtime.project.profiler.span(ProfileCategory::RewriteRule, format!("{} (pre)", rule.tag_name()))
+            });
         self.runtime.rules()
             .try_apply_pre_processors(element, scope, &self.runtime)
             .and_modify_context(|ctx| {
@@ -1,12 +1,14 @@
 use once_cell::sync::Lazy;
 use std::collections::HashSet;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use xml_ast::{AttributeMap, AttributeValueBuf, Node, TagBuf};
+use xml_ast::{AttributeMap, AttributeValueBuf, Element, Node, TagBuf};
 use macro_types::scope::BinderValue;
 use macro_types::lexical_env::{AccumulatedEffects, SourceHostRef, SourcePathResolver};
 use macro_types::helpers::srcset::SrcsetCandidate;
-use macro_types::project::{DependencyRelation, FileDependency, ResolvedDependencies, ResolvedDependencyRelation};
+use macro_types::inline_assets::data_uri;
+use macro_types::project::{DependencyRelation, FileDependency, FileInput, ResolvedDependencies, ResolvedDependencyRelation};
+use macro_types::project::{link_fragment, split_link_suffix, LinkKind, LinkReference};
 use web_compiler_types::CompilerRuntime;
 
 // ————————————————————————————————————————————————————————————————————————————
@@ -119,11 +121,19 @@ fn process_path_if_needed(
     }
 }
 
+/// Encodes `value` as a path relative to the current file. Hrefs bound from
+/// site data (`{{page.href}}` of `nav`) are virtual already, relative to the
+/// page rather than the file they are used in, and are kept as they are.
 pub fn virtualize_href(
     value: &mut String,
     source_context: SourceHostRef,
     effects: &mut AccumulatedEffects,
 ) {
+    if let Some(dependency) = DependencyRelation::decode(value.trim()) {
+        *value = value.trim().to_string();
+        effects.dependencies.insert(dependency);
+        return
+    }
     let dependency = source_context.file_input().with_dependency_relation(&value);
     let virtual_src = dependency.encode();
     effects.dependencies.insert(dependency);
@@ -131,6 +141,112 @@ pub fn virtualize_href(
 }
 
 
+// ————————————————————————————————————————————————————————————————————————————
+// INLINE ASSETS
+// ————————————————————————————————————————————————————————————————————————————
+
+pub const INLINE_ATTRIBUTE: &'static str = "inline";
+
+/// `<img>` attributes that make no sense on an inlined `<svg>`.
+const IMG_ONLY_ATTRIBUTES: &[&str] = &["src", "srcset", "sizes", "alt", "loading", "decoding", "responsive"];
+
+/// Embeds the `<img src>` target instead of linking to it, before
+/// [`virtualize_attribute_paths`] would record it as a dependency.
+///
+/// - `<img src="icon.svg" inline>` is replaced by the SVG markup (returned),
+///   merged with the `<img>`'s attributes.
+/// - `<img src="dot.png" inline>`, or any raster image within the
+///   `[inline] max_size` threshold, gets a `data:` URI as its `src`.
+///
+/// `inline="false"` opts out of the threshold.
+pub fn inline_attribute_assets(
+    tag: &TagBuf,
+    attributes: &mut AttributeMap,
+    source_context: SourceHostRef,
+) -> Option<Node> {
+    if tag.as_normalized() != "img" {
+        return None
+    }
+    let opt_in = attributes
+        .remove(INLINE_ATTRIBUTE)
+        .map(|x| !matches!(x.as_str().trim().to_ascii_lowercase().as_str(), "false" | "no"));
+    if opt_in == Some(false) {
+        return None
+    }
+    let src = attributes.get("src")?.as_str().to_string();
+    let dependency = source_context.file_input().with_dependency_relation(&src);
+    if dependency.is_external_target() || !split_link_suffix(&src).1.is_empty() {
+        return None
+    }
+    let target = path_clean::clean(dependency.as_file_dependency().resolved_target_path());
+    let page = source_context.file_input().source_file();
+    let is_svg = target
+        .extension()
+        .map(|x| x.eq_ignore_ascii_case("svg"))
+        .unwrap_or(false);
+    if opt_in.is_none() && (is_svg || !source_context.project_context().inline_assets.should_inline(&target)) {
+        return None
+    }
+    if is_svg {
+        return inline_svg(&target, attributes, source_context)
+    }
+    match data_uri(&target) {
+        Ok(uri) => {
+            attributes.insert("src", AttributeValueBuf::literal(uri));
+        }
+        Err(error) => {
+            eprintln!("⚠️ {page:?} failed to inline {target:?}: {error}");
+        }
+    }
+    None
+}
+
+fn inline_svg(target: &std::path::Path, attributes: &AttributeMap, source_context: SourceHostRef) -> Option<Node> {
+    let page = source_context.file_input().source_file();
+    let target_input = FileInput { source: target.to_path_buf(), public: None };
+    let parsed = match target_input.load_source_tree(source_context.project_context()) {
+        Ok(x) => x,
+        Err(error) => {
+            eprintln!("⚠️ {page:?} failed to inline {target:?}: {error}");
+            return None
+        }
+    };
+    let svg = parsed.tree
+        .find_first(&TagBuf::from("svg"))
+        .and_then(|x| x.to_element());
+    let Element { tag, attributes: mut svg_attributes, children } = match svg {
+        Some(x) => x,
+        None => {
+            eprintln!("⚠️ {page:?} failed to inline {target:?}: no <svg> element");
+            return None
+        }
+    };
+    for (key, value) in attributes.iter() {
+        let key = key.as_str();
+        if IMG_ONLY_ATTRIBUTES.contains(&key) {
+            continue;
+        }
+        let value = match (key, svg_attributes.get(key)) {
+            ("class", Some(existing)) => format!("{} {}", existing.as_str(), value.as_str()),
+            ("style", Some(existing)) => format!("{}; {}", existing.as_str().trim_end_matches(';'), value.as_str()),
+            _ => value.as_str().to_string(),
+        };
+        svg_attributes.insert(key, AttributeValueBuf::literal(value));
+    }
+    match attributes.get("alt").map(|x| x.as_str().trim()) {
+        Some(alt) if !alt.is_empty() => {
+            svg_attributes.insert("role", AttributeValueBuf::literal("img"));
+            if !svg_attributes.contains_key("aria-label") {
+                svg_attributes.insert("aria-label", AttributeValueBuf::literal(alt));
+            }
+        }
+        _ => {
+            svg_attributes.insert("aria-hidden", AttributeValueBuf::literal("true"));
+        }
+    }
+    Some(Node::Element(Element { tag, attributes: svg_attributes, children }))
+}
+
 // ————————————————————————————————————————————————————————————————————————————
 // POST-PROCESS LOGIC
 // ————————————————————————————————————————————————————————————————————————————
@@ -142,16 +258,23 @@ pub fn resolve_virtual_path_attributes(
     resolved_dependencies: &mut ResolvedDependencies,
 ) {
     let tag = tag.as_normalized();
+    let links_compiled_asset = links_compiled_asset(tag, attributes);
+    let is_classic_script = tag == "script" && !is_module_script(attributes);
     for ( key, value ) in attributes.iter_mut() {
         let key = key.as_str().to_ascii_lowercase();
         if REQUIRES_REGULAR_DEPENDENCY_TRACKING.contains(&(tag, &key)) {
-            rewrite_path_mut(value.as_mut_string(), resolver, resolved_dependencies);
+            let is_compiled = links_compiled_asset && matches!(key.as_str(), "src" | "href");
+            let target = rewrite_path_mut(value.as_mut_string(), LinkKind::Attribute, is_compiled, resolver, resolved_dependencies);
+            if let Some(target) = target.filter(|_| is_classic_script && key == "src") {
+                let page = resolver.source_host.file_input().source_file().to_path_buf();
+                resolved_dependencies.classic_scripts.entry(path_clean::clean(target)).or_default().insert(page);
+            }
         }
         else if REQUIRES_SRC_SET_DEPENDENCY_TRACKING.contains(&(tag, &key)) {
             let source_sets = SrcsetCandidate::parse_srcset(value.as_str())
                 .into_iter()
                 .map(|SrcsetCandidate { mut url, descriptor }| {
-                    rewrite_path_mut(&mut url, resolver, resolved_dependencies);
+                    rewrite_path_mut(&mut url, LinkKind::SrcsetCandidate, false, resolver, resolved_dependencies);
                     SrcsetCandidate {
                         url,
                         descriptor: descriptor,
@@ -164,51 +287,121 @@ pub fn resolve_virtual_path_attributes(
     }
 }
 
+/// `<script src>` and stylesheet or module preload `<link>`s load the
+/// compiled asset (`main.ts` → `main.js`, `main.scss` → `main.css`); any
+/// other reference (`<a href="clip.ts">`, `<video src="stream.ts">`) links
+/// the file as it is.
+fn links_compiled_asset(tag: &str, attributes: &AttributeMap) -> bool {
+    match tag {
+        "script" => true,
+        "link" => attributes.get("rel").is_some_and(|rel| {
+            rel.as_str()
+                .split_ascii_whitespace()
+                .any(|x| x.eq_ignore_ascii_case("stylesheet") || x.eq_ignore_ascii_case("modulepreload"))
+        }),
+        _ => false,
+    }
+}
+
+fn is_module_script(attributes: &AttributeMap) -> bool {
+    attributes
+        .get("type")
+        .is_some_and(|x| x.as_str().trim().eq_ignore_ascii_case("module"))
+}
+
+/// Resolves a virtual path to the target's output path, relative to the
+/// page; with `is_compiled`, to the path the target is compiled to. Returns
+/// the target's public path when it resolves to a project file.
 pub fn rewrite_path_mut(
     href: &mut String,
+    kind: LinkKind,
+    is_compiled: bool,
     resolver: SourcePathResolver,
     resolved_dependencies: &mut ResolvedDependencies,
-) {
+) -> Option<PathBuf> {
     // let _ = resolver;
     // let _ = resolved_dependencies;
     let decoded_virtual_path = match DependencyRelation::decode(&href) {
         Some(x) => x,
-        None => return
+        None => return None
+    };
+    let page = resolver.source_host.file_input().source_file().to_path_buf();
+    let link_reference = |href: &str, target: Option<PathBuf>, suffix: &str| LinkReference {
+        kind,
+        page: page.clone(),
+        origin: PathBuf::from(&decoded_virtual_path.from),
+        include_chain: Vec::default(),
+        href: href.to_string(),
+        target,
+        fragment: link_fragment(suffix).map(ToString::to_string),
     };
     // - -
     if decoded_virtual_path.is_external_target() {
         let path = decoded_virtual_path.to
             .strip_prefix("noop://")
             .unwrap_or(decoded_virtual_path.to.as_str());
+        if path.starts_with('#') {
+            resolved_dependencies.link_references.push(link_reference(path, None, path));
+        } else if !decoded_virtual_path.to.starts_with("noop://") {
+            resolved_dependencies.link_references.push(link_reference(path, None, ""));
+        }
         *href = path.to_string();
-        return
+        return None
     }
     // - -
-    let resolved = resolve_dependency_relation(&resolver, &decoded_virtual_path);
+    let (_, suffix) = split_link_suffix(&decoded_virtual_path.to);
+    let target_relation = decoded_virtual_path.without_link_suffix();
+    if target_relation.to.is_empty() {
+        *href = decoded_virtual_path.to.clone();
+        return None
+    }
+    let project_context = resolver.source_host.project_context();
+    let resolved = resolve_dependency_relation(&resolver, &target_relation);
     let resolved = match resolved {
         Some(x) => x,
         None => {
-            eprintln!("⚠️ TODO: resolve output path for target {href:?}");
-            *href = decoded_virtual_path.to;
-            return
+            let target = FileInput {
+                source: path_clean::clean(target_relation.as_file_dependency().resolved_target_path()),
+                public: None,
+            };
+            let target = target.resolved_public_path(project_context);
+            eprintln!("⚠️ {page:?} unresolved link {:?}", decoded_virtual_path.to);
+            resolved_dependencies.link_references.push(link_reference(&decoded_virtual_path.to, Some(target), suffix));
+            *href = decoded_virtual_path.to.clone();
+            return None
         }
     };
+    let resolved = match is_compiled {
+        true => macro_types::path_utils::compiled_asset_path(&resolved),
+        false => resolved,
+    };
     // - -
-    let resolved_origin = resolver.source_host.file_input().resolved_public_path(resolver.source_host.project_context());
-    let relative = pathdiff::diff_paths(&resolved, resolved_origin.parent().unwrap()).unwrap();
+    let resolved_origin = resolver.source_host.file_input().resolved_public_path(project_context);
+    let relative = pathdiff::diff_paths(&resolved, resolved_origin.parent().unwrap_or(Path::new("")));
     // println!("{resolved:?} <~> {:?} => {relative:?}", resolved_origin.parent());
+    let relative = match relative {
+        Some(x) if x.to_str().is_some() => x,
+        _ => {
+            eprintln!("⚠️ {page:?} can't link {resolved:?} relative to {resolved_origin:?}");
+            *href = decoded_virtual_path.to.clone();
+            return None
+        }
+    };
     // - -
-    *href = relative.to_str().unwrap().to_string();
+    *href = format!("{}{suffix}", relative.display());
     // - -
+    let target = FileInput { source: resolved, public: None }.resolved_public_path(project_context);
+    resolved_dependencies.link_references.push(link_reference(&decoded_virtual_path.to, Some(target.clone()), suffix));
     let resolved_dependency = ResolvedDependencyRelation {
         finalized: FileDependency {
             from: resolved_origin,
             to: relative,
         },
-        original: decoded_virtual_path,
+        original: target_relation,
     };
     // let resolved_dependency = resolved_dependency.cleaned();
     resolved_dependencies.include_dependency_relation(resolved_dependency);
+    Some(target)
 }
 
 fn resolve_dependency_relation(
@@ -364,7 +557,15 @@ impl<'a> ResolvedPathExpression<'a> {
             })
             .and_then(|target| {
                 // let result = scope.binding_scope.lookup(target);
-                let path_expr = macro_types::path_expr::PathExpression::parse(target).unwrap();
+                let path_expr = match macro_types::path_expr::PathExpression::parse(target) {
+                    Ok(x) => x,
+                    Err(error) => {
+                        runtime.with_source_file_path(|file| {
+                            eprintln!("⚠️ {file:?} `ResolvedPathExpression` failed to parse `{target}`: {error}");
+                        });
+                        return None
+                    }
+                };
                 let path_value = path_expr.evaluate(&scope.binding_scope);
                 if path_value.is_none() {
                     runtime.with_source_file_path(|file| {
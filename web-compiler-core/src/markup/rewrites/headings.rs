@@ -32,81 +32,63 @@ pub struct H6RewriteRule;
 // TYPES — INTERNAL
 // ————————————————————————————————————————————————————————————————————————————
 
+/// The level `h1` lands on.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum Baseline {
-    H1, H2, H3, H4, H5, H6
-}
+pub(crate) struct Baseline(HeadingLevel);
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum HeadingLevel {
+pub(crate) enum HeadingLevel {
     H1, H2, H3, H4, H5, H6
 }
 
 impl Baseline {
-    // TODO: reduce LOC?
+    pub fn level(self) -> usize {
+        self.0.level()
+    }
+    pub fn from_level(level: usize) -> Self {
+        Self(HeadingLevel::from_level(level))
+    }
+    /// The level `heading_level` shifts to, which may be past `h6`.
+    pub fn unclamped_level(self, heading_level: HeadingLevel) -> usize {
+        self.level() + heading_level.level() - 1
+    }
+    /// Shifts `heading_level` so that `h1` lands on the baseline; levels past
+    /// `h6` are clamped to `h6`.
     pub fn apply(self, heading_level: HeadingLevel) -> HeadingLevel {
-        match (self, heading_level) {
-            (Self::H1, HeadingLevel::H1) => HeadingLevel::H1,
-            (Self::H1, HeadingLevel::H2) => HeadingLevel::H2,
-            (Self::H1, HeadingLevel::H3) => HeadingLevel::H3,
-            (Self::H1, HeadingLevel::H4) => HeadingLevel::H4,
-            (Self::H1, HeadingLevel::H5) => HeadingLevel::H5,
-            (Self::H1, HeadingLevel::H6) => HeadingLevel::H6,
-            // - -
-            (Self::H2, HeadingLevel::H1) => HeadingLevel::H2,
-            (Self::H2, HeadingLevel::H2) => HeadingLevel::H3,
-            (Self::H2, HeadingLevel::H3) => HeadingLevel::H4,
-            (Self::H2, HeadingLevel::H4) => HeadingLevel::H5,
-            (Self::H2, HeadingLevel::H5) => HeadingLevel::H6,
-            (Self::H2, HeadingLevel::H6) => HeadingLevel::H6,
-            // - -
-            (Self::H3, HeadingLevel::H1) => HeadingLevel::H3,
-            (Self::H3, HeadingLevel::H2) => HeadingLevel::H4,
-            (Self::H3, HeadingLevel::H3) => HeadingLevel::H5,
-            (Self::H3, HeadingLevel::H4) => HeadingLevel::H6,
-            (Self::H3, HeadingLevel::H5) => HeadingLevel::H6,
-            (Self::H3, HeadingLevel::H6) => HeadingLevel::H6,
-            // - -
-            (Self::H4, HeadingLevel::H1) => HeadingLevel::H4,
-            (Self::H4, HeadingLevel::H2) => HeadingLevel::H5,
-            (Self::H4, HeadingLevel::H3) => HeadingLevel::H6,
-            (Self::H4, HeadingLevel::H4) => HeadingLevel::H6,
-            (Self::H4, HeadingLevel::H5) => HeadingLevel::H6,
-            (Self::H4, HeadingLevel::H6) => HeadingLevel::H6,
-            // - -
-            (Self::H5, HeadingLevel::H1) => HeadingLevel::H5,
-            (Self::H5, HeadingLevel::H2) => HeadingLevel::H6,
-            (Self::H5, HeadingLevel::H3) => HeadingLevel::H6,
-            (Self::H5, HeadingLevel::H4) => HeadingLevel::H6,
-            (Self::H5, HeadingLevel::H5) => HeadingLevel::H6,
-            (Self::H5, HeadingLevel::H6) => HeadingLevel::H6,
-            // - -
-            (Self::H6, HeadingLevel::H1) => HeadingLevel::H6,
-            (Self::H6, HeadingLevel::H2) => HeadingLevel::H6,
-            (Self::H6, HeadingLevel::H3) => HeadingLevel::H6,
-            (Self::H6, HeadingLevel::H4) => HeadingLevel::H6,
-            (Self::H6, HeadingLevel::H5) => HeadingLevel::H6,
-            (Self::H6, HeadingLevel::H6) => HeadingLevel::H6,
-        }
+        HeadingLevel::from_level(self.unclamped_level(heading_level))
     }
 }
 
 impl HeadingLevel {
+    pub fn level(self) -> usize {
+        match self {
+            Self::H1 => 1,
+            Self::H2 => 2,
+            Self::H3 => 3,
+            Self::H4 => 4,
+            Self::H5 => 5,
+            Self::H6 => 6,
+        }
+    }
+    /// Levels below `1` are `h1`, levels past `6` are `h6`.
+    pub fn from_level(level: usize) -> Self {
+        match level {
+            0 | 1 => Self::H1,
+            2 => Self::H2,
+            3 => Self::H3,
+            4 => Self::H4,
+            5 => Self::H5,
+            _ => Self::H6,
+        }
+    }
     pub fn try_from_tag(tag: &TagBuf) -> Result<Self, ()> {
         Self::from_str(tag.as_normalized())
     }
 }
 
 impl AsRef<str> for Baseline {
-    fn as_ref(&self) -> &'static str {
-        match self {
-            Self::H1 => "h1",
-            Self::H2 => "h2",
-            Self::H3 => "h3",
-            Self::H4 => "h4",
-            Self::H5 => "h5",
-            Self::H6 => "h6",
-        }
+    fn as_ref(&self) -> &str {
+        self.0.as_ref()
     }
 }
 
@@ -126,23 +108,7 @@ impl AsRef<str> for HeadingLevel {
 impl FromStr for Baseline {
     type Err = ();
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "h1" => Ok(Self::H1),
-            "h2" => Ok(Self::H2),
-            "h3" => Ok(Self::H3),
-            "h4" => Ok(Self::H4),
-            "h5" => Ok(Self::H5),
-            "h6" => Ok(Self::H6),
-            // - -
-            "H1" => Ok(Self::H1),
-            "H2" => Ok(Self::H2),
-            "H3" => Ok(Self::H3),
-            "H4" => Ok(Self::H4),
-            "H5" => Ok(Self::H5),
-            "H6" => Ok(Self::H6),
-            // - -
-            _ => return Err(())
-        }
+        HeadingLevel::from_str(s).map(Self)
     }
 }
 
@@ -174,7 +140,7 @@ impl FromStr for HeadingLevel {
 // ————————————————————————————————————————————————————————————————————————————
 
 
-fn apply(element: Element, scope: &mut ProcessScope) -> MacroIO<Node> {
+fn apply(element: Element, scope: &mut ProcessScope, runtime: &CompilerRuntime) -> MacroIO<Node> {
     let baseline = scope.binding_scope
         .lookup("host")
         .and_then(|x| x.as_object())
@@ -200,6 +166,16 @@ fn apply(element: Element, scope: &mut ProcessScope) -> MacroIO<Node> {
         Err(()) => return MacroIO::wrap(Node::Element(element)),
     };
     let Element { tag: _, attributes, children } = element;
+    let unclamped_level = baseline.unclamped_level(heading_level);
+    if unclamped_level > 6 {
+        runtime.with_source_file_path(|file| {
+            eprintln!(
+                "⚠️ {file:?} <{}> clamped to <h6> by baseline {:?} (would be h{unclamped_level})",
+                heading_level.as_ref(),
+                baseline.as_ref(),
+            );
+        });
+    }
     let resolved_heading_level = baseline.apply(heading_level);
     let tag = TagBuf::new(resolved_heading_level.as_ref());
     let element = Element { tag, attributes, children };
@@ -209,8 +185,8 @@ fn apply(element: Element, scope: &mut ProcessScope) -> MacroIO<Node> {
 impl TagRewriteRule for H1RewriteRule {
     type Runtime = CompilerRuntime;
     fn tag_name(&self) -> &'static str { "h1" }
-    fn pre_process( &self, element: Element, scope: &mut ProcessScope, _: &Self::Runtime) -> MacroIO<Node> {
-        apply(element, scope)
+    fn pre_process( &self, element: Element, scope: &mut ProcessScope, runtime: &Self::Runtime) -> MacroIO<Node> {
+        apply(element, scope, runtime)
     }
     fn post_process(&self, element: Element, _: &SourceHostRef) -> Node {
         Node::Element(element)
@@ -220,8 +196,8 @@ impl TagRewriteRule for H1RewriteRule {
 impl TagRewriteRule for H2RewriteRule {
     type Runtime = CompilerRuntime;
     fn tag_name(&self) -> &'static str { "h2" }
-    fn pre_process( &self, element: Element, scope: &mut ProcessScope, _: &Self::Runtime) -> MacroIO<Node> {
-        apply(element, scope)
+    fn pre_process( &self, element: Element, scope: &mut ProcessScope, runtime: &Self::Runtime) -> MacroIO<Node> {
+        apply(element, scope, runtime)
     }
     fn post_process(&self, element: Element, _: &SourceHostRef) -> Node {
         Node::Element(element)
@@ -231,8 +207,8 @@ impl TagRewriteRule for H2RewriteRule {
 impl TagRewriteRule for H3RewriteRule {
     type Runtime = CompilerRuntime;
     fn tag_name(&self) -> &'static str { "h3" }
-    fn pre_process( &self, element: Element, scope: &mut ProcessScope, _: &Self::Runtime) -> MacroIO<Node> {
-        apply(element, scope)
+    fn pre_process( &self, element: Element, scope: &mut ProcessScope, runtime: &Self::Runtime) -> MacroIO<Node> {
+        apply(element, scope, runtime)
     }
     fn post_process(&self, element: Element, _: &SourceHostRef) -> Node {
         Node::Element(element)
@@ -242,8 +218,8 @@ impl TagRewriteRule for H3RewriteRule {
 impl TagRewriteRule for H4RewriteRule {
     type Runtime = CompilerRuntime;
     fn tag_name(&self) -> &'static str { "h4" }
-    fn pre_process( &self, element: Element, scope: &mut ProcessScope, _: &Self::Runtime) -> MacroIO<Node> {
-        apply(element, scope)
+    fn pre_process( &self, element: Element, scope: &mut ProcessScope, runtime: &Self::Runtime) -> MacroIO<Node> {
+        apply(element, scope, runtime)
     }
     fn post_process(&self, element: Element, _: &SourceHostRef) -> Node {
         Node::Element(element)
@@ -253,8 +229,8 @@ impl TagRewriteRule for H4RewriteRule {
 impl TagRewriteRule for H5RewriteRule {
     type Runtime = CompilerRuntime;
     fn tag_name(&self) -> &'static str { "h5" }
-    fn pre_process( &self, element: Element, scope: &mut ProcessScope, _: &Self::Runtime) -> MacroIO<Node> {
-        apply(element, scope)
+    fn pre_process( &self, element: Element, scope: &mut ProcessScope, runtime: &Self::Runtime) -> MacroIO<Node> {
+        apply(element, scope, runtime)
     }
     fn post_process(&self, element: Element, _: &SourceHostRef) -> Node {
         Node::Element(element)
@@ -264,8 +240,8 @@ impl TagRewriteRule for H5RewriteRule {
 impl TagRewriteRule for H6RewriteRule {
     type Runtime = CompilerRuntime;
     fn tag_name(&self) -> &'static str { "h6" }
-    fn pre_process( &self, element: Element, scope: &mut ProcessScope, _: &Self::Runtime) -> MacroIO<Node> {
-        apply(element, scope)
+    fn pre_process( &self, element: Element, scope: &mut ProcessScope, runtime: &Self::Runtime) -> MacroIO<Node> {
+        apply(element, scope, runtime)
     }
     fn post_process(&self, element: Element, _: &SourceHostRef) -> Node {
         Node::Element(element)
@@ -2,7 +2,7 @@ pub mod attributes;
 
 mod style;
 mod document;
-mod headings;
+pub(crate) mod headings;
 
 use std::rc::Rc;
 use macro_types::tag_rewrite_rule::{TagRewriteRule, TagRewriteRuleSet};
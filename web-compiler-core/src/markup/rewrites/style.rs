@@ -4,6 +4,7 @@ use macro_types::tag_rewrite_rule::TagRewriteRule;
 use xml_ast::{Element, Fragment, Node};
 
 use css::{CssPostprocessor, CssPreprocessor};
+use css::scss::ScssCompiler;
 
 use web_compiler_types::CompilerRuntime;
 
@@ -22,11 +23,26 @@ impl TagRewriteRule for StyleMacroTag {
         let text_contents = element
             .text_contents()
             .join("");
-        let css_preprocessor = CssPreprocessor::new(runtime.source_context());
-        css_preprocessor
-            .execute(&text_contents)
+        let Element { tag, mut attributes, children: _ } = element;
+        let is_scss = attributes
+            .get("lang")
+            .is_some_and(|x| x.as_str().trim().eq_ignore_ascii_case(css::scss::SCSS_LANG));
+        let stylesheet = if is_scss {
+            attributes.remove("lang");
+            ScssCompiler::new(runtime.source_context())
+                .for_style_element()
+                .execute(&text_contents)
+                .map(|compiled| compiled.value)
+        } else {
+            MacroIO::wrap(text_contents)
+        };
+        stylesheet
+            .and_then(|stylesheet| {
+                CssPreprocessor::new(runtime.source_context())
+                    .for_style_element()
+                    .execute(&stylesheet)
+            })
             .map(|stylesheet| {
-                let Element { tag, attributes, children: _ } = element;
                 let children = Fragment::from_nodes(vec![
                     Node::text(stylesheet.value),
                 ]);
@@ -43,8 +59,13 @@ impl TagRewriteRule for StyleMacroTag {
             .text_contents()
             .join("");
         // let source_context = self.
-        let css_post_processor = CssPostprocessor::new(source_host_ref.clone());
-        let result = css_post_processor.execute(&text_contents);
+        let css_post_processor = CssPostprocessor::new(source_host_ref.clone())
+            .for_style_element()
+            .without_parse_errors();
+        let (result, effects) = css_post_processor.execute(&text_contents).collapse();
+        for error in effects.stylesheet_errors {
+            eprintln!("⚠️ {error}");
+        }
         let children = Fragment::from_nodes(vec![Node::text(result.value)]);
         Node::element(tag, attributes, children)
     }
@@ -0,0 +1,112 @@
+//! `<style scoped>` in included fragments.
+//!
+//! Once an `<include>` is expanded, the fragment's `<style scoped>` elements
+//! are taken out of it and their selectors rewritten to only match the
+//! fragment's root elements, which get a boolean `data-scope-{id}` attribute,
+//! and their descendants (see `css::scoped`). The `{id}` is derived from the
+//! fragment's project-relative path, so every usage of a component shares it
+//! and it is the same from one build (and machine) to the next.
+//!
+//! The rewritten styles are hoisted as one `<style data-scope="{id}">` per
+//! component, and placed once per page like the fragment's other styles (see
+//! `markup::hoisting`).
+use std::path::Path;
+
+use macro_types::lexical_env::MacroIO;
+use macro_types::project::ProjectContext;
+use macro_types::scope::{BinderValue, MarkupBinderValue};
+use xml_ast::traversal::ElementVisitor;
+use xml_ast::{AttributeMap, AttributeValueBuf, Element, Fragment, Node, TagBuf};
+
+pub const SCOPED_ATTRIBUTE: &'static str = "scoped";
+
+/// Set on hoisted scoped styles, naming their component scope.
+pub const SCOPE_MARKER_ATTRIBUTE: &'static str = "data-scope";
+
+/// Root elements that can't be styled and don't get the scope attribute.
+const UNSTYLED_TAGS: &[&str] = &["style", "script", "link", "meta", "template", "title", "base"];
+
+/// `components/site-header.html` → `site-header-1f3a9c0b`.
+pub fn scope_id(fragment: &Path, project_context: &ProjectContext) -> String {
+    let fragment = path_clean::clean(fragment);
+    let relative = fragment
+        .strip_prefix(&project_context.project_root)
+        .unwrap_or(&fragment);
+    let key = relative
+        .components()
+        .map(|x| x.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+    let stem = fragment
+        .file_stem()
+        .and_then(|x| x.to_str())
+        .map(super::toc::slugify)
+        .unwrap_or_default()
+        .replace(|x: char| !x.is_ascii_alphanumeric() && x != '-', "");
+    let stem = if stem.is_empty() { String::from("component") } else { stem };
+    format!("{stem}-{:08x}", fnv1a(key.as_bytes()) as u32)
+}
+
+/// 64-bit FNV-1a, which unlike `std`'s hashers is fixed across Rust releases.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// SCOPING
+// ————————————————————————————————————————————————————————————————————————————
+
+struct ScopedStyleExtractor {
+    styles: Vec<Element>,
+}
+
+impl ElementVisitor for ScopedStyleExtractor {
+    fn visit_element(&mut self, tag: TagBuf, attributes: AttributeMap, children: Fragment) -> Node {
+        if tag.as_normalized() == "style" && attributes.contains_key(SCOPED_ATTRIBUTE) {
+            self.styles.push(Element { tag, attributes, children });
+            return Node::empty()
+        }
+        Node::Element(Element { tag, attributes, children })
+    }
+}
+
+/// Scopes the `<style scoped>` elements of the expanded `fragment` and hoists them.
+pub fn apply_scoped_styles(node: Node, fragment: &Path, project_context: &ProjectContext) -> MacroIO<Node> {
+    let mut extractor = ScopedStyleExtractor { styles: Vec::new() };
+    let node = xml_ast::traversal::apply_element_visitor(node, &mut extractor);
+    if extractor.styles.is_empty() {
+        return MacroIO::wrap(node)
+    }
+    let id = scope_id(fragment, project_context);
+    let scope_attribute = format!("data-scope-{id}");
+    let mut attributes = AttributeMap::default();
+    let mut stylesheet = String::new();
+    for style in extractor.styles {
+        let source = style.children.text_contents().join("");
+        let scoped = css::scoped::scope_stylesheet(&source, &scope_attribute).unwrap_or_else(|error| {
+            eprintln!("⚠️ {fragment:?} failed to scope <style scoped>: {error}");
+            source
+        });
+        attributes.merge_if_absent(&style.attributes);
+        stylesheet.push_str(&scoped);
+    }
+    attributes.remove(SCOPED_ATTRIBUTE);
+    attributes.insert(SCOPE_MARKER_ATTRIBUTE, AttributeValueBuf::literal(id));
+    let style = Node::element("style", attributes, vec![Node::text(stylesheet)]);
+    let roots = node
+        .flatten()
+        .into_iter()
+        .map(|root| match root {
+            Node::Element(mut element) if !UNSTYLED_TAGS.contains(&element.tag.as_normalized()) => {
+                element.attributes.insert(scope_attribute.as_str(), AttributeValueBuf::literal(""));
+                Node::Element(element)
+            }
+            root => root,
+        })
+        .collect::<Vec<_>>();
+    MacroIO::wrap(Node::Fragment(Fragment::from_nodes(roots))).and_modify_context(|ctx| {
+        ctx.hoisted.push(BinderValue::Markup(MarkupBinderValue(style)));
+    })
+}
@@ -0,0 +1,314 @@
+//! Table of contents generation.
+//!
+//! ```html
+//! <toc depth="2" min-level="2"></toc>
+//! ```
+//!
+//! `<toc>` is resolved once per page, after pre-processing, so it sees the
+//! final document: includes are expanded, templates are applied and heading
+//! levels are already adjusted by `baseline` (on `<include>` and `<section>`).
+//! Headings within range that lack an `id` are given a slug id
+//! (unique within the page) and the `<toc>` element is replaced with a
+//! `<nav>` containing a nested list of links.
+//!
+//! Attributes:
+//! - `min-level` — the highest heading level listed (default: `2`).
+//! - `depth` — the number of heading levels listed (default: `3`).
+//! - `ordered` — render `<ol>` instead of `<ul>`.
+//!
+//! All other attributes are kept on the generated `<nav>` (which defaults to
+//! `class="toc"`).
+use std::collections::HashSet;
+use std::path::Path;
+
+use xml_ast::traversal::ElementVisitor;
+use xml_ast::{AttributeMap, AttributeValueBuf, Element, Fragment, Node, TagBuf};
+
+use super::rewrites::headings::HeadingLevel;
+
+pub const TAG_NAME: &'static str = "toc";
+
+// ————————————————————————————————————————————————————————————————————————————
+// OPTIONS
+// ————————————————————————————————————————————————————————————————————————————
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct TocOptions {
+    min_level: usize,
+    depth: usize,
+    ordered: bool,
+}
+
+impl Default for TocOptions {
+    fn default() -> Self {
+        Self { min_level: 2, depth: 3, ordered: false }
+    }
+}
+
+impl TocOptions {
+    /// Takes the option attributes out of `attributes`; invalid values are
+    /// reported when `source_path` is given.
+    fn take_from(attributes: &mut AttributeMap, source_path: Option<&Path>) -> Self {
+        let mut options = Self::default();
+        let mut parse_level = |key: &str, max: usize| -> Option<usize> {
+            let value = attributes.remove(key)?;
+            let value = value.as_str().trim();
+            let parsed = value
+                .trim_start_matches(['h', 'H'])
+                .parse::<usize>()
+                .ok()
+                .filter(|x| (1..=max).contains(x));
+            if let (None, Some(source_path)) = (parsed, source_path) {
+                eprintln!("⚠️ {source_path:?} <toc> ignoring invalid {key} {value:?}");
+            }
+            parsed
+        };
+        if let Some(min_level) = parse_level("min-level", 6) {
+            options.min_level = min_level;
+        }
+        if let Some(depth) = parse_level("depth", 6) {
+            options.depth = depth;
+        }
+        options.ordered = attributes.remove("ordered").is_some();
+        options
+    }
+    fn includes(&self, level: usize) -> bool {
+        level >= self.min_level && level < self.min_level + self.depth
+    }
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// SLUGS
+// ————————————————————————————————————————————————————————————————————————————
+
+/// `"Getting Started!"` → `"getting-started"`.
+pub fn slugify(text: &str) -> String {
+    let mut slug = String::with_capacity(text.len());
+    let mut pending_separator = false;
+    for char in text.chars() {
+        if char.is_alphanumeric() {
+            if pending_separator && !slug.is_empty() {
+                slug.push('-');
+            }
+            pending_separator = false;
+            slug.extend(char.to_lowercase());
+        } else if char.is_whitespace() || char == '-' || char == '_' {
+            pending_separator = true;
+        }
+    }
+    if slug.is_empty() {
+        return String::from("section")
+    }
+    slug
+}
+
+/// Hands out slug ids that are unique within one page.
+#[derive(Debug, Clone, Default)]
+pub struct SlugRegistry {
+    taken: HashSet<String>,
+}
+
+impl SlugRegistry {
+    /// Reserves every `id` already present in `node`.
+    pub fn from_existing_ids(node: &Node) -> Self {
+        let mut registry = Self::default();
+        registry.reserve_ids(node);
+        registry
+    }
+    fn reserve_ids(&mut self, node: &Node) {
+        match node {
+            Node::Element(element) => {
+                if let Some(id) = element.attributes.get("id") {
+                    self.taken.insert(id.as_str().to_string());
+                }
+                for child in element.children.iter() {
+                    self.reserve_ids(child);
+                }
+            }
+            Node::Fragment(fragment) => {
+                for child in fragment.iter() {
+                    self.reserve_ids(child);
+                }
+            }
+            Node::Text(_) => (),
+        }
+    }
+    /// `slug`, or `slug-1`, `slug-2`, … when it is already taken.
+    pub fn claim(&mut self, text: &str) -> String {
+        let slug = slugify(text);
+        let mut candidate = slug.clone();
+        let mut counter = 0;
+        while self.taken.contains(&candidate) {
+            counter += 1;
+            candidate = format!("{slug}-{counter}");
+        }
+        self.taken.insert(candidate.clone());
+        candidate
+    }
+}
+
+/// The heading's text with whitespace collapsed.
+pub fn heading_text(element: &Element) -> String {
+    element
+        .text_contents()
+        .join("")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// COLLECTION
+// ————————————————————————————————————————————————————————————————————————————
+
+#[derive(Debug, Clone)]
+struct Heading {
+    level: usize,
+    id: String,
+    text: String,
+}
+
+/// Assigns ids to the headings the page's `<toc>` elements list, in document order.
+struct HeadingCollector {
+    /// Union of the levels listed by any `<toc>` on the page.
+    levels: Vec<usize>,
+    slugs: SlugRegistry,
+    headings: Vec<Heading>,
+}
+
+impl ElementVisitor for HeadingCollector {
+    fn visit_element(&mut self, tag: TagBuf, mut attributes: AttributeMap, children: Fragment) -> Node {
+        let level = HeadingLevel::try_from_tag(&tag)
+            .ok()
+            .map(HeadingLevel::level)
+            .filter(|x| self.levels.contains(x));
+        let element = Element { tag, attributes: AttributeMap::default(), children };
+        if let Some(level) = level {
+            let text = heading_text(&element);
+            let id = match attributes.get("id") {
+                Some(id) => id.as_str().to_string(),
+                None => {
+                    let id = self.slugs.claim(&text);
+                    attributes.insert("id", AttributeValueBuf::literal(id.clone()));
+                    id
+                }
+            };
+            self.headings.push(Heading { level, id, text });
+        }
+        Node::Element(Element { attributes, ..element })
+    }
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// RENDERING
+// ————————————————————————————————————————————————————————————————————————————
+
+#[derive(Debug, Clone)]
+struct TocEntry {
+    heading: Heading,
+    children: Vec<TocEntry>,
+}
+
+impl TocEntry {
+    /// Nests `heading` under the most recent entry of a higher level, so
+    /// skipped levels (e.g. `h2` → `h4`) still nest one step.
+    fn insert(entries: &mut Vec<TocEntry>, heading: Heading) {
+        match entries.last_mut() {
+            Some(last) if last.heading.level < heading.level => {
+                Self::insert(&mut last.children, heading)
+            }
+            _ => entries.push(TocEntry { heading, children: Vec::new() }),
+        }
+    }
+    fn render_list(entries: &[TocEntry], ordered: bool) -> Element {
+        let items = entries
+            .iter()
+            .map(|entry| {
+                let link = Element::new("a")
+                    .with_attribute("href", AttributeValueBuf::literal(format!("#{}", entry.heading.id)))
+                    .with_children(vec![Node::text(entry.heading.text.clone())]);
+                let mut item = Element::new("li").with_children(vec![Node::Element(link)]);
+                if !entry.children.is_empty() {
+                    item.children.push(Node::Element(Self::render_list(&entry.children, ordered)));
+                }
+                Node::Element(item)
+            })
+            .collect::<Vec<_>>();
+        Element::new(if ordered { "ol" } else { "ul" }).with_children(items)
+    }
+}
+
+struct TocRenderer<'a> {
+    headings: &'a [Heading],
+    source_path: &'a Path,
+}
+
+impl<'a> ElementVisitor for TocRenderer<'a> {
+    fn visit_element(&mut self, tag: TagBuf, mut attributes: AttributeMap, children: Fragment) -> Node {
+        if tag.as_normalized() != TAG_NAME {
+            return Node::Element(Element { tag, attributes, children })
+        }
+        let options = TocOptions::take_from(&mut attributes, Some(self.source_path));
+        let mut entries = Vec::<TocEntry>::new();
+        for heading in self.headings.iter().filter(|x| options.includes(x.level)) {
+            TocEntry::insert(&mut entries, heading.clone());
+        }
+        if entries.is_empty() {
+            eprintln!("⚠️ {:?} <toc> found no headings to list", self.source_path);
+            return Node::empty()
+        }
+        if !attributes.contains_key("class") {
+            attributes.insert("class", AttributeValueBuf::literal("toc"));
+        }
+        let list = TocEntry::render_list(&entries, options.ordered);
+        Node::Element(Element::new("nav").with_attributes(attributes).with_children(vec![Node::Element(list)]))
+    }
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// PAGE PASS
+// ————————————————————————————————————————————————————————————————————————————
+
+/// Resolves every `<toc>` element of a fully pre-processed page.
+pub fn apply_table_of_contents(page: Node, source_path: &Path) -> Node {
+    let toc_tag = TagBuf::from(TAG_NAME);
+    if page.find_first(&toc_tag).is_none() {
+        return page
+    }
+    let mut levels = Vec::<usize>::new();
+    collect_requested_levels(&page, &mut levels);
+    let mut collector = HeadingCollector {
+        levels,
+        slugs: SlugRegistry::from_existing_ids(&page),
+        headings: Vec::new(),
+    };
+    let page = xml_ast::traversal::apply_element_visitor(page, &mut collector);
+    let mut renderer = TocRenderer { headings: &collector.headings, source_path };
+    xml_ast::traversal::apply_element_visitor(page, &mut renderer)
+}
+
+fn collect_requested_levels(node: &Node, levels: &mut Vec<usize>) {
+    match node {
+        Node::Element(element) if element.tag.as_normalized() == TAG_NAME => {
+            // Invalid values are reported once, when rendering.
+            let mut attributes = element.attributes.clone();
+            let options = TocOptions::take_from(&mut attributes, None);
+            for level in (1..=6).filter(|x| options.includes(*x)) {
+                if !levels.contains(&level) {
+                    levels.push(level);
+                }
+            }
+        }
+        Node::Element(element) => {
+            for child in element.children.iter() {
+                collect_requested_levels(child, levels);
+            }
+        }
+        Node::Fragment(fragment) => {
+            for child in fragment.iter() {
+                collect_requested_levels(child, levels);
+            }
+        }
+        Node::Text(_) => (),
+    }
+}
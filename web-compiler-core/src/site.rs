@@ -0,0 +1,274 @@
+//! Site-level outputs generated from the set of emitted pages.
+pub mod sitemap;
+pub mod robots;
+pub mod feeds;
+pub mod tree;
+
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use web_compiler_types::LastModifiedSource;
+
+/// Escapes text for use in XML content and attribute values.
+pub fn escape_xml(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for char in value.chars() {
+        match char {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(char),
+        }
+    }
+    escaped
+}
+
+/// Last modification date (`YYYY-MM-DD`) of a source file.
+pub fn last_modified_date(source_file: &Path, source: LastModifiedSource) -> Option<String> {
+    if source == LastModifiedSource::GitCommit {
+        if let Some(date) = git_commit_date(source_file) {
+            return Some(date)
+        }
+    }
+    let modified = std::fs::metadata(source_file).ok()?.modified().ok()?;
+    Some(format_date(modified))
+}
+
+fn git_commit_date(source_file: &Path) -> Option<String> {
+    let output = std::process::Command::new("git")
+        .args(["log", "-1", "--format=%cs", "--"])
+        .arg(source_file)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None
+    }
+    let date = String::from_utf8(output.stdout).ok()?;
+    let date = date.trim();
+    if date.is_empty() {
+        // Not committed yet.
+        return None
+    }
+    Some(date.to_string())
+}
+
+/// Formats a timestamp as a UTC `YYYY-MM-DD` date.
+pub fn format_date(time: SystemTime) -> String {
+    let (year, month, day) = civil_date(time);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// Formats a timestamp as an RFC 3339 UTC date-time.
+pub fn format_rfc3339(time: SystemTime) -> String {
+    let (year, month, day) = civil_date(time);
+    let (hour, minute, second) = clock_time(time);
+    format!("{year:04}-{month:02}-{day:02}T{hour:02}:{minute:02}:{second:02}Z")
+}
+
+/// Formats a timestamp as an RFC 2822 UTC date-time (as used by RSS).
+pub fn format_rfc2822(time: SystemTime) -> String {
+    const WEEKDAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+    const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+    let (year, month, day) = civil_date(time);
+    let weekday = WEEKDAYS[unix_seconds(time).div_euclid(86_400).rem_euclid(7) as usize];
+    let (hour, minute, second) = clock_time(time);
+    let month = MONTHS[(month - 1) as usize];
+    format!("{weekday}, {day:02} {month} {year:04} {hour:02}:{minute:02}:{second:02} +0000")
+}
+
+/// Parses `YYYY-MM-DD`, optionally followed by `THH:MM[:SS[.fraction]]` and a
+/// `Z` or `±HH:MM` offset (a missing offset is taken as UTC). Fractional
+/// seconds are dropped.
+pub fn parse_date(value: &str) -> Option<SystemTime> {
+    let value = value.trim();
+    let (date, time) = match value.split_once(|c| c == 'T' || c == ' ') {
+        Some((date, time)) => (date, Some(time)),
+        None => (value, None),
+    };
+    let mut date_parts = date.splitn(3, '-');
+    let year = date_parts.next()?.parse::<i64>().ok()?;
+    let month = date_parts.next()?.parse::<u32>().ok().filter(|x| (1..=12).contains(x))?;
+    let day = date_parts.next()?.parse::<u32>().ok().filter(|x| (1..=days_in_month(year, month)).contains(x))?;
+    let mut seconds = days_from_civil(year, month, day) * 86_400;
+    if let Some(time) = time {
+        let (clock, offset) = match time.find(|c| c == 'Z' || c == '+' || c == '-') {
+            Some(index) => time.split_at(index),
+            None => (time, ""),
+        };
+        let mut clock_parts = clock.split(':');
+        let hour = clock_parts.next()?.parse::<i64>().ok().filter(|x| (0..24).contains(x))?;
+        let minute = clock_parts.next().map(|x| x.parse::<i64>().ok()).unwrap_or(Some(0)).filter(|x| (0..60).contains(x))?;
+        let second = clock_parts
+            .next()
+            .map(|x| x.split('.').next().unwrap_or(x).parse::<i64>().ok())
+            .unwrap_or(Some(0))
+            .filter(|x| (0..=60).contains(x))?;
+        if clock_parts.next().is_some() {
+            return None
+        }
+        seconds += hour * 3600 + minute * 60 + second;
+        let offset_sign = match offset.chars().next() {
+            Some('+') => Some(1),
+            Some('-') => Some(-1),
+            Some('Z') if offset.len() == 1 => None,
+            Some(_) => return None,
+            None => None,
+        };
+        if let Some(sign) = offset_sign {
+            let (offset_hour, offset_minute) = offset[1..].split_once(':').unwrap_or((&offset[1..], "0"));
+            let offset_hour = offset_hour.parse::<i64>().ok().filter(|x| (0..24).contains(x))?;
+            let offset_minute = offset_minute.parse::<i64>().ok().filter(|x| (0..60).contains(x))?;
+            seconds -= sign * (offset_hour * 3600 + offset_minute * 60);
+        }
+    }
+    match u64::try_from(seconds) {
+        Ok(seconds) => Some(UNIX_EPOCH + Duration::from_secs(seconds)),
+        Err(_) => UNIX_EPOCH.checked_sub(Duration::from_secs(seconds.unsigned_abs())),
+    }
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if year.rem_euclid(4) == 0 && (year.rem_euclid(100) != 0 || year.rem_euclid(400) == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Whole seconds since the Unix epoch, negative (rounded down) before it.
+fn unix_seconds(time: SystemTime) -> i64 {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(duration) => duration.as_secs() as i64,
+        Err(error) => {
+            let duration = error.duration();
+            -(duration.as_secs() as i64) - i64::from(duration.subsec_nanos() > 0)
+        }
+    }
+}
+
+/// UTC `(hour, minute, second)`.
+fn clock_time(time: SystemTime) -> (i64, i64, i64) {
+    let seconds_of_day = unix_seconds(time).rem_euclid(86_400);
+    (seconds_of_day / 3600, (seconds_of_day % 3600) / 60, seconds_of_day % 60)
+}
+
+/// Proleptic Gregorian `(year, month, day)` to days since the Unix epoch.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month = month as i64;
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Days since the Unix epoch to a proleptic Gregorian `(year, month, day)`.
+fn civil_date(time: SystemTime) -> (i64, u32, u32) {
+    let days = unix_seconds(time).div_euclid(86_400) + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// TESTS
+// ————————————————————————————————————————————————————————————————————————————
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(seconds: i64) -> SystemTime {
+        match u64::try_from(seconds) {
+            Ok(seconds) => UNIX_EPOCH + Duration::from_secs(seconds),
+            Err(_) => UNIX_EPOCH - Duration::from_secs(seconds.unsigned_abs()),
+        }
+    }
+
+    #[test]
+    fn formats_the_epoch() {
+        assert_eq!(format_date(UNIX_EPOCH), "1970-01-01");
+        assert_eq!(format_rfc3339(UNIX_EPOCH), "1970-01-01T00:00:00Z");
+        assert_eq!(format_rfc2822(UNIX_EPOCH), "Thu, 01 Jan 1970 00:00:00 +0000");
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(parse_date("1970-01-01"), Some(UNIX_EPOCH));
+    }
+
+    #[test]
+    fn formats_leap_days() {
+        // 2024-02-29T12:34:56Z
+        let leap_day = at(1_709_210_096);
+        assert_eq!(format_rfc3339(leap_day), "2024-02-29T12:34:56Z");
+        assert_eq!(format_rfc2822(leap_day), "Thu, 29 Feb 2024 12:34:56 +0000");
+        assert_eq!(parse_date("2024-02-29T12:34:56Z"), Some(leap_day));
+        assert_eq!(format_date(at(1_709_251_200)), "2024-03-01");
+        // Divisible by 400, so a leap year; 1900 isn't.
+        assert_eq!(format_date(at(days_from_civil(2000, 2, 29) * 86_400)), "2000-02-29");
+        assert!(parse_date("2000-02-29").is_some());
+        assert!(parse_date("1900-02-29").is_none());
+        assert!(parse_date("2023-02-29").is_none());
+    }
+
+    #[test]
+    fn handles_dates_before_1970() {
+        assert_eq!(days_from_civil(1969, 12, 31), -1);
+        assert_eq!(days_from_civil(1600, 3, 1), -135_080);
+        let time = parse_date("1969-07-20T20:17:40Z").unwrap();
+        assert_eq!(time, at(-14_182_940));
+        assert_eq!(format_rfc3339(time), "1969-07-20T20:17:40Z");
+        assert_eq!(format_rfc2822(time), "Sun, 20 Jul 1969 20:17:40 +0000");
+        // One second before the epoch is still the last day of 1969.
+        assert_eq!(format_rfc3339(at(-1)), "1969-12-31T23:59:59Z");
+        assert_eq!(format_date(UNIX_EPOCH - Duration::from_millis(1)), "1969-12-31");
+    }
+
+    #[test]
+    fn applies_offsets() {
+        let utc = parse_date("2024-06-01T12:00:00Z").unwrap();
+        assert_eq!(parse_date("2024-06-01T12:00"), Some(utc));
+        assert_eq!(parse_date("2024-06-01 12:00:00"), Some(utc));
+        assert_eq!(parse_date("2024-06-01T14:30:00+02:30"), Some(utc));
+        assert_eq!(parse_date("2024-06-01T07:00:00-05:00"), Some(utc));
+        // Crossing midnight.
+        assert_eq!(format_rfc3339(parse_date("2024-06-01T01:00:00+03:00").unwrap()), "2024-05-31T22:00:00Z");
+    }
+
+    #[test]
+    fn drops_fractional_seconds() {
+        let utc = parse_date("2024-06-01T12:00:05Z").unwrap();
+        assert_eq!(parse_date("2024-06-01T12:00:05.999Z"), Some(utc));
+        assert_eq!(parse_date("2024-06-01T14:00:05.5+02:00"), Some(utc));
+    }
+
+    #[test]
+    fn rejects_invalid_dates() {
+        for value in [
+            "",
+            "2024",
+            "2024-06",
+            "2024-13-01",
+            "2024-00-10",
+            "2024-04-31",
+            "June 1, 2024",
+            "2024-06-01T",
+            "2024-06-01T24:00",
+            "2024-06-01T12:60",
+            "2024-06-01T12:00:00:00",
+            "2024-06-01T12:00+25:00",
+            "2024-06-01T12:00Q",
+            "2024-06-01T12:00ZZ",
+        ] {
+            assert_eq!(parse_date(value), None, "{value:?}");
+        }
+    }
+}
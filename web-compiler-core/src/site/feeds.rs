@@ -0,0 +1,305 @@
+//! RSS 2.0, Atom and JSON Feed generation for `[[feeds]]` page collections.
+//!
+//! Item bodies come from the post-processed page trees produced by
+//! `SourcePipeline::execute`, with relative links made absolute against the
+//! site's base URL.
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use macro_types::breadcrumbs::get_title;
+use macro_types::helpers::srcset::SrcsetCandidate;
+use macro_types::page_meta::PageMetadata;
+use macro_types::path_utils::is_external_url;
+use macro_types::project::{split_link_suffix, FileInput, ProjectContext};
+use web_compiler_types::{FeedConfig, FeedFormat, SiteConfig};
+use xml_ast::format::FormatSettings;
+use xml_ast::traversal::ElementVisitor;
+use xml_ast::{AttributeMap, Fragment, Node, TagBuf};
+
+use super::escape_xml;
+use crate::markup::rewrites::attributes::{REQUIRES_REGULAR_DEPENDENCY_TRACKING, REQUIRES_SRC_SET_DEPENDENCY_TRACKING};
+
+// ————————————————————————————————————————————————————————————————————————————
+// SELECTION
+// ————————————————————————————————————————————————————————————————————————————
+
+/// Pages are selected by source path glob and/or `<page-meta tags>`; when
+/// both are given a page must match both.
+pub fn is_feed_member(feed: &FeedConfig, page: &FileInput, project_context: &ProjectContext) -> bool {
+    if feed.pages.is_none() && feed.tag.is_none() {
+        return false
+    }
+    if let Some(pattern) = feed.pages.as_ref() {
+        let pattern = match glob::Pattern::new(pattern) {
+            Ok(x) => x,
+            Err(error) => {
+                eprintln!("⚠️ feed {:?}: invalid pages pattern {pattern:?}: {error}", feed.title);
+                return false
+            }
+        };
+        if !pattern.matches_path(&path_clean::clean(page.source_file())) {
+            return false
+        }
+    }
+    if let Some(tag) = feed.tag.as_ref() {
+        let metadata = PageMetadata::load(page, project_context);
+        if !metadata.tags().iter().any(|x| x.eq_ignore_ascii_case(tag)) {
+            return false
+        }
+    }
+    true
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// ITEMS
+// ————————————————————————————————————————————————————————————————————————————
+
+#[derive(Debug, Clone)]
+pub struct FeedItem {
+    pub title: String,
+    pub url: String,
+    pub date: Option<SystemTime>,
+    pub description: Option<String>,
+    pub tags: Vec<String>,
+    pub content_html: String,
+}
+
+/// Builds a feed item from a page and its rendered (post-processed) tree.
+pub fn feed_item(
+    page: &FileInput,
+    rendered: &Node,
+    site: &SiteConfig,
+    project_context: &ProjectContext,
+) -> FeedItem {
+    let metadata = PageMetadata::load(page, project_context);
+    let public_path = page.resolved_public_path(project_context);
+    let title = get_title(page, project_context).unwrap_or_else(|| {
+        page.source_file()
+            .file_stem()
+            .map(|x| x.to_string_lossy().to_string())
+            .unwrap_or_default()
+    });
+    let date = metadata.date().and_then(|date| {
+        let parsed = super::parse_date(date);
+        if parsed.is_none() {
+            eprintln!("⚠️ {:?} <page-meta> invalid date {date:?}", page.source_file());
+        }
+        parsed
+    });
+    let body = ["article", "main", "body"]
+        .into_iter()
+        .find_map(|tag| rendered.find_first(&TagBuf::from(tag)))
+        .and_then(|x| x.to_element())
+        .map(|x| x.children)
+        .unwrap_or_else(|| Fragment::from_nodes(vec![rendered.clone()]));
+    let mut visitor = AbsoluteLinks {
+        page_dir: public_path.parent().map(Path::to_path_buf).unwrap_or_default(),
+        page_url: site.absolute_url(public_path.to_str().unwrap_or_default()),
+        site,
+    };
+    let body = xml_ast::traversal::apply_element_visitor(Node::Fragment(body), &mut visitor);
+    FeedItem {
+        title,
+        url: visitor.page_url,
+        date,
+        description: metadata.description().map(ToString::to_string),
+        tags: metadata.tags().into_iter().map(ToString::to_string).collect(),
+        content_html: body.format(FormatSettings::default()),
+    }
+}
+
+/// Most recent first; undated items last, in source order.
+pub fn sort_feed_items(items: &mut Vec<FeedItem>, limit: Option<usize>) {
+    items.sort_by(|left, right| right.date.cmp(&left.date));
+    if let Some(limit) = limit {
+        items.truncate(limit);
+    }
+}
+
+struct AbsoluteLinks<'a> {
+    page_dir: PathBuf,
+    page_url: String,
+    site: &'a SiteConfig,
+}
+
+impl<'a> AbsoluteLinks<'a> {
+    fn absolute(&self, href: &str) -> String {
+        let href = href.trim();
+        if let Some(fragment) = href.strip_prefix('#') {
+            return format!("{}#{fragment}", self.page_url)
+        }
+        if href.is_empty() || is_external_url(href) || href.starts_with("data:") {
+            return href.to_string()
+        }
+        if href.starts_with('/') {
+            return format!("{}{href}", self.site.base_url.trim_end_matches('/'))
+        }
+        let (path, suffix) = split_link_suffix(href);
+        let path = path_clean::clean(self.page_dir.join(path));
+        format!("{}{suffix}", self.site.absolute_url(path.to_str().unwrap_or_default()))
+    }
+}
+
+impl<'a> ElementVisitor for AbsoluteLinks<'a> {
+    fn visit_element(&mut self, tag: TagBuf, mut attributes: AttributeMap, children: Fragment) -> Node {
+        let tag_name = tag.as_normalized().to_string();
+        for (key, value) in attributes.iter_mut() {
+            let key = key.as_str().to_ascii_lowercase();
+            if REQUIRES_REGULAR_DEPENDENCY_TRACKING.contains(&(tag_name.as_str(), key.as_str())) {
+                let absolute = self.absolute(value.as_str());
+                *value.as_mut_string() = absolute;
+            } else if REQUIRES_SRC_SET_DEPENDENCY_TRACKING.contains(&(tag_name.as_str(), key.as_str())) {
+                let candidates = SrcsetCandidate::parse_srcset(value.as_str())
+                    .into_iter()
+                    .map(|SrcsetCandidate { url, descriptor }| SrcsetCandidate { url: self.absolute(&url), descriptor })
+                    .collect::<Vec<_>>();
+                *value.as_mut_string() = SrcsetCandidate::format_srcset(&candidates);
+            }
+        }
+        Node::Element(xml_ast::Element { tag, attributes, children })
+    }
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// RENDERING
+// ————————————————————————————————————————————————————————————————————————————
+
+struct FeedUrls {
+    home: String,
+    feed: String,
+}
+
+fn feed_urls(feed: &FeedConfig, format: FeedFormat, site: &SiteConfig) -> FeedUrls {
+    let feed_path = feed.output.join(format.file_name());
+    FeedUrls {
+        home: site.absolute_url(feed.output.join("index.html").to_str().unwrap_or_default()),
+        feed: site.absolute_url(feed_path.to_str().unwrap_or_default()),
+    }
+}
+
+pub fn render_feed(feed: &FeedConfig, format: FeedFormat, site: &SiteConfig, items: &[FeedItem]) -> String {
+    match format {
+        FeedFormat::Rss => render_rss(feed, site, items),
+        FeedFormat::Atom => render_atom(feed, site, items),
+        FeedFormat::Json => render_json_feed(feed, site, items),
+    }
+}
+
+fn render_rss(feed: &FeedConfig, site: &SiteConfig, items: &[FeedItem]) -> String {
+    let urls = feed_urls(feed, FeedFormat::Rss, site);
+    let mut lines = vec![
+        String::from(r#"<?xml version="1.0" encoding="UTF-8"?>"#),
+        String::from(r#"<rss version="2.0" xmlns:atom="http://www.w3.org/2005/Atom">"#),
+        String::from("  <channel>"),
+        format!("    <title>{}</title>", escape_xml(&feed.title)),
+        format!("    <link>{}</link>", escape_xml(&urls.home)),
+        format!("    <description>{}</description>", escape_xml(feed.description.as_deref().unwrap_or(&feed.title))),
+        format!(r#"    <atom:link href="{}" rel="self" type="application/rss+xml"/>"#, escape_xml(&urls.feed)),
+    ];
+    if let Some(updated) = items.iter().filter_map(|x| x.date).max() {
+        lines.push(format!("    <lastBuildDate>{}</lastBuildDate>", super::format_rfc2822(updated)));
+    }
+    for item in items {
+        lines.push(String::from("    <item>"));
+        lines.push(format!("      <title>{}</title>", escape_xml(&item.title)));
+        lines.push(format!("      <link>{}</link>", escape_xml(&item.url)));
+        lines.push(format!(r#"      <guid isPermaLink="true">{}</guid>"#, escape_xml(&item.url)));
+        if let Some(date) = item.date {
+            lines.push(format!("      <pubDate>{}</pubDate>", super::format_rfc2822(date)));
+        }
+        for tag in item.tags.iter() {
+            lines.push(format!("      <category>{}</category>", escape_xml(tag)));
+        }
+        // RSS has no separate summary; prefer the full body.
+        let description = if item.content_html.trim().is_empty() {
+            item.description.clone().unwrap_or_default()
+        } else {
+            item.content_html.clone()
+        };
+        lines.push(format!("      <description>{}</description>", escape_xml(&description)));
+        lines.push(String::from("    </item>"));
+    }
+    lines.push(String::from("  </channel>"));
+    lines.push(String::from("</rss>"));
+    lines.push(String::default());
+    lines.join("\n")
+}
+
+fn render_atom(feed: &FeedConfig, site: &SiteConfig, items: &[FeedItem]) -> String {
+    let urls = feed_urls(feed, FeedFormat::Atom, site);
+    let updated = items
+        .iter()
+        .filter_map(|x| x.date)
+        .max()
+        .unwrap_or_else(SystemTime::now);
+    let mut lines = vec![
+        String::from(r#"<?xml version="1.0" encoding="UTF-8"?>"#),
+        String::from(r#"<feed xmlns="http://www.w3.org/2005/Atom">"#),
+        format!("  <title>{}</title>", escape_xml(&feed.title)),
+        format!("  <id>{}</id>", escape_xml(&urls.feed)),
+        format!(r#"  <link href="{}"/>"#, escape_xml(&urls.home)),
+        format!(r#"  <link href="{}" rel="self"/>"#, escape_xml(&urls.feed)),
+        format!("  <updated>{}</updated>", super::format_rfc3339(updated)),
+    ];
+    if let Some(description) = feed.description.as_ref() {
+        lines.push(format!("  <subtitle>{}</subtitle>", escape_xml(description)));
+    }
+    for item in items {
+        let item_updated = item.date.unwrap_or(updated);
+        lines.push(String::from("  <entry>"));
+        lines.push(format!("    <title>{}</title>", escape_xml(&item.title)));
+        lines.push(format!("    <id>{}</id>", escape_xml(&item.url)));
+        lines.push(format!(r#"    <link href="{}"/>"#, escape_xml(&item.url)));
+        lines.push(format!("    <updated>{}</updated>", super::format_rfc3339(item_updated)));
+        if let Some(date) = item.date {
+            lines.push(format!("    <published>{}</published>", super::format_rfc3339(date)));
+        }
+        for tag in item.tags.iter() {
+            lines.push(format!(r#"    <category term="{}"/>"#, escape_xml(tag)));
+        }
+        if let Some(description) = item.description.as_ref() {
+            lines.push(format!("    <summary>{}</summary>", escape_xml(description)));
+        }
+        lines.push(format!(r#"    <content type="html">{}</content>"#, escape_xml(&item.content_html)));
+        lines.push(String::from("  </entry>"));
+    }
+    lines.push(String::from("</feed>"));
+    lines.push(String::default());
+    lines.join("\n")
+}
+
+fn render_json_feed(feed: &FeedConfig, site: &SiteConfig, items: &[FeedItem]) -> String {
+    let urls = feed_urls(feed, FeedFormat::Json, site);
+    let items = items
+        .iter()
+        .map(|item| {
+            let mut value = serde_json::json!({
+                "id": item.url,
+                "url": item.url,
+                "title": item.title,
+                "content_html": item.content_html,
+            });
+            if let Some(description) = item.description.as_ref() {
+                value["summary"] = serde_json::Value::from(description.as_str());
+            }
+            if let Some(date) = item.date {
+                value["date_published"] = serde_json::Value::from(super::format_rfc3339(date));
+            }
+            if !item.tags.is_empty() {
+                value["tags"] = serde_json::Value::from(item.tags.clone());
+            }
+            value
+        })
+        .collect::<Vec<_>>();
+    let mut value = serde_json::json!({
+        "version": "https://jsonfeed.org/version/1.1",
+        "title": feed.title,
+        "home_page_url": urls.home,
+        "feed_url": urls.feed,
+        "items": items,
+    });
+    if let Some(description) = feed.description.as_ref() {
+        value["description"] = serde_json::Value::from(description.as_str());
+    }
+    serde_json::to_string_pretty(&value).unwrap()
+}
@@ -0,0 +1,26 @@
+//! `robots.txt` generation.
+use web_compiler_types::SiteConfig;
+
+pub fn render_robots(site: &SiteConfig) -> String {
+    let robots = &site.robots;
+    let mut lines = vec![format!("User-agent: {}", robots.user_agent)];
+    for path in robots.allow.iter() {
+        lines.push(format!("Allow: {path}"));
+    }
+    for path in robots.disallow.iter() {
+        lines.push(format!("Disallow: {path}"));
+    }
+    if robots.allow.is_empty() && robots.disallow.is_empty() {
+        lines.push(String::from("Allow: /"));
+    }
+    if site.sitemap.enabled {
+        lines.push(String::default());
+        lines.push(format!("Sitemap: {}", site.absolute_url("sitemap.xml")));
+    }
+    if let Some(extra) = robots.extra.as_ref() {
+        lines.push(String::default());
+        lines.push(extra.trim_end().to_string());
+    }
+    lines.push(String::default());
+    lines.join("\n")
+}
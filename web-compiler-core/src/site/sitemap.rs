@@ -0,0 +1,78 @@
+//! `sitemap.xml` generation.
+use std::path::PathBuf;
+
+use macro_types::page_meta::PageMetadata;
+use macro_types::project::{FileInput, ProjectContext, ResolvedDependencies};
+use web_compiler_types::SiteConfig;
+
+use super::escape_xml;
+
+#[derive(Debug, Clone)]
+pub struct SitemapEntry {
+    pub loc: String,
+    pub lastmod: Option<String>,
+    pub changefreq: Option<String>,
+    pub priority: Option<f32>,
+}
+
+/// One entry per emitted page, minus pages excluded via `<page-meta>`.
+pub fn sitemap_entries(
+    pages: &[FileInput],
+    resolved_dependencies: &ResolvedDependencies,
+    site: &SiteConfig,
+    project_context: &ProjectContext,
+) -> Vec<SitemapEntry> {
+    let mut entries = pages
+        .iter()
+        .filter_map(|page| {
+            let public_path = page.resolved_public_path(project_context);
+            if !resolved_dependencies.emitted_files.contains(&public_path) {
+                return None
+            }
+            let metadata = PageMetadata::load(page, project_context);
+            if metadata.is_sitemap_excluded() {
+                return None
+            }
+            let lastmod = metadata
+                .get("lastmod")
+                .map(ToString::to_string)
+                .or_else(|| {
+                    site.sitemap.lastmod.and_then(|source| super::last_modified_date(page.source_file(), source))
+                });
+            let loc = site.absolute_url(public_path.to_str()?);
+            Some((public_path, SitemapEntry {
+                loc,
+                lastmod,
+                changefreq: metadata.sitemap_changefreq().map(ToString::to_string),
+                priority: metadata.sitemap_priority(),
+            }))
+        })
+        .collect::<Vec<(PathBuf, SitemapEntry)>>();
+    entries.sort_by(|(left, _), (right, _)| left.cmp(right));
+    entries.dedup_by(|(left, _), (right, _)| left == right);
+    entries.into_iter().map(|(_, entry)| entry).collect()
+}
+
+pub fn render_sitemap(entries: &[SitemapEntry]) -> String {
+    let mut lines = vec![
+        String::from(r#"<?xml version="1.0" encoding="UTF-8"?>"#),
+        String::from(r#"<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">"#),
+    ];
+    for entry in entries {
+        lines.push(String::from("  <url>"));
+        lines.push(format!("    <loc>{}</loc>", escape_xml(&entry.loc)));
+        if let Some(lastmod) = entry.lastmod.as_ref() {
+            lines.push(format!("    <lastmod>{}</lastmod>", escape_xml(lastmod)));
+        }
+        if let Some(changefreq) = entry.changefreq.as_ref() {
+            lines.push(format!("    <changefreq>{}</changefreq>", escape_xml(changefreq)));
+        }
+        if let Some(priority) = entry.priority {
+            lines.push(format!("    <priority>{priority:.1}</priority>"));
+        }
+        lines.push(String::from("  </url>"));
+    }
+    lines.push(String::from("</urlset>"));
+    lines.push(String::default());
+    lines.join("\n")
+}
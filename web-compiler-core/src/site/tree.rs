@@ -0,0 +1,195 @@
+//! Site tree files: the pages of the site declared as a `<page>` hierarchy,
+//! with or instead of `[[sources]]` globs.
+//!
+//! ```html
+//! <page src="pages/index.html" target="index.html">
+//!     <title>Home</title>
+//!     <page src="pages/about.html"></page>
+//!     <page src="pages/blog/index.html">
+//!         <title>Blog</title>
+//!         <page-files src="pages/blog/*.html"></page-files>
+//!     </page>
+//! </page>
+//! ```
+//!
+//! Paths are relative to the site tree file. A page without a `target` is
+//! emitted next to its parent page, or in a directory named after it when
+//! the parent isn't an index page (`blog/post.html` under `blog.html`);
+//! nested `index.html` pages keep their directory name. `<title>` overrides
+//! the page's `<define-title>`, and `<page-files>` adds the pages matching
+//! its glob that aren't listed elsewhere in the file, in path order.
+//!
+//! `template` on `<page>` or `<page-files>` overrides the global template.
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use macro_types::breadcrumbs::{DeclaredHierarchy, DeclaredPage};
+use macro_types::project::FileInput;
+use web_compiler_types::CompilerInputRule;
+use xml_ast::{Element, Node};
+
+use crate::error::ManifestError;
+
+pub const PAGE_TAG: &'static str = "page";
+pub const PAGE_FILES_TAG: &'static str = "page-files";
+
+#[derive(Debug, Clone, Default)]
+pub struct SiteTree {
+    /// Every page, parents before their children.
+    pub sources: Vec<CompilerInputRule>,
+    pub hierarchy: DeclaredHierarchy,
+}
+
+impl SiteTree {
+    pub fn load(file_path: &Path) -> Result<Self, ManifestError> {
+        let text = std::fs::read_to_string(file_path).map_err(|error| {
+            ManifestError::Read { path: file_path.to_path_buf(), error }
+        })?;
+        let tree = xml_ast::parse_fragment_str(&text).output;
+        let mut loader = SiteTreeLoader {
+            file_path,
+            directory: file_path.parent().unwrap_or(Path::new("")),
+            explicit: HashSet::new(),
+            site_tree: SiteTree::default(),
+        };
+        loader.collect_explicit(&tree);
+        loader.visit(&tree, None)?;
+        Ok(loader.site_tree)
+    }
+    pub fn contains(&self, source: &Path) -> bool {
+        self.hierarchy.pages.contains_key(source)
+    }
+}
+
+struct SiteTreeLoader<'a> {
+    file_path: &'a Path,
+    directory: &'a Path,
+    /// Sources of every `<page>`, which `<page-files>` leaves out.
+    explicit: HashSet<PathBuf>,
+    site_tree: SiteTree,
+}
+
+/// The enclosing `<page>`.
+struct ParentPage {
+    source: PathBuf,
+    target: PathBuf,
+}
+
+impl<'a> SiteTreeLoader<'a> {
+    fn resolve(&self, path: &str) -> PathBuf {
+        path_clean::clean(self.directory.join(path.trim()))
+    }
+    fn invalid(&self, message: String) -> ManifestError {
+        ManifestError::InvalidSiteTree { path: self.file_path.to_path_buf(), message }
+    }
+    fn collect_explicit(&mut self, node: &Node) {
+        match node {
+            Node::Element(element) => {
+                if element.tag.as_normalized() == PAGE_TAG {
+                    if let Some(src) = element.attributes.get("src") {
+                        self.explicit.insert(self.resolve(src.as_str()));
+                    }
+                }
+                element.children.iter().for_each(|x| self.collect_explicit(x));
+            }
+            Node::Fragment(fragment) => fragment.iter().for_each(|x| self.collect_explicit(x)),
+            Node::Text(_) => (),
+        }
+    }
+    fn visit(&mut self, node: &Node, parent: Option<&ParentPage>) -> Result<(), ManifestError> {
+        match node {
+            Node::Element(element) => match element.tag.as_normalized() {
+                PAGE_TAG => self.visit_page(element, parent),
+                PAGE_FILES_TAG => self.visit_page_files(element, parent),
+                "title" => Ok(()),
+                _ => element.children.iter().try_for_each(|x| self.visit(x, parent)),
+            },
+            Node::Fragment(fragment) => fragment.iter().try_for_each(|x| self.visit(x, parent)),
+            Node::Text(_) => Ok(()),
+        }
+    }
+    fn visit_page(&mut self, element: &Element, parent: Option<&ParentPage>) -> Result<(), ManifestError> {
+        let src = element.attributes
+            .get("src")
+            .ok_or_else(|| self.invalid(format!("<{PAGE_TAG}> without a src")))?;
+        let source = self.resolve(src.as_str());
+        let target = element.attributes
+            .get("target")
+            .map(|x| path_clean::clean(x.as_str().trim()))
+            .unwrap_or_else(|| default_target(parent.map(|x| x.target.as_path()), &source));
+        let title = element.children
+            .iter()
+            .filter_map(|x| x.as_element())
+            .find(|x| x.tag.as_normalized() == "title")
+            .map(|x| x.text_contents().join("").trim().to_string())
+            .filter(|x| !x.is_empty());
+        let template = element.attributes.get("template").map(|x| self.resolve(x.as_str()));
+        self.push_page(source.clone(), target.clone(), title, template, parent)?;
+        let page = ParentPage { source, target };
+        element.children.iter().try_for_each(|x| self.visit(x, Some(&page)))
+    }
+    fn visit_page_files(&mut self, element: &Element, parent: Option<&ParentPage>) -> Result<(), ManifestError> {
+        let src = element.attributes
+            .get("src")
+            .ok_or_else(|| self.invalid(format!("<{PAGE_FILES_TAG}> without a src")))?;
+        let pattern = self.resolve(src.as_str()).to_string_lossy().into_owned();
+        let paths = crate::common::path_utils::resolve_file_path_patern(&pattern)
+            .map_err(|error| ManifestError::InvalidSourcePattern { pattern: pattern.clone(), message: error.to_string() })?;
+        let template = element.attributes.get("template").map(|x| self.resolve(x.as_str()));
+        for source in paths.into_iter().map(path_clean::clean) {
+            if self.explicit.contains(&source) || self.site_tree.contains(&source) {
+                continue
+            }
+            let target = default_target(parent.map(|x| x.target.as_path()), &source);
+            self.push_page(source, target, None, template.clone(), parent)?;
+        }
+        Ok(())
+    }
+    fn push_page(
+        &mut self,
+        source: PathBuf,
+        target: PathBuf,
+        title: Option<String>,
+        template: Option<PathBuf>,
+        parent: Option<&ParentPage>,
+    ) -> Result<(), ManifestError> {
+        if !source.is_file() {
+            return Err(ManifestError::MissingFile { key: "<page> src", path: source })
+        }
+        if self.site_tree.contains(&source) {
+            return Err(self.invalid(format!("{source:?} is listed more than once")))
+        }
+        let declared = DeclaredPage {
+            parent: parent.map(|x| x.source.clone()),
+            title,
+        };
+        self.site_tree.hierarchy.pages.insert(source.clone(), declared);
+        self.site_tree.sources.push(CompilerInputRule {
+            source: FileInput {
+                source,
+                public: Some(target),
+            },
+            local_template: template,
+        });
+        Ok(())
+    }
+}
+
+/// Next to an index parent, in a directory named after any other parent.
+fn default_target(parent: Option<&Path>, source: &Path) -> PathBuf {
+    let file_name = source.file_name().map(PathBuf::from).unwrap_or_default();
+    // Nested index pages keep their directory (`cli/index.html`).
+    let name = match source.parent().and_then(|x| x.file_name()) {
+        Some(directory) if file_name == Path::new("index.html") && parent.is_some() => {
+            Path::new(directory).join(file_name)
+        }
+        _ => file_name,
+    };
+    match parent {
+        None => name,
+        Some(parent) if parent.file_name().is_some_and(|x| x == "index.html") => {
+            parent.parent().unwrap_or(Path::new("")).join(name)
+        }
+        Some(parent) => parent.with_extension("").join(name),
+    }
+}
@@ -0,0 +1,109 @@
+//! Production stylesheet optimizations over the rendered pages.
+//!
+//! With `[css] critical = true`, pages are written after the stylesheets
+//! are compiled. Every `<link rel="stylesheet">` to a compiled local
+//! stylesheet is preceded by a `<style>` with the rules that match an element
+//! of the page, and is itself loaded without blocking rendering:
+//!
+//! ```html
+//! <style>/* rules used by this page */</style>
+//! <link rel="stylesheet" href="main.css" media="print" onload="this.onload=null;this.media='all'">
+//! <noscript><link rel="stylesheet" href="main.css"></noscript>
+//! ```
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use css::matching::DocumentTree;
+use macro_types::project::split_link_suffix;
+use xml_ast::{AttributeMap, AttributeValueBuf, Element, Fragment, Node, TagBuf};
+use xml_ast::traversal::ElementVisitor;
+
+// ————————————————————————————————————————————————————————————————————————————
+// CRITICAL CSS
+// ————————————————————————————————————————————————————————————————————————————
+
+/// Inlines the critical CSS of `page` (at the public path `page_path`);
+/// `stylesheets` maps the public paths of compiled stylesheets to their
+/// contents.
+pub fn inline_critical_css(
+    page: Node,
+    page_path: &Path,
+    stylesheets: &HashMap<PathBuf, String>,
+    minify: bool,
+) -> Node {
+    let tree = DocumentTree::new(&page);
+    let mut visitor = CriticalCssVisitor { tree: &tree, page_path, stylesheets, minify };
+    xml_ast::traversal::apply_element_visitor(page, &mut visitor)
+}
+
+struct CriticalCssVisitor<'a> {
+    tree: &'a DocumentTree,
+    page_path: &'a Path,
+    stylesheets: &'a HashMap<PathBuf, String>,
+    minify: bool,
+}
+
+impl<'a> ElementVisitor for CriticalCssVisitor<'a> {
+    fn visit_element(&mut self, tag: TagBuf, attributes: AttributeMap, children: Fragment) -> Node {
+        let link = Element { tag, attributes, children };
+        let stylesheet_path = match stylesheet_link_target(&link, self.page_path) {
+            Some(x) => x,
+            None => return Node::Element(link),
+        };
+        let source = match self.stylesheets.get(&stylesheet_path) {
+            Some(x) => x,
+            None => return Node::Element(link),
+        };
+        let critical = css::critical::critical_css(source, &stylesheet_path, self.tree, self.page_path, self.minify);
+        let critical = match critical {
+            Ok(x) => x,
+            Err(error) => {
+                eprintln!("⚠️ {:?} failed to extract critical CSS from {stylesheet_path:?}: {error}", self.page_path);
+                return Node::Element(link)
+            }
+        };
+        let mut nodes = Vec::with_capacity(3);
+        if !critical.trim().is_empty() {
+            nodes.push(Node::element("style", AttributeMap::default(), vec![Node::text(critical)]));
+        }
+        nodes.push(Node::Element(async_stylesheet_link(&link)));
+        nodes.push(Node::element("noscript", AttributeMap::default(), vec![Node::Element(link)]));
+        Node::Fragment(Fragment::from_nodes(nodes))
+    }
+}
+
+/// The public path of the local stylesheet `element` links to, if it is a
+/// `<link rel="stylesheet">` that isn't already loaded asynchronously.
+fn stylesheet_link_target(element: &Element, page_path: &Path) -> Option<PathBuf> {
+    if element.tag.as_normalized() != "link" {
+        return None
+    }
+    let is_stylesheet = element.attributes
+        .get("rel")
+        .is_some_and(|x| x.as_str().split_ascii_whitespace().any(|x| x.eq_ignore_ascii_case("stylesheet")));
+    if !is_stylesheet || element.attributes.contains_key("onload") {
+        return None
+    }
+    let href = element.attributes.get("href")?.as_str().trim();
+    let (href, _) = split_link_suffix(href);
+    if href.is_empty() || href.contains(':') || href.starts_with("//") {
+        return None
+    }
+    let target = match href.strip_prefix('/') {
+        Some(rooted) => PathBuf::from(rooted),
+        None => page_path.parent().unwrap_or(Path::new("")).join(href),
+    };
+    Some(path_clean::clean(target))
+}
+
+/// `link` with `media="print"`, switched back to its original media once loaded.
+fn async_stylesheet_link(link: &Element) -> Element {
+    let mut link = link.clone();
+    let media = link.attributes
+        .remove("media")
+        .map(|x| x.as_str().replace('\'', "\\'"))
+        .unwrap_or_else(|| String::from("all"));
+    link.attributes.insert("media", AttributeValueBuf::literal("print"));
+    link.attributes.insert("onload", AttributeValueBuf::literal(format!("this.onload=null;this.media='{media}'")));
+    link
+}
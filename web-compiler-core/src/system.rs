@@ -1,11 +1,17 @@
 //! Types for defining the overall compiler.
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Arc;
 
-use macro_types::lexical_env::{Featureset, SourceHostRef};
+use macro_types::lexical_env::{Featureset, MacroIO, SourceHostRef};
 use macro_types::breadcrumbs::SiteTreeLayout;
+use macro_types::page_meta::PageMetadata;
+use macro_types::path_utils::compiled_asset_path;
 use macro_types::project::{FileInput, ProjectContext, ResolvedDependencies};
-use web_compiler_types::{CompilationMode, CompilerFeatureset, CompilerPipeline, CompilerRuntime};
+use web_compiler_types::{CompilationMode, CompilerFeatureset, CompilerInputs, CssOptions, CompilerPipeline, CompilerRuntime, FeedConfig, SiteConfig};
+use xml_ast::Node;
 
+use crate::error::{CompilerError, PipelineError};
 use crate::markup::OutputWriterMode;
 
 
@@ -24,14 +30,54 @@ pub fn web_publishing_compiler_runtime(project: ProjectContext, source_file: Fil
     }
 }
 
-pub fn execute_compiler_pipeline(compiler_pipeline: CompilerPipeline) {
+/// Builds every source and emits its dependencies, returning the resolved
+/// dependency graph (including the link references used by [`crate::check`]).
+///
+/// A page that fails to compile doesn't stop the build: the rest of the site
+/// is emitted, then the failures are returned.
+pub fn execute_compiler_pipeline(mut compiler_pipeline: CompilerPipeline) -> Result<ResolvedDependencies, CompilerError> {
     let compilation_mode = compiler_pipeline.inputs.compilation_mode;
+    if !compiler_pipeline.inputs.drafts {
+        exclude_drafts(&mut compiler_pipeline.inputs);
+    }
     let all_input_rules = compiler_pipeline.inputs.sources
         .iter()
         .map(|x| x.source.clone())
         .collect::<Vec<_>>();
-    let site_tree_layout = SiteTreeLayout::compute(&all_input_rules, &compiler_pipeline.inputs.project);
-    let resolved_dependencies = compiler_pipeline.inputs.sources
+    let profiler = compiler_pipeline.inputs.project.profiler.clone();
+    let site_tree_layout = {
+        let _span = profiler.phase("site layout scan");
+        Arc::new(SiteTreeLayout::compute(
+            &all_input_rules,
+            &compiler_pipeline.inputs.site_hierarchy,
+            &compiler_pipeline.inputs.title_sources,
+            &compiler_pipeline.inputs.project,
+        ))
+    };
+    // Pages whose rendered trees are kept for feed generation.
+    let feed_members = compiler_pipeline.inputs.feeds
+        .iter()
+        .flat_map(|feed| {
+            all_input_rules
+                .iter()
+                .filter(|page| crate::site::feeds::is_feed_member(feed, page, &compiler_pipeline.inputs.project))
+        })
+        .map(|page| page.source.clone())
+        .collect::<HashSet<_>>();
+    let mut rendered_pages = HashMap::<PathBuf, Node>::new();
+    // Pages are written once their stylesheets are compiled.
+    let inline_critical_css = compilation_mode.is_production() && compiler_pipeline.inputs.css.critical;
+    let output_writer_mode = if inline_critical_css {
+        OutputWriterMode::JustReturnNode
+    } else {
+        OutputWriterMode::WriteFile
+    };
+    let mut deferred_pages = Vec::<(FileInput, Node)>::new();
+    let mut page_errors = Vec::<PipelineError>::new();
+    // Every rendered page, for pruning unused stylesheet rules.
+    let mut site_tree = (compilation_mode.is_production() && compiler_pipeline.inputs.css.prune)
+        .then(|| css_site_tree(&compiler_pipeline.inputs.css));
+    let mut resolved_dependencies = compiler_pipeline.inputs.sources
         .iter()
         .map(|input| {
             let global_pipeline_spec = crate::markup::GlobalPipelineSpec {
@@ -40,6 +86,9 @@ pub fn execute_compiler_pipeline(compiler_pipeline: CompilerPipeline) {
                 rules: compiler_pipeline.featureset.rules().to_owned(),
                 project: compiler_pipeline.inputs.project.clone(),
                 global_template: compiler_pipeline.inputs.global_template.clone(),
+                max_include_depth: compiler_pipeline.inputs.max_include_depth,
+                headings: compiler_pipeline.inputs.headings.clone(),
+                images: compiler_pipeline.inputs.images.clone(),
             };
             let mut input_pipeline = crate::markup::SourcePipeline {
                 file_input: input.source.clone(),
@@ -48,9 +97,22 @@ pub fn execute_compiler_pipeline(compiler_pipeline: CompilerPipeline) {
                 all_input_rules: all_input_rules.clone(),
                 resolved_dependencies: ResolvedDependencies::default(),
                 site_tree_layout: site_tree_layout.clone(),
-                output_writer_mode: OutputWriterMode::WriteFile,
+                output_writer_mode: output_writer_mode.clone(),
             };
-            let _ = input_pipeline.execute();
+            match input_pipeline.execute() {
+                Ok(node) => {
+                    if feed_members.contains(&input.source.source) {
+                        rendered_pages.insert(input.source.source.clone(), node.clone());
+                    }
+                    if let Some(site_tree) = site_tree.as_mut() {
+                        site_tree.push_page(&node);
+                    }
+                    if inline_critical_css {
+                        deferred_pages.push((input.source.clone(), node));
+                    }
+                }
+                Err(error) => page_errors.push(error),
+            }
             input_pipeline.resolved_dependencies
         })
         .fold(ResolvedDependencies::default(), |mut acc, item| {
@@ -81,16 +143,81 @@ pub fn execute_compiler_pipeline(compiler_pipeline: CompilerPipeline) {
     let (css_files, mut remaining) = remaining
         .into_iter()
         .partition::<Vec<_>, _>(|x| {
-            x.source.extension() == Some("css".as_ref())
+            x.source.extension() == Some("css".as_ref()) || (css::scss::is_scss_path(&x.source) && is_compiled_link(x))
         });
     // println!("css_files: {css_files:#?}");
-    compile_css(&css_files, &compiler_pipeline.inputs.project, compilation_mode, &mut remaining);
+    let mut compiled_stylesheets = HashMap::<PathBuf, String>::new();
+    {
+        let _span = profiler.phase("css");
+        compile_css(
+            &css_files,
+            &compiler_pipeline.inputs.project,
+            compiler_pipeline.inputs.minify,
+            &mut remaining,
+            &mut resolved_dependencies,
+            site_tree.as_ref(),
+            &mut compiled_stylesheets,
+        );
+    }
+    if inline_critical_css {
+        let _span = profiler.phase("critical css");
+        emit_deferred_pages(
+            deferred_pages,
+            &compiled_stylesheets,
+            &compiler_pipeline.inputs.project,
+            compiler_pipeline.inputs.minify,
+        );
+    }
+    let (js_files, remaining) = remaining
+        .into_iter()
+        .partition::<Vec<_>, _>(|x| {
+            js::is_script_path(&x.source) && (!js::is_typescript_path(&x.source) || is_compiled_link(x))
+        });
+    {
+        let _span = profiler.phase("js");
+        compile_js(&js_files, &compiler_pipeline.inputs.project, compiler_pipeline.inputs.minify, &mut resolved_dependencies);
+    }
     // println!("remaining: {remaining:#?}");
-    emit_assets(&remaining, &compiler_pipeline.inputs.project, compilation_mode);
+    {
+        let _span = profiler.phase("asset emission");
+        emit_assets(&remaining, &compiler_pipeline.inputs.project, compilation_mode);
+    }
+    if let Some(site) = compiler_pipeline.inputs.site.as_ref() {
+        let _span = profiler.phase("site files");
+        let pages = compiler_pipeline.inputs.sources
+            .iter()
+            .map(|x| x.source.clone())
+            .collect::<Vec<_>>();
+        emit_site_files(&pages, site, &compiler_pipeline.inputs.project, &mut resolved_dependencies);
+    }
+    if !compiler_pipeline.inputs.feeds.is_empty() {
+        let _span = profiler.phase("feeds");
+        match compiler_pipeline.inputs.site.as_ref() {
+            Some(site) => {
+                for feed in compiler_pipeline.inputs.feeds.iter() {
+                    emit_feed(feed, site, &all_input_rules, &rendered_pages, &compiler_pipeline.inputs.project, &mut resolved_dependencies);
+                }
+            }
+            None => {
+                eprintln!("⚠️ [[feeds]] require a [site] section with a base_url; skipping feed generation");
+            }
+        }
+    }
+    if !page_errors.is_empty() {
+        return Err(CompilerError::Pages(page_errors))
+    }
+    Ok(resolved_dependencies)
 }
 
-fn compile_css(css_files: &[FileInput], project_context: &ProjectContext, compilation_mode: CompilationMode, remaining: &mut Vec<FileInput>) {
-    let _ = compilation_mode;
+fn compile_css(
+    css_files: &[FileInput],
+    project_context: &ProjectContext,
+    minify: bool,
+    remaining: &mut Vec<FileInput>,
+    resolved_dependencies: &mut ResolvedDependencies,
+    site_tree: Option<&css::matching::DocumentTree>,
+    compiled_stylesheets: &mut HashMap<PathBuf, String>,
+) {
     // let mut resolved_dependencies = ResolvedDependencies::default();
     for css_file in css_files {
         let css_source = css_file.load_source_file();
@@ -105,10 +232,23 @@ fn compile_css(css_files: &[FileInput], project_context: &ProjectContext, compil
             project_context: &project_context,
             file_input: css_file,
         };
-        let css_preprocessor = css::CssPreprocessor::new(source_context);
+        let css_source = if css::scss::is_scss_path(css_file.source_file()) {
+            css::scss::ScssCompiler::new(source_context)
+                .execute(&css_source)
+                .map(|compiled| compiled.value)
+        } else {
+            MacroIO::wrap(css_source)
+        };
         // let environment = &();
-        let css_postprocessor = css::CssPostprocessor::new(source_context);
-        let ( pre_processed, effects ) = css_preprocessor.execute(&css_source).collapse();
+        let css_postprocessor = css::CssPostprocessor::new(source_context).minify(minify);
+        let ( pre_processed, effects ) = css_source
+            .and_then(|css_source| css::CssPreprocessor::new(source_context).execute(&css_source))
+            .collapse();
+        resolved_dependencies.link_references.extend(crate::check::stylesheet_link_references(
+            css_file.source_file(),
+            &effects.stylesheet_urls,
+            project_context,
+        ));
         // let _ = effects; // TODO
         let assets = effects.dependencies
             .iter()
@@ -120,8 +260,26 @@ fn compile_css(css_files: &[FileInput], project_context: &ProjectContext, compil
                 public: None,
             });
         }
-        let post_processed = css_postprocessor.execute(&pre_processed.value);
-        let output_path = css_file.to_output_file_path(project_context);
+        let (mut post_processed, post_effects) = css_postprocessor.execute(&pre_processed.value).collapse();
+        // CSS that fails to parse is reported by both passes.
+        for error in effects.stylesheet_errors.union(&post_effects.stylesheet_errors) {
+            eprintln!("⚠️ {error}");
+        }
+        let public_path = compiled_asset_path(&path_clean::clean(css_file.resolved_public_path(project_context)));
+        let output_path = project_context.output_dir.join(&public_path);
+        if let Some(site_tree) = site_tree {
+            match css::prune::prune_unused_rules(&post_processed.value, site_tree, minify) {
+                Ok(pruned) if pruned.removed > 0 => {
+                    println!("> pruning {} unused rules from {:?}", pruned.removed, output_path);
+                    post_processed.value = pruned.code;
+                    post_processed.modified.mark_modified_mut();
+                }
+                Ok(_) => (),
+                Err(error) => {
+                    eprintln!("⚠️ {:?} skipping unused rule pruning: {error}", css_file.source_file());
+                }
+            }
+        }
         let is_modified = pre_processed.modified.union(post_processed.modified).is_modified();
         let write_or_symlink_output = crate::common::path_utils::WriteOrSymlinkOutput {
             output_file: output_path.as_path(),
@@ -131,6 +289,8 @@ fn compile_css(css_files: &[FileInput], project_context: &ProjectContext, compil
         let _ = is_modified; // TODO: MAYBE USE THIS
         let _ = write_or_symlink_output; // TODO: DISGARD THIS?
         crate::common::path_utils::write_output_file_smart(output_path.as_path(), post_processed.value.as_bytes());
+        resolved_dependencies.include_emitted_file(public_path.clone());
+        compiled_stylesheets.insert(public_path, post_processed.value);
         // if compilation_mode.is_production() {
         // } else if is_modified  {
         //     write_or_symlink_output.execute();
@@ -142,13 +302,150 @@ fn compile_css(css_files: &[FileInput], project_context: &ProjectContext, compil
     }
 }
 
+/// Whether `file` was linked as a stylesheet or script to compile (see
+/// `links_compiled_asset`), its public path renamed to the compiled one.
+fn is_compiled_link(file: &FileInput) -> bool {
+    file.public
+        .as_deref()
+        .is_some_and(|public| public.extension() != file.source.extension())
+}
+
+/// Drops the sources marked `<page-meta draft>`.
+fn exclude_drafts(inputs: &mut CompilerInputs) {
+    inputs.sources.retain(|input| {
+        let is_draft = PageMetadata::load(&input.source, &inputs.project).is_draft();
+        if is_draft {
+            println!("> skipping draft {:?}", input.source.source_file());
+        }
+        !is_draft
+    });
+}
+
+/// An empty site-wide tree with the `[css] safelist`.
+fn css_site_tree(options: &CssOptions) -> css::matching::DocumentTree {
+    let (safelist, errors) = css::matching::ClassSafelist::new(&options.safelist);
+    for (pattern, error) in errors {
+        eprintln!("⚠️ [css] invalid safelist pattern {pattern:?}: {error}");
+    }
+    css::matching::DocumentTree::default().with_safelist(safelist)
+}
+
+/// Writes the pages held back for critical CSS extraction.
+fn emit_deferred_pages(
+    pages: Vec<(FileInput, Node)>,
+    compiled_stylesheets: &HashMap<PathBuf, String>,
+    project_context: &ProjectContext,
+    minify: bool,
+) {
+    for (page, node) in pages {
+        let public_path = path_clean::clean(page.resolved_public_path(project_context));
+        let node = crate::stylesheets::inline_critical_css(node, &public_path, compiled_stylesheets, minify);
+        let output_path = page.to_output_file_path(project_context);
+        crate::common::path_utils::write_output_file_smart(&output_path, node.format_document_pretty().as_bytes());
+    }
+}
+
+/// Bundles every `<script src>` target (and the local modules it imports)
+/// into its output file, stripping TypeScript types and minifying when the
+/// target asks for it. A script that fails to compile is emitted unchanged.
+fn compile_js(
+    js_files: &[FileInput],
+    project_context: &ProjectContext,
+    minify: bool,
+    resolved_dependencies: &mut ResolvedDependencies,
+) {
+    let options = js::ScriptOptions { minify };
+    for js_file in js_files {
+        if !js_file.source_file().is_file() {
+            eprintln!("⚠️ missing script file {:?}", js_file.source_file());
+            continue;
+        }
+        let public_path = compiled_asset_path(&path_clean::clean(js_file.resolved_public_path(project_context)));
+        let output_path = project_context.output_dir.join(&public_path);
+        match js::compile_script(js_file.source_file(), options) {
+            Ok(compiled) => {
+                if compiled.is_module {
+                    let mut pages = resolved_dependencies.classic_scripts
+                        .get(&public_path)
+                        .into_iter()
+                        .flatten()
+                        .collect::<Vec<_>>();
+                    pages.sort();
+                    for page in pages {
+                        eprintln!(
+                            "⚠️ {page:?} loads {:?} without type=\"module\", but it compiles to an ES module",
+                            js_file.source_file(),
+                        );
+                    }
+                }
+                crate::common::path_utils::write_output_file_smart(&output_path, compiled.code);
+            }
+            Err(error) => {
+                eprintln!("⚠️ {error}");
+                if let Ok(source) = js_file.load_source_file() {
+                    crate::common::path_utils::write_output_file_smart(&output_path, source);
+                }
+            }
+        }
+        resolved_dependencies.include_emitted_file(public_path);
+    }
+}
+
+fn emit_site_files(
+    pages: &[FileInput],
+    site: &SiteConfig,
+    project_context: &ProjectContext,
+    resolved_dependencies: &mut ResolvedDependencies,
+) {
+    if site.sitemap.enabled {
+        let entries = crate::site::sitemap::sitemap_entries(pages, resolved_dependencies, site, project_context);
+        let sitemap = crate::site::sitemap::render_sitemap(&entries);
+        crate::common::path_utils::write_output_file_smart(project_context.output_dir.join("sitemap.xml"), sitemap);
+        resolved_dependencies.include_emitted_file("sitemap.xml");
+    }
+    if site.robots.enabled {
+        let robots = crate::site::robots::render_robots(site);
+        crate::common::path_utils::write_output_file_smart(project_context.output_dir.join("robots.txt"), robots);
+        resolved_dependencies.include_emitted_file("robots.txt");
+    }
+}
+
+fn emit_feed(
+    feed: &FeedConfig,
+    site: &SiteConfig,
+    pages: &[FileInput],
+    rendered_pages: &HashMap<PathBuf, Node>,
+    project_context: &ProjectContext,
+    resolved_dependencies: &mut ResolvedDependencies,
+) {
+    let mut items = pages
+        .iter()
+        .filter_map(|page| {
+            let rendered = rendered_pages.get(&page.source)?;
+            if !crate::site::feeds::is_feed_member(feed, page, project_context) {
+                return None
+            }
+            Some(crate::site::feeds::feed_item(page, rendered, site, project_context))
+        })
+        .collect::<Vec<_>>();
+    crate::site::feeds::sort_feed_items(&mut items, feed.limit);
+    for format in feed.formats.iter() {
+        let contents = crate::site::feeds::render_feed(feed, *format, site, &items);
+        let public_path = path_clean::clean(feed.output.join(format.file_name()));
+        crate::common::path_utils::write_output_file_smart(project_context.output_dir.join(&public_path), contents);
+        resolved_dependencies.include_emitted_file(public_path);
+    }
+}
+
 fn emit_assets(asset_files: &[FileInput], project_context: &ProjectContext, compilation_mode: CompilationMode) {
     for asset_file in asset_files {
         let source_file = asset_file.source_file();
         let output_path = asset_file.to_output_file_path(project_context);
         if compilation_mode.is_production() {
-            let output_data = std::fs::read(source_file).unwrap();
-            crate::common::path_utils::write_output_file_smart(&output_path, output_data);
+            match std::fs::read(source_file) {
+                Ok(output_data) => crate::common::path_utils::write_output_file_smart(&output_path, output_data),
+                Err(error) => eprintln!("⚠️ failed to read asset {source_file:?}: {error}"),
+            }
         } else {
             let status = crate::common::symlink::create_relative_symlink(source_file, &output_path);
             match status {
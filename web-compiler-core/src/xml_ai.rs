@@ -33,7 +33,11 @@ fn rules() -> TagRewriteRuleSet<CompilerRuntime> {
 fn pre_process(file_path: impl AsRef<Path>) -> Result<xml_ast::Node, ()> {
     let project_context = ProjectContext {
         project_root: file_path.as_ref().parent().unwrap().to_path_buf(),
-        output_dir: PathBuf::from(".web-compiler/xml-ai-work")
+        output_dir: PathBuf::from(".web-compiler/xml-ai-work"),
+        source_cache: Default::default(),
+        image_cache: Default::default(),
+        profiler: Default::default(),
+        inline_assets: Default::default(),
     };
     let global_pipeline_spec = crate::markup::GlobalPipelineSpec {
         compilation_mode: CompilationMode::Dev,
@@ -41,6 +45,9 @@ fn pre_process(file_path: impl AsRef<Path>) -> Result<xml_ast::Node, ()> {
         rules: rules(),
         project: project_context.clone(),
         global_template: None,
+        max_include_depth: None,
+        headings: Default::default(),
+        images: Default::default(),
     };
     let file_input = FileInput {
         source: file_path.as_ref().to_path_buf(),
@@ -55,6 +62,8 @@ fn pre_process(file_path: impl AsRef<Path>) -> Result<xml_ast::Node, ()> {
         site_tree_layout: Default::default(),
         output_writer_mode: OutputWriterMode::JustReturnNode,
     };
-    input_pipeline.execute()
+    input_pipeline.execute().map_err(|error| {
+        eprintln!("{error}");
+    })
 }
 
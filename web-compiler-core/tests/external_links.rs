@@ -0,0 +1,181 @@
+//! The external link checker against a stub HTTP server on localhost,
+//! through the default (`reqwest`) backend.
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use web_compiler_core::check::external::{
+    check_external_links_with,
+    ExternalLinkCheckOptions,
+    ExternalLinkStatus,
+    ReqwestHttpClient,
+};
+use web_compiler_macro_types::project::{LinkKind, LinkReference, ResolvedDependencies};
+
+// ————————————————————————————————————————————————————————————————————————————
+// STUB SERVER
+// ————————————————————————————————————————————————————————————————————————————
+
+#[derive(Default)]
+struct ServerState {
+    /// Requests seen per path.
+    requests: HashMap<String, usize>,
+    in_flight: usize,
+    max_in_flight: usize,
+}
+
+/// Serves:
+/// - `/ok/*`: 200 after a short delay (to overlap requests),
+/// - `/missing`: 404,
+/// - `/flaky`: 503 twice, then 200,
+/// - `/down`: always 503.
+struct StubServer {
+    base_url: String,
+    state: Arc<Mutex<ServerState>>,
+}
+
+impl StubServer {
+    async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let state = Arc::new(Mutex::new(ServerState::default()));
+        let server_state = state.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let state = server_state.clone();
+                tokio::spawn(async move {
+                    let mut buffer = vec![0u8; 4096];
+                    let size = socket.read(&mut buffer).await.unwrap_or(0);
+                    let request = String::from_utf8_lossy(&buffer[..size]).to_string();
+                    let path = request.split_whitespace().nth(1).unwrap_or("/").to_string();
+                    let attempt = {
+                        let mut state = state.lock().unwrap();
+                        state.in_flight += 1;
+                        state.max_in_flight = state.max_in_flight.max(state.in_flight);
+                        let count = state.requests.entry(path.clone()).or_default();
+                        *count += 1;
+                        *count
+                    };
+                    let code = match path.as_str() {
+                        x if x.starts_with("/ok/") => {
+                            tokio::time::sleep(Duration::from_millis(50)).await;
+                            200
+                        }
+                        "/missing" => 404,
+                        "/flaky" if attempt <= 2 => 503,
+                        "/flaky" => 200,
+                        _ => 503,
+                    };
+                    state.lock().unwrap().in_flight -= 1;
+                    let response = format!("HTTP/1.1 {code} Stub\r\ncontent-length: 0\r\nconnection: close\r\n\r\n");
+                    let _ = socket.write_all(response.as_bytes()).await;
+                    let _ = socket.shutdown().await;
+                });
+            }
+        });
+        Self { base_url, state }
+    }
+    fn url(&self, path: &str) -> String {
+        format!("{}{path}", self.base_url)
+    }
+    fn requests(&self, path: &str) -> usize {
+        self.state.lock().unwrap().requests.get(path).copied().unwrap_or_default()
+    }
+    fn max_in_flight(&self) -> usize {
+        self.state.lock().unwrap().max_in_flight
+    }
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// HELPERS
+// ————————————————————————————————————————————————————————————————————————————
+
+fn dependencies(urls: &[String]) -> ResolvedDependencies {
+    let link_references = urls
+        .iter()
+        .map(|url| LinkReference {
+            kind: LinkKind::Attribute,
+            page: PathBuf::from("pages/index.html"),
+            origin: PathBuf::from("pages/index.html"),
+            include_chain: Vec::new(),
+            href: url.clone(),
+            target: None,
+            fragment: None,
+        })
+        .collect();
+    ResolvedDependencies { link_references, ..Default::default() }
+}
+
+fn options(cache_path: Option<PathBuf>) -> ExternalLinkCheckOptions {
+    ExternalLinkCheckOptions {
+        concurrency: 2,
+        retries: 2,
+        retry_delay: Duration::from_millis(10),
+        timeout: Duration::from_secs(5),
+        cache_path,
+        ..Default::default()
+    }
+}
+
+fn status_of<'a>(report: &'a web_compiler_core::check::external::ExternalLinkReport, url: &str) -> &'a ExternalLinkStatus {
+    &report.results.iter().find(|x| x.url == url).unwrap().status
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// TESTS
+// ————————————————————————————————————————————————————————————————————————————
+
+#[tokio::test]
+async fn retries_transient_failures() {
+    let server = StubServer::start().await;
+    let urls = vec![server.url("/flaky"), server.url("/down"), server.url("/missing")];
+    let client = ReqwestHttpClient::new(Duration::from_secs(5)).unwrap();
+    let report = check_external_links_with(client, &dependencies(&urls), &options(None)).await;
+    assert_eq!(status_of(&report, &urls[0]), &ExternalLinkStatus::Ok { code: 200 });
+    assert_eq!(server.requests("/flaky"), 3);
+    assert!(matches!(status_of(&report, &urls[1]), ExternalLinkStatus::Unreachable { .. }));
+    assert_eq!(server.requests("/down"), 3);
+    // Client errors are definitive and never retried.
+    assert_eq!(status_of(&report, &urls[2]), &ExternalLinkStatus::Broken { code: 404 });
+    assert_eq!(server.requests("/missing"), 1);
+    assert!(!report.is_ok());
+}
+
+#[tokio::test]
+async fn limits_requests_in_flight() {
+    let server = StubServer::start().await;
+    let urls = (0..8).map(|x| server.url(&format!("/ok/{x}"))).collect::<Vec<_>>();
+    let client = ReqwestHttpClient::new(Duration::from_secs(5)).unwrap();
+    let report = check_external_links_with(client, &dependencies(&urls), &options(None)).await;
+    assert_eq!(report.results.len(), 8);
+    assert!(report.is_ok());
+    assert!(server.max_in_flight() <= 2, "{} requests in flight", server.max_in_flight());
+}
+
+#[tokio::test]
+async fn reuses_cached_successes_only() {
+    let server = StubServer::start().await;
+    let cache_path = std::env::temp_dir()
+        .join(format!("web-compiler-external-links-{}", std::process::id()))
+        .join("external-links.json");
+    let _ = std::fs::remove_file(&cache_path);
+    let urls = vec![server.url("/ok/cached"), server.url("/missing")];
+    let dependencies = dependencies(&urls);
+    // - -
+    let client = ReqwestHttpClient::new(Duration::from_secs(5)).unwrap();
+    let first = check_external_links_with(client, &dependencies, &options(Some(cache_path.clone()))).await;
+    assert!(first.results.iter().all(|x| !x.cached));
+    // - -
+    let client = ReqwestHttpClient::new(Duration::from_secs(5)).unwrap();
+    let second = check_external_links_with(client, &dependencies, &options(Some(cache_path.clone()))).await;
+    let cached = second.results.iter().find(|x| x.url == urls[0]).unwrap();
+    assert!(cached.cached);
+    assert_eq!(server.requests("/ok/cached"), 1);
+    // Failures are always re-checked.
+    assert_eq!(server.requests("/missing"), 2);
+    let _ = std::fs::remove_dir_all(cache_path.parent().unwrap());
+}
@@ -0,0 +1,98 @@
+//! Critical CSS: the subset of a compiled stylesheet that applies to the
+//! elements of one page, for inlining into the page's `<head>`.
+use std::convert::Infallible;
+use std::path::Path;
+
+use lightningcss::printer::PrinterOptions;
+use lightningcss::rules::{CssRule, CssRuleList};
+use lightningcss::selector::SelectorList;
+use lightningcss::stylesheet::{ParserOptions, StyleSheet};
+use lightningcss::values::url::Url;
+use lightningcss::visit_types;
+use lightningcss::visitor::{Visit, VisitTypes, Visitor};
+
+use crate::matching::DocumentTree;
+
+/// Extracts the rules of `source` (the compiled stylesheet at
+/// `stylesheet_path`) whose selectors match an element of `page`.
+///
+/// Relative `url()`s are rebased from the stylesheet's directory to
+/// `page_path`'s, both given as public paths. `@import` rules are dropped;
+/// the imported sheets arrive with the full stylesheet.
+pub fn critical_css(
+    source: &str,
+    stylesheet_path: &Path,
+    page: &DocumentTree,
+    page_path: &Path,
+    minify: bool,
+) -> Result<String, String> {
+    let mut stylesheet = StyleSheet::parse(source, ParserOptions::default()).map_err(|x| x.to_string())?;
+    retain_style_rules(&mut stylesheet.rules, &mut |selectors| page.matches_selector_list(selectors));
+    stylesheet.rules.0.retain(|rule| !matches!(rule, CssRule::Import(_)));
+    let mut visitor = UrlRebaseVisitor {
+        from: stylesheet_path.parent().unwrap_or(Path::new("")),
+        to: page_path.parent().unwrap_or(Path::new("")),
+    };
+    let _ = stylesheet.visit(&mut visitor);
+    let printer_options = PrinterOptions { minify, ..Default::default() };
+    stylesheet
+        .to_css(printer_options)
+        .map(|x| x.code)
+        .map_err(|x| x.to_string())
+}
+
+/// Drops the style rules for which `keep` is false, along with the
+/// conditional group rules (`@media`, `@supports`, `@layer`, …) left empty.
+/// Other at-rules (`@font-face`, `@keyframes`, `@property`, …) are kept.
+pub(crate) fn retain_style_rules<'i>(
+    rules: &mut CssRuleList<'i>,
+    keep: &mut impl FnMut(&SelectorList<'i>) -> bool,
+) {
+    rules.0.retain_mut(|rule| match rule {
+        CssRule::Style(rule) => keep(&rule.selectors),
+        CssRule::Media(rule) => retain_nested(&mut rule.rules, keep),
+        CssRule::Supports(rule) => retain_nested(&mut rule.rules, keep),
+        CssRule::LayerBlock(rule) => retain_nested(&mut rule.rules, keep),
+        CssRule::Container(rule) => retain_nested(&mut rule.rules, keep),
+        CssRule::Scope(rule) => retain_nested(&mut rule.rules, keep),
+        CssRule::StartingStyle(rule) => retain_nested(&mut rule.rules, keep),
+        CssRule::MozDocument(rule) => retain_nested(&mut rule.rules, keep),
+        _ => true,
+    });
+}
+
+fn retain_nested<'i>(rules: &mut CssRuleList<'i>, keep: &mut impl FnMut(&SelectorList<'i>) -> bool) -> bool {
+    retain_style_rules(rules, keep);
+    !rules.0.is_empty()
+}
+
+struct UrlRebaseVisitor<'a> {
+    from: &'a Path,
+    to: &'a Path,
+}
+
+impl<'a, 'i> Visitor<'i> for UrlRebaseVisitor<'a> {
+    type Error = Infallible;
+    fn visit_types(&self) -> VisitTypes {
+        visit_types!(URLS)
+    }
+    fn visit_url(&mut self, url: &mut Url<'i>) -> Result<(), Self::Error> {
+        if let Some(rebased) = rebase_url(&url.url, self.from, self.to) {
+            url.url = rebased.into();
+        }
+        Ok(())
+    }
+}
+
+/// Rewrites a document-relative `url` from `from` to `to`; absolute, rooted,
+/// fragment-only and `data:` URLs are left alone.
+fn rebase_url(url: &str, from: &Path, to: &Path) -> Option<String> {
+    let is_absolute = url.starts_with(['/', '#']) || url.contains("://") || url.starts_with("data:");
+    if url.is_empty() || is_absolute || from == to {
+        return None
+    }
+    let (path, suffix) = macro_types::project::split_link_suffix(url);
+    let target = path_clean::clean(from.join(path));
+    let relative = pathdiff::diff_paths(&target, to).unwrap_or_else(|| target.clone());
+    Some(format!("{}{suffix}", relative.to_str()?))
+}
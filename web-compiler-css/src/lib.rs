@@ -1,16 +1,27 @@
 extern crate web_compiler_macro_types as macro_types;
 extern crate web_compiler_io_types as io_types;
+extern crate web_compiler_xml_ast as xml_ast;
+
+pub mod matching;
+pub mod critical;
+pub mod prune;
+pub mod scoped;
+pub mod scss;
 
 
 use std::convert::Infallible;
+use std::path::Path;
 use lightningcss::printer::PrinterOptions;
-use lightningcss::stylesheet::{ParserOptions, StyleSheet};
+use lightningcss::rules::{CssRule, CssRuleList};
+use lightningcss::stylesheet::{MinifyOptions, ParserFlags, ParserOptions, StyleSheet};
+use lightningcss::targets::{Features, Targets};
 use lightningcss::values::url::Url;
 use lightningcss::visit_types;
 use lightningcss::visitor::{Visit, VisitTypes, Visitor};
 
+use macro_types::inline_assets::data_uri;
 use macro_types::project::DependencyRelation;
-use macro_types::lexical_env::{AccumulatedEffects, MacroIO, SourceHostRef};
+use macro_types::lexical_env::{AccumulatedEffects, MacroIO, SourceHostRef, StylesheetError};
 use io_types::Effectful;
 
 // ————————————————————————————————————————————————————————————————————————————
@@ -55,6 +66,97 @@ pub struct Payload<Value> {
     pub modified: ModifiedFlag,
 }
 
+/// Also accepts `@custom-media` (lowered by the [`CssPostprocessor`]).
+pub fn parser_options<'i>() -> ParserOptions<'i> {
+    ParserOptions { flags: ParserFlags::CUSTOM_MEDIA, ..Default::default() }
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// LOWERING
+// ————————————————————————————————————————————————————————————————————————————
+
+/// Emitted stylesheets are plain CSS: nesting and custom media queries are
+/// compiled away, and media queries keep their `min-`/`max-` form.
+fn lowering_targets() -> Targets {
+    Targets {
+        include: Features::Nesting | Features::CustomMediaQueries | Features::MediaRangeSyntax,
+        ..Default::default()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct LoweringNeeds {
+    nesting: bool,
+    custom_media: bool,
+}
+
+impl LoweringNeeds {
+    fn of(rules: &CssRuleList) -> Self {
+        let mut needs = Self::default();
+        needs.visit(rules);
+        needs
+    }
+    fn any(&self) -> bool {
+        self.nesting || self.custom_media
+    }
+    fn visit(&mut self, rules: &CssRuleList) {
+        for rule in rules.0.iter() {
+            match rule {
+                CssRule::Style(rule) => {
+                    self.nesting |= !rule.rules.0.is_empty();
+                }
+                CssRule::Nesting(_) | CssRule::NestedDeclarations(_) => self.nesting = true,
+                CssRule::CustomMedia(_) => self.custom_media = true,
+                CssRule::Media(rule) => self.visit(&rule.rules),
+                CssRule::Supports(rule) => self.visit(&rule.rules),
+                CssRule::LayerBlock(rule) => self.visit(&rule.rules),
+                CssRule::Container(rule) => self.visit(&rule.rules),
+                CssRule::Scope(rule) => self.visit(&rule.rules),
+                CssRule::StartingStyle(rule) => self.visit(&rule.rules),
+                CssRule::MozDocument(rule) => self.visit(&rule.rules),
+                _ => (),
+            }
+        }
+    }
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// ERRORS
+// ————————————————————————————————————————————————————————————————————————————
+
+#[derive(Debug, Clone, Copy)]
+struct ErrorContext<'a> {
+    source_context: SourceHostRef<'a>,
+    style_element: bool,
+}
+
+impl<'a> ErrorContext<'a> {
+    fn new(source_context: SourceHostRef<'a>, style_element: bool) -> Self {
+        Self { source_context, style_element }
+    }
+    fn error<T: std::fmt::Display>(&self, error: lightningcss::error::Error<T>) -> StylesheetError {
+        StylesheetError {
+            file: self.source_context.file_input().source_file().to_path_buf(),
+            style_element: self.style_element,
+            line: error.loc.as_ref().map(|x| x.line + 1),
+            column: error.loc.as_ref().map(|x| x.column),
+            message: format!("invalid CSS: {}", error.kind),
+        }
+    }
+    /// `source_code` unchanged, with the error recorded.
+    fn fallback<T: std::fmt::Display>(
+        &self,
+        source_code: &str,
+        error: lightningcss::error::Error<T>,
+    ) -> MacroIO<Payload<String>> {
+        let error = self.error(error);
+        MacroIO::wrap(Payload { value: source_code.to_string(), modified: ModifiedFlag::Default })
+            .and_modify_context(|ctx| {
+                ctx.stylesheet_errors.insert(error);
+            })
+    }
+}
+
 // ————————————————————————————————————————————————————————————————————————————
 // STYLE TAG PRE-PROCESSOR
 // ————————————————————————————————————————————————————————————————————————————
@@ -63,25 +165,38 @@ pub struct CssPreprocessor<'a> {
     effects: AccumulatedEffects,
     source_context: SourceHostRef<'a>,
     modified: ModifiedFlag,
+    style_element: bool,
 }
 
 impl<'a> CssPreprocessor<'a> {
     pub fn new(source_context: SourceHostRef<'a>) -> Self {
-        Self { effects: Default::default(), source_context, modified: ModifiedFlag::default() }
+        Self { effects: Default::default(), source_context, modified: ModifiedFlag::default(), style_element: false }
+    }
+    /// The stylesheet is a `<style>` element of the source file.
+    pub fn for_style_element(mut self) -> Self {
+        self.style_element = true;
+        self
     }
+    /// Invalid CSS is recorded in `stylesheet_errors` and returned unchanged,
+    /// without its dependencies.
     pub fn execute(mut self, source_code: &str) -> MacroIO<Payload<String>> {
-        let mut stylesheet = StyleSheet::parse(source_code, ParserOptions::default()).unwrap();
+        let error_context = ErrorContext::new(self.source_context, self.style_element);
+        let mut stylesheet = match StyleSheet::parse(source_code, parser_options()) {
+            Ok(x) => x,
+            Err(error) => return error_context.fallback(source_code, error),
+        };
         let mut visitor = CssPreprocessorVisitor {
             effects: &mut self.effects,
             source_context: self.source_context,
             modified: &mut self.modified,
         };
-        stylesheet.visit(&mut visitor).unwrap();
+        let _ = stylesheet.visit(&mut visitor);
         // - -
         let printer_options = PrinterOptions { minify: false, ..Default::default() };
-        let res: lightningcss::stylesheet::ToCssResult = stylesheet
-            .to_css(printer_options)
-            .unwrap();
+        let res: lightningcss::stylesheet::ToCssResult = match stylesheet.to_css(printer_options) {
+            Ok(x) => x,
+            Err(error) => return error_context.fallback(source_code, error),
+        };
         // - -
         MacroIO::wrap(res.code)
             .and_modify_context(|ctx| {
@@ -120,9 +235,25 @@ impl<'a, 'i> Visitor<'i> for CssPreprocessorVisitor<'a> {
         let url_string = url.url.to_string();
         let dependency = self.source_context.file_input().with_dependency_relation(&url_string);
         if dependency.is_external_target() {
+            self.effects.stylesheet_urls.insert(dependency);
             return Ok(())
         }
+        let target = dependency.as_file_dependency().resolved_target_path();
+        let is_plain_path = !url_string.contains(['?', '#']);
+        if is_plain_path && self.source_context.project_context().inline_assets.should_inline(&target) {
+            match data_uri(&target) {
+                Ok(uri) => {
+                    url.url = uri.into();
+                    self.modified.mark_modified_mut();
+                    return Ok(())
+                }
+                Err(error) => {
+                    eprintln!("⚠️ {:?} failed to inline {target:?}: {error}", self.source_context.file_input().source_file());
+                }
+            }
+        }
         let encoded_url = dependency.encode();
+        self.effects.stylesheet_urls.insert(dependency.clone());
         self.effects.dependencies.insert(dependency);
         url.url = encoded_url.into();
         self.modified.mark_modified_mut();
@@ -138,36 +269,95 @@ pub struct CssPostprocessor<'a> {
     modified: ModifiedFlag,
     // environment: &'a (),
     source_host: SourceHostRef<'a>,
+    style_element: bool,
+    minify: bool,
+    report_parse_errors: bool,
 }
 
 impl<'a> CssPostprocessor<'a> {
     pub fn new(source_host: SourceHostRef<'a>) -> Self {
-        Self { source_host, modified: ModifiedFlag::default() }
+        Self {
+            source_host,
+            modified: ModifiedFlag::default(),
+            style_element: false,
+            minify: false,
+            report_parse_errors: true,
+        }
+    }
+    /// Print the stylesheet minified.
+    pub fn minify(mut self, minify: bool) -> Self {
+        self.minify = minify;
+        self
+    }
+    /// The stylesheet is a `<style>` element of the source file.
+    pub fn for_style_element(mut self) -> Self {
+        self.style_element = true;
+        self
+    }
+    /// Don't record parse errors, e.g. because the [`CssPreprocessor`]
+    /// already reported them to the same output.
+    pub fn without_parse_errors(mut self) -> Self {
+        self.report_parse_errors = false;
+        self
     }
-    pub fn execute(mut self, source_code: &str) -> Payload<String> {
-        let mut stylesheet = StyleSheet::parse(source_code, ParserOptions::default()).unwrap();
-        
+    /// URLs are resolved before anything else, so a stylesheet that fails to
+    /// lower or minify is still printed with them resolved (unminified), and
+    /// the error is recorded in `stylesheet_errors`.
+    ///
+    /// One that fails to parse or print is recorded and returned unchanged.
+    pub fn execute(mut self, source_code: &str) -> MacroIO<Payload<String>> {
+        let error_context = ErrorContext::new(self.source_host, self.style_element);
+        let unchanged = || MacroIO::wrap(Payload { value: source_code.to_string(), modified: ModifiedFlag::Default });
+        let mut stylesheet = match StyleSheet::parse(source_code, parser_options()) {
+            Ok(x) => x,
+            Err(_) if !self.report_parse_errors => return unchanged(),
+            Err(error) => return error_context.fallback(source_code, error),
+        };
+
         let mut visitor = CssPostprocessorVisitor {
             // environment: self.environment,
             modified: &mut self.modified,
             source_host: self.source_host,
         };
-        
-        stylesheet.visit(&mut visitor ).unwrap();
-        
-        let res: lightningcss::stylesheet::ToCssResult = stylesheet.to_css(PrinterOptions { minify: false, ..Default::default() }).unwrap();
 
-        if self.modified.is_modified() {
-            Payload {
+        let _ = stylesheet.visit(&mut visitor);
+        let mut errors = Vec::new();
+
+        let lowering = LoweringNeeds::of(&stylesheet.rules);
+        let mut minify = self.minify;
+        if lowering.custom_media || self.minify {
+            // Custom media queries are only resolved while minifying, which
+            // leaves the rules emptied when it fails.
+            let visited = stylesheet.rules.clone();
+            let minified = stylesheet.minify(MinifyOptions { targets: lowering_targets(), ..Default::default() });
+            if let Err(error) = minified {
+                // The location would be in the pre-processed stylesheet.
+                errors.push(StylesheetError { line: None, column: None, ..error_context.error(error) });
+                stylesheet.rules = visited;
+                minify = false;
+            }
+        }
+        if lowering.any() || self.minify {
+            self.modified.mark_modified_mut();
+        }
+
+        let printer_options = PrinterOptions { minify, targets: lowering_targets(), ..Default::default() };
+        let res: lightningcss::stylesheet::ToCssResult = match stylesheet.to_css(printer_options) {
+            Ok(x) => x,
+            Err(error) => return error_context.fallback(source_code, error),
+        };
+
+        let output = if self.modified.is_modified() {
+            MacroIO::wrap(Payload {
                 value: res.code,
                 modified: self.modified,
-            }
+            })
         } else {
-            Payload {
-                value: source_code.to_string(),
-                modified: self.modified,
-            }
-        }
+            unchanged()
+        };
+        output.and_modify_context(|ctx| {
+            ctx.stylesheet_errors.extend(errors);
+        })
     }
 }
 
@@ -200,14 +390,21 @@ impl<'a, 'i> Visitor<'i> for CssPostprocessorVisitor<'a> {
         }
         let file_dependency = decoded_virtual_path.as_file_dependency();
         let decoded_path = file_dependency.resolved_target_path();
-        let source_host = file_dependency.from.parent().unwrap();
+        let source_host = file_dependency.from.parent().unwrap_or(Path::new(""));
         let decoded_path = path_clean::clean(decoded_path);
         let relative = pathdiff::diff_paths(
             &decoded_path,
             source_host
-        ).unwrap();
+        );
         self.modified.mark_modified_mut();
-        url.url = relative.to_str().unwrap().to_owned().into();
+        match relative.as_deref().and_then(Path::to_str) {
+            Some(relative) => url.url = relative.to_owned().into(),
+            None => {
+                let file = self.source_host.file_input().source_file();
+                eprintln!("⚠️ {file:?} can't make url({:?}) relative to {source_host:?}", decoded_virtual_path.to);
+                url.url = decoded_virtual_path.to.into();
+            }
+        }
         Ok(())
     }
 }
@@ -0,0 +1,560 @@
+//! Selector matching against rendered pages.
+//!
+//! A [`DocumentTree`] is a flattened, read-only view of a page's `xml_ast`
+//! tree that implements the `parcel_selectors` element interface, so the
+//! selectors of a parsed stylesheet can be tested against it.
+//!
+//! Matching is conservative: dynamic pseudo-classes (`:hover`, `:checked`,
+//! …) and pseudo-elements are assumed to match, and selectors that can't be
+//! evaluated statically (`:has()`, `:nth-child(… of …)`, nesting, shadow DOM
+//! parts, or a `:not()` around a dynamic pseudo-class) are treated as matching
+//! every page.
+//!
+//! A tree may hold several pages (see [`DocumentTree::push_page`]), in which
+//! case a selector matches if it matches an element of any of them. Parsed
+//! pages lose their `<html>` element, so one is implied around each page, as
+//! browsers do.
+use lightningcss::selector::{Component, Selector, SelectorList};
+use parcel_selectors::attr::{AttrSelectorOperation, CaseSensitivity, NamespaceConstraint};
+use parcel_selectors::context::{MatchingContext, MatchingMode, QuirksMode};
+use parcel_selectors::matching::{matches_selector, ElementSelectorFlags};
+use parcel_selectors::parser::NthType;
+use parcel_selectors::{Element, OpaqueElement, SelectorImpl};
+
+use xml_ast::{Fragment, Node};
+
+// ————————————————————————————————————————————————————————————————————————————
+// DOCUMENT TREE
+// ————————————————————————————————————————————————————————————————————————————
+
+#[derive(Debug, Clone, Default)]
+pub struct DocumentTree {
+    elements: Vec<ElementData>,
+    safelist: ClassSafelist,
+}
+
+/// Class names (glob patterns such as `is-*`) that are assumed to be present
+/// on every element, e.g. because scripts add them at runtime.
+#[derive(Debug, Clone, Default)]
+pub struct ClassSafelist {
+    patterns: Vec<glob::Pattern>,
+}
+
+impl ClassSafelist {
+    /// Parses `patterns`; a leading `.` is optional. Returns the invalid
+    /// patterns alongside.
+    pub fn new(patterns: &[String]) -> (Self, Vec<(String, glob::PatternError)>) {
+        let mut safelist = Self::default();
+        let mut errors = Vec::new();
+        for pattern in patterns {
+            let trimmed = pattern.trim();
+            match glob::Pattern::new(trimmed.strip_prefix('.').unwrap_or(trimmed)) {
+                Ok(x) => safelist.patterns.push(x),
+                Err(error) => errors.push((pattern.clone(), error)),
+            }
+        }
+        (safelist, errors)
+    }
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+    pub fn matches(&self, class: &str) -> bool {
+        self.patterns.iter().any(|x| x.matches(class))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ElementData {
+    /// Lowercase tag name.
+    tag: String,
+    attributes: Vec<(String, String)>,
+    parent: Option<usize>,
+    prev_sibling: Option<usize>,
+    next_sibling: Option<usize>,
+    /// No child elements and only whitespace text (the pretty printer
+    /// indents otherwise empty elements).
+    is_empty: bool,
+}
+
+impl ElementData {
+    fn attribute(&self, key: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(key))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+impl DocumentTree {
+    pub fn new(root: &Node) -> Self {
+        let mut tree = Self::default();
+        tree.push_page(root);
+        tree
+    }
+    pub fn with_safelist(mut self, safelist: ClassSafelist) -> Self {
+        self.safelist = safelist;
+        self
+    }
+    /// Adds another page, as an independent root.
+    pub fn push_page(&mut self, root: &Node) {
+        let nodes = std::slice::from_ref(root);
+        if has_html_element(nodes) {
+            self.push_nodes(nodes, None, &mut None);
+            return
+        }
+        let index = self.elements.len();
+        self.elements.push(ElementData {
+            tag: String::from("html"),
+            attributes: Vec::new(),
+            parent: None,
+            prev_sibling: None,
+            next_sibling: None,
+            is_empty: true,
+        });
+        let is_empty = !self.push_nodes(nodes, Some(index), &mut None);
+        self.elements[index].is_empty = is_empty;
+    }
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+    /// Appends `nodes` (flattening fragments) as children of `parent`,
+    /// returning whether any of them is an element or non-whitespace text.
+    fn push_nodes(&mut self, nodes: &[Node], parent: Option<usize>, previous: &mut Option<usize>) -> bool {
+        let mut has_content = false;
+        for node in nodes {
+            match node {
+                Node::Text(text) => {
+                    has_content |= !text.trim().is_empty();
+                }
+                Node::Fragment(fragment) => {
+                    has_content |= self.push_nodes(fragment.as_node_slice(), parent, previous);
+                }
+                Node::Element(element) => {
+                    has_content = true;
+                    let index = self.elements.len();
+                    self.elements.push(ElementData {
+                        tag: element.tag.as_normalized().to_ascii_lowercase(),
+                        attributes: element.attributes
+                            .iter()
+                            .map(|(key, value)| (key.as_str().to_string(), value.as_str().to_string()))
+                            .collect(),
+                        parent,
+                        prev_sibling: *previous,
+                        next_sibling: None,
+                        is_empty: true,
+                    });
+                    if let Some(previous) = *previous {
+                        self.elements[previous].next_sibling = Some(index);
+                    }
+                    *previous = Some(index);
+                    let is_empty = !self.push_children(&element.children, index);
+                    self.elements[index].is_empty = is_empty;
+                }
+            }
+        }
+        has_content
+    }
+    fn push_children(&mut self, children: &Fragment, parent: usize) -> bool {
+        self.push_nodes(children.as_node_slice(), Some(parent), &mut None)
+    }
+    fn element(&self, index: usize) -> ElementRef<'_> {
+        ElementRef { tree: self, index }
+    }
+    /// Whether `selector` matches some element of the page.
+    pub fn matches_selector(&self, selector: &Selector) -> bool {
+        if !is_statically_matchable(selector, &self.safelist) {
+            return true
+        }
+        let mut context = MatchingContext::new(MatchingMode::Normal, None, None, QuirksMode::NoQuirks);
+        (0..self.elements.len()).any(|index| {
+            matches_selector(selector, 0, None, &self.element(index), &mut context, &mut |_, _| {})
+        })
+    }
+    /// Whether any selector of the list matches some element of the page.
+    pub fn matches_selector_list(&self, selectors: &SelectorList) -> bool {
+        selectors.0.iter().any(|selector| self.matches_selector(selector))
+    }
+}
+
+/// Whether `nodes` (flattening fragments) include an `<html>` element.
+fn has_html_element(nodes: &[Node]) -> bool {
+    nodes.iter().any(|node| match node {
+        Node::Element(element) => element.tag.as_normalized().eq_ignore_ascii_case("html"),
+        Node::Fragment(fragment) => has_html_element(fragment.as_node_slice()),
+        Node::Text(_) => false,
+    })
+}
+
+/// Whether `selector` only uses components that can be decided from the
+/// markup alone (and that `parcel_selectors` can match: it panics on
+/// `:nth-child(… of …)` and the column pseudo-classes).
+fn is_statically_matchable(selector: &Selector, safelist: &ClassSafelist) -> bool {
+    let is_negatable = |x: &Selector| is_statically_matchable(x, safelist) && !has_dynamic_component(x, safelist);
+    selector.iter_raw_match_order().all(|component| match component {
+        Component::Has(_)
+        | Component::Nesting
+        | Component::Part(_)
+        | Component::Slotted(_)
+        | Component::Host(_)
+        | Component::NthOf(_) => false,
+        Component::Nth(nth) => !matches!(nth.ty, NthType::Col | NthType::LastCol),
+        Component::Negation(selectors) => selectors.iter().all(is_negatable),
+        Component::Is(selectors) | Component::Where(selectors) | Component::Any(_, selectors) => {
+            selectors.iter().all(|x| is_statically_matchable(x, safelist))
+        }
+        _ => true,
+    })
+}
+
+/// Whether `selector` contains a pseudo-class, pseudo-element or safelisted
+/// class, which are assumed to match and so can't be negated.
+fn has_dynamic_component(selector: &Selector, safelist: &ClassSafelist) -> bool {
+    selector.iter_raw_match_order().any(|component| match component {
+        Component::NonTSPseudoClass(_) | Component::PseudoElement(_) => true,
+        Component::Class(name) => safelist.matches(name.as_ref()),
+        Component::Negation(selectors)
+        | Component::Is(selectors)
+        | Component::Where(selectors)
+        | Component::Any(_, selectors) => selectors.iter().any(|x| has_dynamic_component(x, safelist)),
+        Component::NthOf(nth) => nth.selectors().iter().any(|x| has_dynamic_component(x, safelist)),
+        _ => false,
+    })
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// ELEMENT INTERFACE
+// ————————————————————————————————————————————————————————————————————————————
+
+/// Names the (otherwise unnameable) selector implementation of lightningcss.
+trait SelectorImplOf<'i> {
+    type Impl: SelectorImpl<'i>;
+}
+
+impl<'i, Impl: SelectorImpl<'i>> SelectorImplOf<'i> for parcel_selectors::SelectorList<'i, Impl> {
+    type Impl = Impl;
+}
+
+type Selectors<'i> = <SelectorList<'i> as SelectorImplOf<'i>>::Impl;
+
+#[derive(Clone, Copy)]
+struct ElementRef<'t> {
+    tree: &'t DocumentTree,
+    index: usize,
+}
+
+impl<'t> ElementRef<'t> {
+    fn data(&self) -> &'t ElementData {
+        &self.tree.elements[self.index]
+    }
+    fn with_index(&self, index: Option<usize>) -> Option<Self> {
+        index.map(|index| Self { tree: self.tree, index })
+    }
+}
+
+impl<'t> std::fmt::Debug for ElementRef<'t> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<{}> #{}", self.data().tag, self.index)
+    }
+}
+
+impl<'i, 't> Element<'i> for ElementRef<'t> {
+    type Impl = Selectors<'i>;
+
+    fn opaque(&self) -> OpaqueElement {
+        OpaqueElement::new(self.data())
+    }
+    fn parent_element(&self) -> Option<Self> {
+        self.with_index(self.data().parent)
+    }
+    fn parent_node_is_shadow_root(&self) -> bool {
+        false
+    }
+    fn containing_shadow_host(&self) -> Option<Self> {
+        None
+    }
+    /// Pseudo-elements are matched on their originating element itself.
+    fn pseudo_element_originating_element(&self) -> Option<Self> {
+        Some(*self)
+    }
+    fn is_pseudo_element(&self) -> bool {
+        false
+    }
+    fn prev_sibling_element(&self) -> Option<Self> {
+        self.with_index(self.data().prev_sibling)
+    }
+    fn next_sibling_element(&self) -> Option<Self> {
+        self.with_index(self.data().next_sibling)
+    }
+    fn is_html_element_in_html_document(&self) -> bool {
+        true
+    }
+    fn has_local_name(&self, local_name: &<Self::Impl as SelectorImpl<'i>>::BorrowedLocalName) -> bool {
+        self.data().tag.eq_ignore_ascii_case(local_name.as_ref())
+    }
+    fn has_namespace(&self, _: &<Self::Impl as SelectorImpl<'i>>::BorrowedNamespaceUrl) -> bool {
+        true
+    }
+    fn is_same_type(&self, other: &Self) -> bool {
+        self.data().tag == other.data().tag
+    }
+    fn attr_matches(
+        &self,
+        _: &NamespaceConstraint<&<Self::Impl as SelectorImpl<'i>>::NamespaceUrl>,
+        local_name: &<Self::Impl as SelectorImpl<'i>>::LocalName,
+        operation: &AttrSelectorOperation<&<Self::Impl as SelectorImpl<'i>>::AttrValue>,
+    ) -> bool {
+        self.data()
+            .attribute(local_name.as_ref())
+            .is_some_and(|value| operation.eval_str(value))
+    }
+    fn match_non_ts_pseudo_class<F>(
+        &self,
+        _: &<Self::Impl as SelectorImpl<'i>>::NonTSPseudoClass,
+        _: &mut MatchingContext<'_, 'i, Self::Impl>,
+        _: &mut F,
+    ) -> bool
+    where
+        F: FnMut(&Self, ElementSelectorFlags),
+    {
+        true
+    }
+    fn match_pseudo_element(
+        &self,
+        _: &<Self::Impl as SelectorImpl<'i>>::PseudoElement,
+        _: &mut MatchingContext<'_, 'i, Self::Impl>,
+    ) -> bool {
+        true
+    }
+    fn is_link(&self) -> bool {
+        let data = self.data();
+        matches!(data.tag.as_str(), "a" | "area" | "link") && data.attribute("href").is_some()
+    }
+    fn is_html_slot_element(&self) -> bool {
+        self.data().tag == "slot"
+    }
+    fn has_id(&self, id: &<Self::Impl as SelectorImpl<'i>>::Identifier, case_sensitivity: CaseSensitivity) -> bool {
+        self.data()
+            .attribute("id")
+            .is_some_and(|value| case_sensitivity.eq(value.as_bytes(), id.as_ref().as_bytes()))
+    }
+    fn has_class(
+        &self,
+        name: &<Self::Impl as SelectorImpl<'i>>::Identifier,
+        case_sensitivity: CaseSensitivity,
+    ) -> bool {
+        let has_class = self.data()
+            .attribute("class")
+            .is_some_and(|value| {
+                value
+                    .split_ascii_whitespace()
+                    .any(|class| case_sensitivity.eq(class.as_bytes(), name.as_ref().as_bytes()))
+            });
+        has_class || self.tree.safelist.matches(name.as_ref())
+    }
+    fn imported_part(
+        &self,
+        _: &<Self::Impl as SelectorImpl<'i>>::Identifier,
+    ) -> Option<<Self::Impl as SelectorImpl<'i>>::Identifier> {
+        None
+    }
+    fn is_part(&self, _: &<Self::Impl as SelectorImpl<'i>>::Identifier) -> bool {
+        false
+    }
+    fn is_empty(&self) -> bool {
+        self.data().is_empty
+    }
+    fn is_root(&self) -> bool {
+        self.data().parent.is_none() && self.data().tag == "html"
+    }
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// TESTS
+// ————————————————————————————————————————————————————————————————————————————
+
+#[cfg(test)]
+mod tests {
+    use lightningcss::rules::CssRule;
+    use lightningcss::stylesheet::StyleSheet;
+
+    use super::*;
+
+    const PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<body>
+    <nav><a href="/" id="home" class="Active link">Home</a><a class="link">About</a></nav>
+    <p></p>
+    <div> </div>
+    <section><span>Text</span></section>
+</body>
+</html>"#;
+
+    /// Applies `f` to the selector list of the style rule `selectors {}`.
+    fn with_selectors<T>(selectors: &str, f: impl FnOnce(&SelectorList) -> T) -> T {
+        let source = format!("{selectors} {{}}");
+        let stylesheet = StyleSheet::parse(&source, crate::parser_options()).unwrap();
+        match &stylesheet.rules.0[0] {
+            CssRule::Style(rule) => f(&rule.selectors),
+            rule => panic!("not a style rule: {rule:?}"),
+        }
+    }
+
+    fn safelist(patterns: &[&str]) -> ClassSafelist {
+        let patterns = patterns.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+        let (safelist, errors) = ClassSafelist::new(&patterns);
+        assert!(errors.is_empty());
+        safelist
+    }
+
+    fn is_static(selector: &str, safelist: &ClassSafelist) -> bool {
+        with_selectors(selector, |x| is_statically_matchable(&x.0[0], safelist))
+    }
+
+    fn is_dynamic(selector: &str, safelist: &ClassSafelist) -> bool {
+        with_selectors(selector, |x| has_dynamic_component(&x.0[0], safelist))
+    }
+
+    fn page() -> DocumentTree {
+        DocumentTree::new(&xml_ast::parse_document_str(PAGE).output)
+    }
+
+    fn matches(tree: &DocumentTree, selectors: &str) -> bool {
+        with_selectors(selectors, |x| tree.matches_selector_list(x))
+    }
+
+    #[test]
+    fn detects_dynamic_components() {
+        let none = ClassSafelist::default();
+        assert!(is_dynamic("a:hover", &none));
+        assert!(is_dynamic("p::before", &none));
+        assert!(is_dynamic(":is(.a, :focus)", &none));
+        assert!(is_dynamic(":where(:not(:checked))", &none));
+        assert!(is_dynamic(":nth-child(2 of :checked)", &none));
+        assert!(!is_dynamic(".a > p", &none));
+        assert!(!is_dynamic(":is(.a, .b):not(#c)", &none));
+        assert!(!is_dynamic(":nth-child(2 of .a)", &none));
+    }
+
+    #[test]
+    fn treats_safelisted_classes_as_dynamic() {
+        let safelist = safelist(&["is-*", ".js-open"]);
+        assert!(is_dynamic(".is-active", &safelist));
+        assert!(is_dynamic("nav.js-open", &safelist));
+        assert!(!is_dynamic(".active", &safelist));
+        assert!(!is_dynamic(".js-opened", &safelist));
+        assert!(is_static(":not(.active)", &safelist));
+        assert!(!is_static(":not(.is-active)", &safelist));
+        assert!(is_static(":not(.is-active)", &ClassSafelist::default()));
+    }
+
+    #[test]
+    fn rejects_negated_dynamic_pseudo_classes() {
+        let none = ClassSafelist::default();
+        assert!(is_static("a:hover", &none));
+        assert!(is_static("a:not(.link)", &none));
+        assert!(!is_static("a:not(:hover)", &none));
+        assert!(!is_static("a:not(:is(.a, :focus))", &none));
+        assert!(is_static("a:is(:not(.a), .b)", &none));
+        assert!(!is_static("a:is(:not(:visited), .b)", &none));
+    }
+
+    #[test]
+    fn rejects_has() {
+        let none = ClassSafelist::default();
+        assert!(!is_static("section:has(> span)", &none));
+        assert!(!is_static(":is(.a, section:has(span))", &none));
+        assert!(!is_static(":not(:has(span))", &none));
+        assert!(is_static(":is(.a, section span)", &none));
+    }
+
+    #[test]
+    fn rejects_nth_child_of() {
+        let none = ClassSafelist::default();
+        assert!(is_static("a:nth-child(2n + 1)", &none));
+        assert!(is_static("a:nth-last-of-type(2)", &none));
+        assert!(!is_static("a:nth-child(2 of .link)", &none));
+        assert!(!is_static("a:nth-last-child(2 of .link)", &none));
+        assert!(!is_static(":is(.a, a:nth-child(2 of .link))", &none));
+    }
+
+    #[test]
+    fn matches_tree_structure() {
+        let tree = page();
+        assert!(matches(&tree, "nav a"));
+        assert!(!matches(&tree, "section a"));
+        assert!(matches(&tree, "body > nav > a + a"));
+        assert!(!matches(&tree, "a + nav"));
+        assert!(matches(&tree, "p ~ section span"));
+        assert!(!matches(&tree, "section ~ p"));
+        assert!(matches(&tree, "html:root > body"));
+        assert!(!matches(&tree, "body:root"));
+        assert!(matches(&tree, "nav:first-child"));
+        assert!(!matches(&tree, "section:first-child"));
+        assert!(matches(&tree, "a:last-of-type"));
+        assert!(!matches(&tree, "p:nth-of-type(2)"));
+    }
+
+    #[test]
+    fn matches_attributes() {
+        let tree = page();
+        assert!(matches(&tree, "#home"));
+        assert!(!matches(&tree, "#away"));
+        assert!(matches(&tree, "a.link.Active"));
+        assert!(!matches(&tree, ".active"));
+        assert!(!matches(&tree, ".missing"));
+        assert!(matches(&tree, r#"a[href="/"]"#));
+        assert!(!matches(&tree, r#"a[href^="/about"]"#));
+        assert!(matches(&tree, "a:not([href])"));
+        assert!(!matches(&tree, "nav:not(nav)"));
+    }
+
+    #[test]
+    fn matches_empty_elements() {
+        let tree = page();
+        assert!(matches(&tree, "p:empty"));
+        // Whitespace is indentation, as far as the pretty printer goes.
+        assert!(matches(&tree, "div:empty"));
+        assert!(!matches(&tree, "section:empty"));
+    }
+
+    #[test]
+    fn matches_nth_child() {
+        let tree = page();
+        assert!(matches(&tree, "nav a:nth-child(2)"));
+        assert!(!matches(&tree, "nav a:nth-child(3)"));
+        // Not implemented by `parcel_selectors`, so kept.
+        assert!(matches(&tree, "a:nth-child(2 of .link)"));
+        assert!(matches(&tree, "aside:nth-child(2 of .link)"));
+    }
+
+    #[test]
+    fn assumes_dynamic_components_match() {
+        let tree = page();
+        assert!(matches(&tree, "section:hover"));
+        assert!(matches(&tree, "a::before"));
+        assert!(!matches(&tree, "aside:hover"));
+        // Not decidable from the markup, so kept.
+        assert!(matches(&tree, "aside:has(p)"));
+        assert!(matches(&tree, "aside:not(:hover)"));
+    }
+
+    #[test]
+    fn matches_safelisted_classes() {
+        let tree = page();
+        assert!(!matches(&tree, "nav.is-open"));
+        let tree = tree.with_safelist(safelist(&["is-*"]));
+        assert!(matches(&tree, "nav.is-open a"));
+        assert!(!matches(&tree, "nav.was-open"));
+    }
+
+    #[test]
+    fn matches_any_page() {
+        let mut tree = page();
+        assert!(!matches(&tree, "aside"));
+        tree.push_page(&xml_ast::parse_fragment_str("<aside>Note</aside>").output);
+        assert!(matches(&tree, "aside, .missing"));
+        // Pages are independent roots, with an implied `<html>`.
+        assert!(!matches(&tree, "body aside"));
+        assert!(matches(&tree, ":root > aside"));
+    }
+}
@@ -0,0 +1,73 @@
+//! Site-wide removal of style rules that match no emitted page.
+use lightningcss::printer::PrinterOptions;
+use lightningcss::stylesheet::{ParserOptions, StyleSheet};
+
+use crate::critical::retain_style_rules;
+use crate::matching::DocumentTree;
+
+#[derive(Debug, Clone)]
+pub struct PrunedStylesheet {
+    pub code: String,
+    /// Number of style rules removed.
+    pub removed: usize,
+}
+
+/// Removes the style rules of `source` whose selectors match no element of
+/// `site` (a tree holding every emitted page), reprinting it minified if
+/// `minify` is set.
+pub fn prune_unused_rules(source: &str, site: &DocumentTree, minify: bool) -> Result<PrunedStylesheet, String> {
+    let mut stylesheet = StyleSheet::parse(source, ParserOptions::default()).map_err(|x| x.to_string())?;
+    let mut removed = 0;
+    retain_style_rules(&mut stylesheet.rules, &mut |selectors| {
+        let keep = site.matches_selector_list(selectors);
+        if !keep {
+            removed += 1;
+        }
+        keep
+    });
+    if removed == 0 {
+        return Ok(PrunedStylesheet { code: source.to_string(), removed })
+    }
+    let code = stylesheet
+        .to_css(PrinterOptions { minify, ..Default::default() })
+        .map_err(|x| x.to_string())?
+        .code;
+    Ok(PrunedStylesheet { code, removed })
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// TESTS
+// ————————————————————————————————————————————————————————————————————————————
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = ".used { color: red }\n.unused { color: blue }\n.used:hover .unused { color: green }\n";
+
+    fn site() -> DocumentTree {
+        DocumentTree::new(&xml_ast::parse_fragment_str(r#"<p class="used">Text</p>"#).output)
+    }
+
+    #[test]
+    fn removes_unmatched_rules() {
+        let pruned = prune_unused_rules(SOURCE, &site(), true).unwrap();
+        assert_eq!(pruned.removed, 2);
+        assert_eq!(pruned.code, ".used{color:red}");
+    }
+
+    #[test]
+    fn keeps_the_output_unminified() {
+        let pruned = prune_unused_rules(SOURCE, &site(), false).unwrap();
+        assert_eq!(pruned.removed, 2);
+        assert_eq!(pruned.code, ".used {\n  color: red;\n}\n");
+    }
+
+    #[test]
+    fn keeps_the_source_when_every_rule_matches() {
+        let source = ".used{color:red}";
+        let pruned = prune_unused_rules(source, &site(), false).unwrap();
+        assert_eq!(pruned.removed, 0);
+        assert_eq!(pruned.code, source);
+    }
+}
@@ -0,0 +1,90 @@
+//! Component-scoped stylesheets (`<style scoped>` in included fragments).
+//!
+//! Every top-level selector is restricted to the component's root elements
+//! (which carry a boolean scope attribute) and their descendants by wrapping
+//! one simple selector of its subject compound:
+//!
+//! ```css
+//! .title a:hover { … }
+//! /* becomes */
+//! .title :is(a:where([data-scope-x], [data-scope-x] *)):hover { … }
+//! ```
+//!
+//! `:where()` adds no specificity, so the rule keeps the specificity the
+//! author wrote.
+use lightningcss::printer::PrinterOptions;
+use lightningcss::rules::{CssRule, CssRuleList};
+use lightningcss::selector::{Combinator, Component, Selector};
+use lightningcss::stylesheet::StyleSheet;
+use lightningcss::values::ident::Ident;
+
+/// Rewrites the selectors of `source` to only match elements with the
+/// boolean attribute `scope_attribute`, or inside one.
+pub fn scope_stylesheet(source: &str, scope_attribute: &str) -> Result<String, String> {
+    let mut stylesheet = StyleSheet::parse(source, crate::parser_options()).map_err(|x| x.to_string())?;
+    let scope = scope_component(scope_attribute);
+    scope_rules(&mut stylesheet.rules, &scope);
+    stylesheet
+        .to_css(PrinterOptions { minify: false, ..Default::default() })
+        .map(|x| x.code)
+        .map_err(|x| x.to_string())
+}
+
+/// `:where([attribute], [attribute] *)`
+fn scope_component<'i>(attribute: &str) -> Component<'i> {
+    let name = Ident(attribute.to_string().into());
+    let attribute = Component::AttributeInNoNamespaceExists { local_name: name.clone(), local_name_lower: name };
+    let root = Selector::from(attribute.clone());
+    let descendant = Selector::from(vec![
+        attribute,
+        Component::Combinator(Combinator::Descendant),
+        Component::ExplicitUniversalType,
+    ]);
+    Component::Where(vec![root, descendant].into_boxed_slice())
+}
+
+/// Nested style rules are relative to their (already scoped) parent and are
+/// left alone.
+fn scope_rules<'i>(rules: &mut CssRuleList<'i>, scope: &Component<'i>) {
+    for rule in rules.0.iter_mut() {
+        match rule {
+            CssRule::Style(rule) => {
+                for selector in rule.selectors.0.iter_mut() {
+                    scope_selector(selector, scope);
+                }
+            }
+            CssRule::Media(rule) => scope_rules(&mut rule.rules, scope),
+            CssRule::Supports(rule) => scope_rules(&mut rule.rules, scope),
+            CssRule::LayerBlock(rule) => scope_rules(&mut rule.rules, scope),
+            CssRule::Container(rule) => scope_rules(&mut rule.rules, scope),
+            CssRule::StartingStyle(rule) => scope_rules(&mut rule.rules, scope),
+            CssRule::MozDocument(rule) => scope_rules(&mut rule.rules, scope),
+            _ => (),
+        }
+    }
+}
+
+/// Wraps the first simple selector of the subject compound (the rightmost
+/// one, not counting a pseudo-element) as `:is(x:where(…))`. Selectors
+/// without one (a lone `::selection`) are left unscoped.
+fn scope_selector<'i>(selector: &mut Selector<'i>, scope: &Component<'i>) {
+    let mut components = selector.iter_mut_raw_match_order().collect::<Vec<_>>();
+    // In match order a pseudo-element comes first, in its own compound.
+    let subject_start = components
+        .iter()
+        .position(|x| matches!(x, Component::Combinator(Combinator::PseudoElement)))
+        .filter(|index| {
+            !components[..*index].iter().any(|x| matches!(x, Component::Combinator(_)))
+        })
+        .map(|index| index + 1)
+        .unwrap_or(0);
+    let target = components[subject_start..]
+        .iter_mut()
+        .take_while(|x| !matches!(x, Component::Combinator(_)))
+        .next();
+    if let Some(target) = target {
+        let original = std::mem::replace(&mut **target, Component::ExplicitUniversalType);
+        let scoped = Selector::from(vec![original, scope.clone()]);
+        **target = Component::Is(vec![scoped].into_boxed_slice());
+    }
+}
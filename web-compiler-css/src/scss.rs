@@ -0,0 +1,139 @@
+//! SCSS (`.scss` files and `<style lang="scss">`), compiled in-process with
+//! `grass` ahead of the [`CssPreprocessor`](crate::CssPreprocessor), so the
+//! `url()`s of the compiled CSS are virtualized like any other stylesheet's.
+//!
+//! Sass doesn't rebase `url()`s: they resolve against the file being
+//! compiled (the `.scss` file, or the page for inline styles), not the
+//! partial they were written in. `@use`/`@import` paths resolve against the
+//! importing file, then the project root.
+//!
+//! Every partial read while compiling is recorded as a deferred dependency of
+//! the compiled file; it is tracked, but never emitted itself.
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use macro_types::lexical_env::{MacroIO, SourceHostRef, StylesheetError};
+use macro_types::project::DependencyRelation;
+
+use crate::{ModifiedFlag, Payload};
+
+pub const SCSS_LANG: &'static str = "scss";
+
+pub fn is_scss_path(path: &Path) -> bool {
+    path.extension().is_some_and(|x| x.eq_ignore_ascii_case(SCSS_LANG))
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// COMPILER
+// ————————————————————————————————————————————————————————————————————————————
+
+pub struct ScssCompiler<'a> {
+    source_context: SourceHostRef<'a>,
+    style_element: bool,
+}
+
+impl<'a> ScssCompiler<'a> {
+    pub fn new(source_context: SourceHostRef<'a>) -> Self {
+        Self { source_context, style_element: false }
+    }
+    /// The stylesheet is a `<style lang="scss">` element of the source file.
+    pub fn for_style_element(mut self) -> Self {
+        self.style_element = true;
+        self
+    }
+    /// Compiles `source_code` as if it were the contents of the source file.
+    /// A stylesheet that fails to compile is recorded in `stylesheet_errors`
+    /// and returned unchanged, like invalid CSS.
+    pub fn execute(self, source_code: &str) -> MacroIO<Payload<String>> {
+        let source_file = self.source_context.file_input().source_file();
+        let fs = RecordingFs {
+            entry: (source_file.to_path_buf(), source_code.to_string()),
+            imports: RefCell::default(),
+        };
+        let options = grass::Options::default()
+            .fs(&fs)
+            .input_syntax(grass::InputSyntax::Scss)
+            .load_path(&self.source_context.project_context().project_root);
+        let compiled = grass::from_path(source_file, &options);
+        let dependencies = fs.imports
+            .into_inner()
+            .into_iter()
+            .map(|import| {
+                let relative = source_file
+                    .parent()
+                    .and_then(|dir| pathdiff::diff_paths(&import, dir))
+                    .unwrap_or(import);
+                self.source_context.file_input().with_dependency_relation(relative.to_string_lossy())
+            })
+            .collect::<Vec<DependencyRelation>>();
+        let (payload, error) = match compiled {
+            Ok(compiled) => (Payload { value: compiled, modified: ModifiedFlag::Modified }, None),
+            Err(error) => {
+                let payload = Payload { value: source_code.to_string(), modified: ModifiedFlag::Default };
+                (payload, Some(self.stylesheet_error(*error)))
+            }
+        };
+        MacroIO::wrap(payload).and_modify_context(|ctx| {
+            ctx.deferred_dependencies.extend(dependencies);
+            ctx.stylesheet_errors.extend(error);
+        })
+    }
+    /// Located in the file the error is in, which may be a partial.
+    fn stylesheet_error(&self, error: grass::Error) -> StylesheetError {
+        let source_file = self.source_context.file_input().source_file();
+        match error.kind() {
+            grass::ErrorKind::ParseError { message, loc, .. } => {
+                let file = PathBuf::from(loc.file.name());
+                StylesheetError {
+                    style_element: self.style_element && file == source_file,
+                    file,
+                    line: Some(loc.begin.line as u32 + 1),
+                    column: Some(loc.begin.column as u32 + 1),
+                    message: format!("failed to compile SCSS: {message}"),
+                }
+            }
+            kind => {
+                let message = match kind {
+                    grass::ErrorKind::IoError(error) => error.to_string(),
+                    grass::ErrorKind::FromUtf8Error(error) => error,
+                    _ => String::from("unknown error"),
+                };
+                StylesheetError {
+                    file: source_file.to_path_buf(),
+                    style_element: self.style_element,
+                    line: None,
+                    column: None,
+                    message: format!("failed to compile SCSS: {message}"),
+                }
+            }
+        }
+    }
+}
+
+/// The file system as `grass` sees it: the entry file is served from memory
+/// (it may be an inline `<style>`), everything else is read from disk and
+/// recorded.
+#[derive(Debug)]
+struct RecordingFs {
+    entry: (PathBuf, String),
+    imports: RefCell<BTreeSet<PathBuf>>,
+}
+
+impl grass::Fs for RecordingFs {
+    fn is_dir(&self, path: &Path) -> bool {
+        path.is_dir()
+    }
+    fn is_file(&self, path: &Path) -> bool {
+        path == self.entry.0 || path.is_file()
+    }
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        if path == self.entry.0 {
+            return Ok(self.entry.1.as_bytes().to_vec())
+        }
+        let contents = std::fs::read(path)?;
+        self.imports.borrow_mut().insert(path_clean::clean(path));
+        Ok(contents)
+    }
+}
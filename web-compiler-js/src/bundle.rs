@@ -0,0 +1,576 @@
+//! Bundling of local ES modules.
+//!
+//! The entry and every module it imports through a relative specifier
+//! (`./x`, `../x`; the extension may be omitted, and `./x.js` also finds
+//! `x.ts`) are wrapped in factory functions of a single script:
+//!
+//! ```js
+//! import * as __web_compiler_external_0 from "https://esm.sh/lit";
+//! (() => {
+//! "use strict";
+//! // runtime: __web_compiler_define, __web_compiler_require, …
+//! // util.ts
+//! __web_compiler_define(1, function (__web_compiler_exports) {
+//! __web_compiler_export(__web_compiler_exports, { "greet": () => greet });
+//! export function greet() { … }     // `export` blanked out
+//! });
+//! // main.ts
+//! __web_compiler_define(0, function (__web_compiler_exports) {
+//! const __web_compiler_import_1 = __web_compiler_require(1);
+//! __web_compiler_import_1.greet(__web_compiler_external_0.html);
+//! });
+//! __web_compiler_require(0);
+//! })();
+//! ```
+//!
+//! References to imported bindings are rewritten to property accesses on the
+//! exporting module's exports object, so bindings stay live as they are
+//! between ES modules (including across import cycles). Other specifiers
+//! (packages, URLs, absolute paths) are hoisted to the top of the bundle and
+//! left to the browser; dynamic `import()` is left as is.
+//!
+//! An entry without local imports is passed through as is (with types
+//! stripped).
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use oxc_allocator::Allocator;
+use oxc_ast::AstKind;
+use oxc_ast::ast::*;
+use oxc_ast_visit::{walk, Visit};
+use oxc_ecmascript::BoundNames;
+use oxc_parser::Parser;
+use oxc_semantic::{ScopeFlags, Semantic, SemanticBuilder};
+use oxc_span::{GetSpan, SourceType};
+
+use crate::typescript::{is_erased_declaration, TypeStripper};
+use crate::{apply_edits, is_script_path, is_typescript_path, Edit, ScriptError, SCRIPT_EXTENSIONS};
+
+const RUNTIME: &str = r#"const __web_compiler_modules = [];
+function __web_compiler_define(id, factory) {
+    __web_compiler_modules[id] = { factory, exports: null };
+}
+function __web_compiler_require(id) {
+    const module = __web_compiler_modules[id];
+    if (module.exports === null) {
+        module.exports = Object.create(null);
+        module.factory(module.exports);
+    }
+    return module.exports;
+}
+function __web_compiler_export(exports, getters) {
+    for (const name of Object.keys(getters)) {
+        Object.defineProperty(exports, name, { enumerable: true, get: getters[name] });
+    }
+}
+function __web_compiler_export_star(exports, module) {
+    for (const name of Object.keys(module)) {
+        if (name !== "default" && !(name in exports)) {
+            Object.defineProperty(exports, name, { enumerable: true, get: () => module[name] });
+        }
+    }
+}
+"#;
+
+const DEFAULT_EXPORT_BINDING: &str = "__web_compiler_default";
+
+pub(crate) struct Bundle {
+    pub(crate) code: String,
+    pub(crate) modules: Vec<PathBuf>,
+    /// The output has `import` or `export` statements, so it must be loaded
+    /// with `<script type="module">`.
+    pub(crate) is_module: bool,
+}
+
+pub(crate) fn bundle(entry: &Path) -> Result<Bundle, ScriptError> {
+    let mut graph = ModuleGraph::default();
+    graph.local(path_clean::clean(entry));
+    let mut linked = Vec::<LinkedModule>::new();
+    while linked.len() < graph.modules.len() {
+        let path = graph.modules[linked.len()].clone();
+        linked.push(link_module(&path, &mut graph)?);
+    }
+    if linked.len() == 1 {
+        let entry = linked.remove(0);
+        let is_module = entry.has_module_syntax;
+        return Ok(Bundle { code: entry.standalone, modules: graph.modules, is_module })
+    }
+    if let Some(error) = linked.iter_mut().find_map(|x| x.unbundlable.take()) {
+        return Err(error)
+    }
+    let entry_dir = entry.parent().unwrap_or(Path::new(""));
+    let mut code = String::new();
+    for (id, specifier) in graph.externals.iter().enumerate() {
+        let namespace = ImportTarget::External(id).namespace();
+        code.push_str(&format!("import * as {namespace} from {};\n", js_string(specifier)));
+    }
+    code.push_str("(() => {\n\"use strict\";\n");
+    code.push_str(RUNTIME);
+    for (id, module) in linked.iter().enumerate() {
+        let path = &graph.modules[id];
+        let display_path = pathdiff::diff_paths(path, entry_dir).unwrap_or_else(|| path.clone());
+        code.push_str(&format!("// {}\n", display_path.display()));
+        code.push_str(&format!("__web_compiler_define({id}, function (__web_compiler_exports) {{\n"));
+        if !module.exports.is_empty() {
+            let getters = module.exports
+                .iter()
+                .map(|(name, expression)| format!("{}: () => {expression}", js_string(name)))
+                .collect::<Vec<_>>()
+                .join(", ");
+            code.push_str(&format!("__web_compiler_export(__web_compiler_exports, {{ {getters} }});\n"));
+        }
+        for dependency in module.requires.iter() {
+            let namespace = ImportTarget::Local(*dependency).namespace();
+            code.push_str(&format!("const {namespace} = __web_compiler_require({dependency});\n"));
+        }
+        for target in module.star_exports.iter() {
+            code.push_str(&format!("__web_compiler_export_star(__web_compiler_exports, {});\n", target.namespace()));
+        }
+        code.push_str(&module.body);
+        code.push_str("\n});\n");
+    }
+    code.push_str("__web_compiler_require(0);\n})();\n");
+    let is_module = !graph.externals.is_empty();
+    Ok(Bundle { code, modules: graph.modules, is_module })
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// MODULE GRAPH
+// ————————————————————————————————————————————————————————————————————————————
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum ImportTarget {
+    /// Index of a bundled module.
+    Local(usize),
+    /// Index of a hoisted external import.
+    External(usize),
+}
+
+impl ImportTarget {
+    /// The variable holding the target's exports object.
+    fn namespace(self) -> String {
+        match self {
+            Self::Local(id) => format!("__web_compiler_import_{id}"),
+            Self::External(id) => format!("__web_compiler_external_{id}"),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct ModuleGraph {
+    /// Bundled modules in discovery order; the entry is `0`.
+    modules: Vec<PathBuf>,
+    module_ids: HashMap<PathBuf, usize>,
+    externals: Vec<String>,
+    external_ids: HashMap<String, usize>,
+}
+
+impl ModuleGraph {
+    fn local(&mut self, path: PathBuf) -> usize {
+        if let Some(id) = self.module_ids.get(&path) {
+            return *id
+        }
+        let id = self.modules.len();
+        self.modules.push(path.clone());
+        self.module_ids.insert(path, id);
+        id
+    }
+    fn external(&mut self, specifier: &str) -> usize {
+        if let Some(id) = self.external_ids.get(specifier) {
+            return *id
+        }
+        let id = self.externals.len();
+        self.externals.push(specifier.to_string());
+        self.external_ids.insert(specifier.to_string(), id);
+        id
+    }
+}
+
+fn is_local_specifier(specifier: &str) -> bool {
+    specifier.starts_with("./") || specifier.starts_with("../")
+}
+
+fn resolve_local_module(from: &Path, specifier: &str) -> Option<PathBuf> {
+    let base = path_clean::clean(from.parent()?.join(specifier));
+    let mut candidates = vec![base.clone()];
+    // TypeScript sources import each other by their emitted `.js` paths.
+    match base.extension().and_then(|x| x.to_str()) {
+        Some("js") => candidates.push(base.with_extension("ts")),
+        Some("mjs") => candidates.push(base.with_extension("mts")),
+        _ => (),
+    }
+    for extension in SCRIPT_EXTENSIONS {
+        let mut candidate = base.clone().into_os_string();
+        candidate.push(format!(".{extension}"));
+        candidates.push(PathBuf::from(candidate));
+    }
+    for extension in SCRIPT_EXTENSIONS {
+        candidates.push(base.join(format!("index.{extension}")));
+    }
+    candidates
+        .into_iter()
+        .find(|x| x.is_file() && is_script_path(x))
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// MODULE LINKING
+// ————————————————————————————————————————————————————————————————————————————
+
+struct LinkedModule {
+    /// Module source with types stripped and `import`/`export` rewritten.
+    body: String,
+    /// Module source with types stripped, for an entry without local imports.
+    standalone: String,
+    /// Bundled modules this one imports from.
+    requires: Vec<usize>,
+    /// Export name → expression evaluating to the exported binding.
+    exports: Vec<(String, String)>,
+    star_exports: Vec<ImportTarget>,
+    has_module_syntax: bool,
+    /// Top-level `await` or `import.meta`, which bundled modules can't use:
+    /// they are wrapped in functions of a single script, and `import.meta`
+    /// would describe the bundle rather than the module.
+    unbundlable: Option<ScriptError>,
+}
+
+fn source_type(path: &Path) -> SourceType {
+    match path.extension().and_then(|x| x.to_str()) {
+        Some("ts") | Some("mts") => SourceType::ts(),
+        Some("mjs") => SourceType::mjs(),
+        _ => SourceType::unambiguous(),
+    }
+}
+
+fn link_module(path: &Path, graph: &mut ModuleGraph) -> Result<LinkedModule, ScriptError> {
+    let source = std::fs::read_to_string(path)
+        .map_err(|error| ScriptError::new(path, error.to_string()))?;
+    let allocator = Allocator::default();
+    let parsed = Parser::new(&allocator, &source, source_type(path)).parse();
+    if let Some(error) = parsed.errors.first() {
+        let offset = error.labels
+            .as_ref()
+            .and_then(|x| x.first())
+            .map(|x| x.offset() as u32)
+            .unwrap_or(0);
+        return Err(ScriptError::at(path, &source, offset, error.message.to_string()))
+    }
+    let program = &parsed.program;
+    let is_typescript = is_typescript_path(path);
+    let mut edits = Vec::new();
+    if is_typescript {
+        let mut stripper = TypeStripper::new(&source);
+        stripper.visit_program(program);
+        if let Some((offset, message)) = stripper.errors.first() {
+            return Err(ScriptError::at(path, &source, *offset, message.clone()))
+        }
+        edits = stripper.edits;
+    }
+    let import_meta = parsed.module_record.import_metas.first().map(|x| x.start);
+    let has_module_syntax = parsed.module_record.has_module_syntax || import_meta.is_some();
+    if !has_module_syntax {
+        let body = apply_edits(&source, edits);
+        return Ok(LinkedModule {
+            standalone: body.clone(),
+            body,
+            requires: Vec::default(),
+            exports: Vec::default(),
+            star_exports: Vec::default(),
+            has_module_syntax,
+            unbundlable: None,
+        })
+    }
+    let mut top_level_await = TopLevelAwait::default();
+    top_level_await.visit_program(program);
+    let unbundlable = match (top_level_await.offset, import_meta) {
+        (Some(offset), _) => Some((offset, "top-level await is not supported in bundled modules")),
+        (None, Some(offset)) => Some((offset, "`import.meta` is not supported in bundled modules")),
+        (None, None) => None,
+    };
+    let unbundlable = unbundlable.map(|(offset, message)| ScriptError::at(path, &source, offset, message));
+    let stripped = edits.clone();
+    let semantic = SemanticBuilder::new().build(program).semantic;
+    let mut linker = ModuleLinker {
+        path,
+        source: &source,
+        is_typescript,
+        graph,
+        semantic: &semantic,
+        edits,
+        elided_imports: Vec::default(),
+        requires: Vec::default(),
+        exports: Vec::default(),
+        star_exports: Vec::default(),
+        imported_bindings: HashMap::default(),
+    };
+    // Imports are hoisted, so their bindings are known before any export
+    // specifier refers to them.
+    for statement in program.body.iter() {
+        if let Statement::ImportDeclaration(declaration) = statement {
+            linker.link_import(declaration)?;
+        }
+    }
+    for statement in program.body.iter() {
+        match statement {
+            Statement::ExportNamedDeclaration(declaration) => linker.link_export_named(declaration)?,
+            Statement::ExportDefaultDeclaration(declaration) => linker.link_export_default(declaration),
+            Statement::ExportAllDeclaration(declaration) => linker.link_export_all(declaration)?,
+            _ => (),
+        }
+    }
+    let ModuleLinker { edits, elided_imports, requires, exports, star_exports, .. } = linker;
+    let standalone = stripped.into_iter().chain(elided_imports).collect::<Vec<_>>();
+    Ok(LinkedModule {
+        body: apply_edits(&source, edits),
+        standalone: apply_edits(&source, standalone),
+        requires,
+        exports,
+        star_exports,
+        has_module_syntax,
+        unbundlable,
+    })
+}
+
+struct ModuleLinker<'l, 'a> {
+    path: &'l Path,
+    source: &'l str,
+    is_typescript: bool,
+    graph: &'l mut ModuleGraph,
+    semantic: &'l Semantic<'a>,
+    edits: Vec<Edit>,
+    /// Imports only used as types.
+    elided_imports: Vec<Edit>,
+    requires: Vec<usize>,
+    exports: Vec<(String, String)>,
+    star_exports: Vec<ImportTarget>,
+    /// Local name → rewritten expression of every imported binding.
+    imported_bindings: HashMap<String, String>,
+}
+
+impl<'l, 'a> ModuleLinker<'l, 'a> {
+    fn resolve(&mut self, source: &StringLiteral) -> Result<ImportTarget, ScriptError> {
+        let specifier = source.value.as_str();
+        if !is_local_specifier(specifier) {
+            return Ok(ImportTarget::External(self.graph.external(specifier)))
+        }
+        match resolve_local_module(self.path, specifier) {
+            Some(path) => {
+                let id = self.graph.local(path);
+                if !self.requires.contains(&id) {
+                    self.requires.push(id);
+                }
+                Ok(ImportTarget::Local(id))
+            }
+            None => Err(ScriptError::at(
+                self.path,
+                self.source,
+                source.span.start,
+                format!("cannot resolve module {specifier:?} (bundled modules must be .js, .mjs, .ts or .mts files)"),
+            )),
+        }
+    }
+    /// Whether `binding` is used as a value (TypeScript drops imports that
+    /// are only used as types).
+    fn has_value_references(&self, binding: &BindingIdentifier) -> bool {
+        let scoping = self.semantic.scoping();
+        binding.symbol_id
+            .get()
+            .map(|symbol_id| {
+                scoping
+                    .get_resolved_reference_ids(symbol_id)
+                    .iter()
+                    .any(|x| scoping.get_reference(*x).flags().is_value())
+            })
+            .unwrap_or(false)
+    }
+    fn rewrite_references(&mut self, binding: &BindingIdentifier, expression: &str) {
+        let symbol_id = match binding.symbol_id.get() {
+            Some(x) => x,
+            None => return,
+        };
+        let scoping = self.semantic.scoping();
+        let nodes = self.semantic.nodes();
+        for reference_id in scoping.get_resolved_reference_ids(symbol_id) {
+            let node_id = scoping.get_reference(*reference_id).node_id();
+            let span = nodes.get_node(node_id).kind().span();
+            let replacement = match nodes.parent_kind(node_id) {
+                AstKind::ObjectProperty(property) if property.shorthand => {
+                    format!("{}: {expression}", binding.name)
+                }
+                _ => expression.to_string(),
+            };
+            self.edits.push(Edit::replace(span.start, span.end, replacement));
+        }
+    }
+    fn link_import(&mut self, declaration: &ImportDeclaration<'a>) -> Result<(), ScriptError> {
+        if declaration.import_kind.is_type() {
+            return Ok(())
+        }
+        let mut bindings = Vec::new();
+        if let Some(specifiers) = declaration.specifiers.as_ref() {
+            for specifier in specifiers.iter() {
+                let (local, member) = match specifier {
+                    ImportDeclarationSpecifier::ImportSpecifier(x) if x.import_kind.is_type() => continue,
+                    ImportDeclarationSpecifier::ImportSpecifier(x) => (&x.local, Some(x.imported.name().to_string())),
+                    ImportDeclarationSpecifier::ImportDefaultSpecifier(x) => (&x.local, Some(String::from("default"))),
+                    ImportDeclarationSpecifier::ImportNamespaceSpecifier(x) => (&x.local, None),
+                };
+                if self.is_typescript && !self.has_value_references(local) {
+                    continue;
+                }
+                bindings.push((local, member));
+            }
+            if bindings.is_empty() && !specifiers.is_empty() && self.is_typescript {
+                let elided = Edit::blank(declaration.span.start, declaration.span.end);
+                self.elided_imports.push(elided.clone());
+                self.edits.push(elided);
+                return Ok(())
+            }
+        }
+        let target = self.resolve(&declaration.source)?;
+        for (local, member) in bindings {
+            let expression = match member {
+                Some(member) => member_expression(&target.namespace(), &member),
+                None => target.namespace(),
+            };
+            self.rewrite_references(local, &expression);
+            self.imported_bindings.insert(local.name.to_string(), expression);
+        }
+        self.edits.push(Edit::blank(declaration.span.start, declaration.span.end));
+        Ok(())
+    }
+    fn link_export_named(&mut self, declaration: &ExportNamedDeclaration<'a>) -> Result<(), ScriptError> {
+        if declaration.export_kind.is_type() {
+            return Ok(())
+        }
+        if let Some(inner) = declaration.declaration.as_ref() {
+            if self.is_typescript && is_erased_declaration(inner) {
+                return Ok(())
+            }
+            inner.bound_names(&mut |binding| {
+                self.exports.push((binding.name.to_string(), binding.name.to_string()));
+            });
+            self.edits.push(Edit::blank(declaration.span.start, inner.span().start));
+            return Ok(())
+        }
+        let target = match declaration.source.as_ref() {
+            Some(source) => Some(self.resolve(source)?),
+            None => None,
+        };
+        for specifier in declaration.specifiers.iter() {
+            if specifier.export_kind.is_type() {
+                continue;
+            }
+            let local = specifier.local.name().to_string();
+            let expression = match target {
+                Some(target) => member_expression(&target.namespace(), &local),
+                None => self.imported_bindings.get(&local).cloned().unwrap_or(local),
+            };
+            self.exports.push((specifier.exported.name().to_string(), expression));
+        }
+        self.edits.push(Edit::blank(declaration.span.start, declaration.span.end));
+        Ok(())
+    }
+    fn link_export_default(&mut self, declaration: &ExportDefaultDeclaration<'a>) {
+        let (inner_start, binding) = match &declaration.declaration {
+            ExportDefaultDeclarationKind::TSInterfaceDeclaration(_) => return,
+            ExportDefaultDeclarationKind::FunctionDeclaration(x) if x.is_typescript_syntax() => return,
+            ExportDefaultDeclarationKind::FunctionDeclaration(x) => (x.span.start, x.id.as_ref().map(|x| x.name)),
+            ExportDefaultDeclarationKind::ClassDeclaration(x) => (x.span.start, x.id.as_ref().map(|x| x.name)),
+            expression => (expression.span().start, None),
+        };
+        match binding {
+            Some(name) => {
+                self.edits.push(Edit::blank(declaration.span.start, inner_start));
+                self.exports.push((String::from("default"), name.to_string()));
+            }
+            None => {
+                let binding = format!("const {DEFAULT_EXPORT_BINDING} = ");
+                self.edits.push(Edit::replace(declaration.span.start, inner_start, binding));
+                if !self.source[..declaration.span.end as usize].ends_with(';') {
+                    self.edits.push(Edit::insert(declaration.span.end, ";"));
+                }
+                self.exports.push((String::from("default"), DEFAULT_EXPORT_BINDING.to_string()));
+            }
+        }
+    }
+    fn link_export_all(&mut self, declaration: &ExportAllDeclaration<'a>) -> Result<(), ScriptError> {
+        if declaration.export_kind.is_type() {
+            return Ok(())
+        }
+        let target = self.resolve(&declaration.source)?;
+        match declaration.exported.as_ref() {
+            Some(exported) => self.exports.push((exported.name().to_string(), target.namespace())),
+            None => self.star_exports.push(target),
+        }
+        self.edits.push(Edit::blank(declaration.span.start, declaration.span.end));
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct TopLevelAwait {
+    function_depth: usize,
+    offset: Option<u32>,
+}
+
+impl<'a> Visit<'a> for TopLevelAwait {
+    fn visit_function(&mut self, it: &Function<'a>, flags: ScopeFlags) {
+        self.function_depth += 1;
+        walk::walk_function(self, it, flags);
+        self.function_depth -= 1;
+    }
+    fn visit_arrow_function_expression(&mut self, it: &ArrowFunctionExpression<'a>) {
+        self.function_depth += 1;
+        walk::walk_arrow_function_expression(self, it);
+        self.function_depth -= 1;
+    }
+    fn visit_await_expression(&mut self, it: &AwaitExpression<'a>) {
+        if self.function_depth == 0 && self.offset.is_none() {
+            self.offset = Some(it.span.start);
+        }
+        walk::walk_await_expression(self, it);
+    }
+    fn visit_for_of_statement(&mut self, it: &ForOfStatement<'a>) {
+        if it.r#await && self.function_depth == 0 && self.offset.is_none() {
+            self.offset = Some(it.span.start);
+        }
+        walk::walk_for_of_statement(self, it);
+    }
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// HELPERS
+// ————————————————————————————————————————————————————————————————————————————
+
+fn js_string(value: &str) -> String {
+    let mut output = String::from("\"");
+    for character in value.chars() {
+        match character {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\u{2028}' => output.push_str("\\u2028"),
+            '\u{2029}' => output.push_str("\\u2029"),
+            x => output.push(x),
+        }
+    }
+    output.push('"');
+    output
+}
+
+fn is_identifier_name(value: &str) -> bool {
+    let mut characters = value.chars();
+    let is_start = |x: char| x.is_alphabetic() || x == '_' || x == '$';
+    characters.next().is_some_and(is_start)
+        && characters.all(|x| is_start(x) || x.is_numeric())
+}
+
+/// `namespace.member`, or `namespace["member"]` for string export names.
+fn member_expression(namespace: &str, member: &str) -> String {
+    if is_identifier_name(member) {
+        format!("{namespace}.{member}")
+    } else {
+        format!("{namespace}[{}]", js_string(member))
+    }
+}
@@ -0,0 +1,173 @@
+//! In-process script compilation for `<script src>` targets: TypeScript type
+//! stripping, bundling of local ES module imports, and minification.
+//!
+//! Everything is done on source text with the oxc parser providing spans;
+//! there is no code generator, so the output keeps the author's formatting
+//! (types are blanked out with spaces, line breaks are preserved) unless it is
+//! minified.
+use std::path::{Path, PathBuf};
+
+mod bundle;
+mod typescript;
+
+// ————————————————————————————————————————————————————————————————————————————
+// BASICS
+// ————————————————————————————————————————————————————————————————————————————
+
+/// File extensions handled by the script compiler.
+pub const SCRIPT_EXTENSIONS: &[&str] = &["js", "mjs", "ts", "mts"];
+
+pub fn is_script_path(path: &Path) -> bool {
+    path.extension()
+        .and_then(|x| x.to_str())
+        .is_some_and(|x| SCRIPT_EXTENSIONS.contains(&x))
+}
+
+pub fn is_typescript_path(path: &Path) -> bool {
+    path.extension()
+        .and_then(|x| x.to_str())
+        .is_some_and(|x| x == "ts" || x == "mts")
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ScriptOptions {
+    /// Minify the bundle (production builds).
+    pub minify: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct CompiledScript {
+    pub code: String,
+    /// Every local module that was bundled, the entry first.
+    pub modules: Vec<PathBuf>,
+    /// The output has `import` or `export` statements, so it must be loaded
+    /// with `<script type="module">`.
+    pub is_module: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct ScriptError {
+    pub path: PathBuf,
+    /// One-based line and column.
+    pub location: Option<(usize, usize)>,
+    pub message: String,
+}
+
+impl ScriptError {
+    pub(crate) fn new(path: &Path, message: impl Into<String>) -> Self {
+        Self { path: path.to_path_buf(), location: None, message: message.into() }
+    }
+    pub(crate) fn at(path: &Path, source: &str, offset: u32, message: impl Into<String>) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            location: Some(line_column(source, offset as usize)),
+            message: message.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.location {
+            Some((line, column)) => write!(f, "{:?}:{line}:{column}: {}", self.path, self.message),
+            None => write!(f, "{:?}: {}", self.path, self.message),
+        }
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+fn line_column(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset.min(source.len())];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map(|x| x + 1).unwrap_or(0);
+    let column = before[line_start..].chars().count() + 1;
+    (line, column)
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// ENTRYPOINT
+// ————————————————————————————————————————————————————————————————————————————
+
+/// Compiles the script at `entry` (and the local modules it imports) into a
+/// single file.
+pub fn compile_script(entry: &Path, options: ScriptOptions) -> Result<CompiledScript, ScriptError> {
+    let bundle = bundle::bundle(entry)?;
+    let code = if options.minify {
+        minify(entry, &bundle.code, bundle.is_module)
+    } else {
+        bundle.code
+    };
+    Ok(CompiledScript { code, modules: bundle.modules, is_module: bundle.is_module })
+}
+
+/// Falls back to the unminified code (with a warning) when the minifier
+/// rejects it.
+fn minify(entry: &Path, code: &str, is_module: bool) -> String {
+    let session = minify_js::Session::new();
+    let top_level_mode = if is_module {
+        minify_js::TopLevelMode::Module
+    } else {
+        minify_js::TopLevelMode::Global
+    };
+    let mut output = Vec::new();
+    match minify_js::minify(&session, top_level_mode, code.as_bytes(), &mut output) {
+        Ok(()) => String::from_utf8(output).unwrap_or_else(|_| code.to_string()),
+        Err(error) => {
+            eprintln!("⚠️ {entry:?} skipping minification: {error}");
+            code.to_string()
+        }
+    }
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// SOURCE EDITS
+// ————————————————————————————————————————————————————————————————————————————
+
+/// A replacement of `start..end` in the module source.
+#[derive(Debug, Clone)]
+pub(crate) struct Edit {
+    start: u32,
+    end: u32,
+    /// `None` blanks the range out with spaces, keeping line breaks.
+    replacement: Option<String>,
+}
+
+impl Edit {
+    pub(crate) fn blank(start: u32, end: u32) -> Self {
+        Self { start, end, replacement: None }
+    }
+    pub(crate) fn replace(start: u32, end: u32, replacement: impl Into<String>) -> Self {
+        Self { start, end, replacement: Some(replacement.into()) }
+    }
+    pub(crate) fn insert(at: u32, text: impl Into<String>) -> Self {
+        Self::replace(at, at, text)
+    }
+}
+
+/// Applies `edits`; an edit that overlaps an earlier (or enclosing) one is
+/// dropped, e.g. a rewritten reference inside a blanked type annotation.
+pub(crate) fn apply_edits(source: &str, mut edits: Vec<Edit>) -> String {
+    edits.sort_by_key(|x| (x.start, x.end > x.start, std::cmp::Reverse(x.end)));
+    let mut output = String::with_capacity(source.len());
+    let mut cursor = 0;
+    for edit in edits {
+        let (start, end) = (edit.start as usize, edit.end as usize);
+        if start < cursor {
+            continue;
+        }
+        output.push_str(&source[cursor..start]);
+        match edit.replacement {
+            Some(text) => output.push_str(&text),
+            None => {
+                let blanked = source[start..end]
+                    .chars()
+                    .map(|x| if x == '\n' || x == '\r' { x } else { ' ' });
+                output.extend(blanked);
+            }
+        }
+        cursor = end;
+    }
+    output.push_str(&source[cursor..]);
+    output
+}
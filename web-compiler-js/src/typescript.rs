@@ -0,0 +1,286 @@
+//! TypeScript type stripping.
+//!
+//! Type-only syntax (annotations, type parameters, interfaces, `declare`
+//! declarations, overload signatures, `import type`, modifiers such as
+//! `private`, …) is blanked out with spaces, which leaves plain JavaScript
+//! with unchanged line numbers.
+//!
+//! Only erasable syntax is supported: enums, namespaces, parameter properties
+//! and `import x = require(…)` generate runtime code and are reported as
+//! errors instead.
+use oxc_ast::ast::*;
+use oxc_ast_visit::{walk, Visit};
+use oxc_span::{GetSpan, Span};
+
+use crate::Edit;
+
+/// Class and class member modifiers that only exist in TypeScript.
+const TYPESCRIPT_MODIFIERS: &[&str] = &[
+    "public",
+    "private",
+    "protected",
+    "readonly",
+    "override",
+    "declare",
+    "abstract",
+];
+
+pub(crate) struct TypeStripper<'s> {
+    source: &'s str,
+    pub(crate) edits: Vec<Edit>,
+    /// Offset and message of unsupported (non-erasable) syntax.
+    pub(crate) errors: Vec<(u32, String)>,
+}
+
+impl<'s> TypeStripper<'s> {
+    pub(crate) fn new(source: &'s str) -> Self {
+        Self { source, edits: Vec::default(), errors: Vec::default() }
+    }
+    fn blank(&mut self, span: Span) {
+        self.edits.push(Edit::blank(span.start, span.end));
+    }
+    /// Blanks a list item together with the comma that follows it.
+    fn blank_list_item(&mut self, span: Span) {
+        let rest = &self.source[span.end as usize..];
+        let trimmed = rest.trim_start();
+        let end = if trimmed.starts_with(',') {
+            span.end + (rest.len() - trimmed.len()) as u32 + 1
+        } else {
+            span.end
+        };
+        self.edits.push(Edit::blank(span.start, end));
+    }
+    /// Blanks the first `marker` (`?` or `!`) in `start..end`.
+    fn blank_marker(&mut self, start: u32, end: u32, marker: char) {
+        if end <= start {
+            return
+        }
+        if let Some(index) = self.source[start as usize..end as usize].find(marker) {
+            let index = start + index as u32;
+            self.edits.push(Edit::blank(index, index + 1));
+        }
+    }
+    /// Blanks TypeScript modifier keywords in `start..end` (the text between
+    /// a class member's decorators and its key, or a class's decorators and
+    /// its body), stopping at the `class` keyword.
+    fn blank_modifiers(&mut self, start: u32, end: u32) {
+        if end <= start {
+            return
+        }
+        let region = &self.source[start as usize..end as usize];
+        let bytes = region.as_bytes();
+        let is_word_byte = |x: u8| x.is_ascii_alphanumeric() || x == b'_' || x == b'$' || x >= 0x80;
+        let mut index = 0;
+        while index < bytes.len() {
+            if !is_word_byte(bytes[index]) {
+                index += 1;
+                continue;
+            }
+            let word_start = index;
+            while index < bytes.len() && is_word_byte(bytes[index]) {
+                index += 1;
+            }
+            let word = &region[word_start..index];
+            if word == "class" {
+                return
+            }
+            let is_qualified = word_start > 0 && matches!(bytes[word_start - 1], b'@' | b'.' | b'#');
+            if !is_qualified && TYPESCRIPT_MODIFIERS.contains(&word) {
+                self.edits.push(Edit::blank(start + word_start as u32, start + index as u32));
+            }
+        }
+    }
+    fn error(&mut self, span: Span, message: impl Into<String>) {
+        self.errors.push((span.start, message.into()));
+    }
+    /// [`is_erased_declaration`], reporting the declarations that have
+    /// runtime semantics but can't be erased.
+    fn is_erased(&mut self, declaration: &Declaration) -> bool {
+        match declaration {
+            Declaration::TSEnumDeclaration(x) if !x.declare => {
+                self.error(x.span, "enums are not supported; use a plain object instead");
+            }
+            Declaration::TSModuleDeclaration(x) if !x.declare => {
+                self.error(x.span, "namespaces are not supported; use modules instead");
+            }
+            Declaration::TSImportEqualsDeclaration(x) if !x.import_kind.is_type() => {
+                self.error(x.span, "`import … = …` is not supported; use `import` instead");
+            }
+            _ => (),
+        }
+        is_erased_declaration(declaration)
+    }
+}
+
+/// Whether the type stripper removes `declaration` entirely.
+pub(crate) fn is_erased_declaration(declaration: &Declaration) -> bool {
+    match declaration {
+        Declaration::VariableDeclaration(x) => x.declare,
+        Declaration::FunctionDeclaration(x) => x.is_typescript_syntax(),
+        Declaration::ClassDeclaration(x) => x.declare,
+        _ => true,
+    }
+}
+
+impl<'a, 's> Visit<'a> for TypeStripper<'s> {
+    // —— STATEMENTS ——————————————————————————————————————————————————————————
+    fn visit_statement(&mut self, it: &Statement<'a>) {
+        match it {
+            Statement::ImportDeclaration(declaration) => {
+                if declaration.import_kind.is_type() {
+                    return self.blank(declaration.span)
+                }
+                for specifier in declaration.specifiers.iter().flatten() {
+                    if let ImportDeclarationSpecifier::ImportSpecifier(x) = specifier
+                        && x.import_kind.is_type()
+                    {
+                        self.blank_list_item(x.span);
+                    }
+                }
+            }
+            Statement::ExportNamedDeclaration(declaration) => {
+                if declaration.export_kind.is_type() {
+                    return self.blank(declaration.span)
+                }
+                if let Some(inner) = declaration.declaration.as_ref() {
+                    if self.is_erased(inner) {
+                        return self.blank(declaration.span)
+                    }
+                    walk::walk_declaration(self, inner);
+                }
+                for specifier in declaration.specifiers.iter() {
+                    if specifier.export_kind.is_type() {
+                        self.blank_list_item(specifier.span);
+                    }
+                }
+            }
+            Statement::ExportDefaultDeclaration(declaration) => match &declaration.declaration {
+                ExportDefaultDeclarationKind::TSInterfaceDeclaration(_) => self.blank(declaration.span),
+                ExportDefaultDeclarationKind::FunctionDeclaration(x) if x.is_typescript_syntax() => {
+                    self.blank(declaration.span)
+                }
+                _ => walk::walk_export_default_declaration(self, declaration),
+            },
+            Statement::ExportAllDeclaration(declaration) if declaration.export_kind.is_type() => {
+                self.blank(declaration.span)
+            }
+            Statement::TSExportAssignment(declaration) => {
+                self.error(declaration.span, "`export = …` is not supported; use `export default` instead");
+            }
+            Statement::TSNamespaceExportDeclaration(declaration) => self.blank(declaration.span),
+            _ => walk::walk_statement(self, it),
+        }
+    }
+    fn visit_declaration(&mut self, it: &Declaration<'a>) {
+        if self.is_erased(it) {
+            return self.blank(it.span())
+        }
+        walk::walk_declaration(self, it);
+    }
+    fn visit_variable_declarator(&mut self, it: &VariableDeclarator<'a>) {
+        if let (true, Some(annotation)) = (it.definite, it.type_annotation.as_ref()) {
+            self.blank_marker(it.id.span().end, annotation.span.start, '!');
+        }
+        walk::walk_variable_declarator(self, it);
+    }
+    // —— FUNCTIONS ———————————————————————————————————————————————————————————
+    fn visit_formal_parameter(&mut self, it: &FormalParameter<'a>) {
+        if it.accessibility.is_some() || it.readonly || it.r#override {
+            self.error(it.span, "parameter properties are not supported; assign the field in the constructor body");
+        }
+        if it.optional {
+            let end = it.type_annotation
+                .as_ref()
+                .map(|x| x.span.start)
+                .unwrap_or(it.span.end);
+            self.blank_marker(it.pattern.span().end, end, '?');
+        }
+        walk::walk_formal_parameter(self, it);
+    }
+    fn visit_ts_this_parameter(&mut self, it: &TSThisParameter<'a>) {
+        self.blank_list_item(it.span);
+    }
+    // —— CLASSES —————————————————————————————————————————————————————————————
+    fn visit_class(&mut self, it: &Class<'a>) {
+        let start = it.decorators.last().map(|x| x.span.end).unwrap_or(it.span.start);
+        self.blank_modifiers(start, it.body.span.start);
+        walk::walk_class(self, it);
+    }
+    fn visit_ts_class_implements_list(&mut self, it: &oxc_allocator::Vec<'a, TSClassImplements<'a>>) {
+        let (first, last) = match (it.first(), it.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return,
+        };
+        if let Some(keyword) = self.source[..first.span.start as usize].rfind("implements") {
+            self.edits.push(Edit::blank(keyword as u32, last.span.end));
+        }
+    }
+    fn visit_class_element(&mut self, it: &ClassElement<'a>) {
+        let is_declared = matches!(it, ClassElement::PropertyDefinition(x) if x.declare);
+        if it.is_typescript_syntax() || is_declared {
+            return self.blank(it.span())
+        }
+        walk::walk_class_element(self, it);
+    }
+    fn visit_property_definition(&mut self, it: &PropertyDefinition<'a>) {
+        let start = it.decorators.last().map(|x| x.span.end).unwrap_or(it.span.start);
+        self.blank_modifiers(start, it.key.span().start);
+        if it.optional || it.definite {
+            let end = it.type_annotation
+                .as_ref()
+                .map(|x| x.span.start)
+                .or_else(|| it.value.as_ref().map(|x| x.span().start))
+                .unwrap_or(it.span.end);
+            let marker = if it.optional { '?' } else { '!' };
+            self.blank_marker(it.key.span().end, end, marker);
+        }
+        walk::walk_property_definition(self, it);
+    }
+    fn visit_method_definition(&mut self, it: &MethodDefinition<'a>) {
+        let start = it.decorators.last().map(|x| x.span.end).unwrap_or(it.span.start);
+        self.blank_modifiers(start, it.key.span().start);
+        if it.optional {
+            let end = it.value.type_parameters
+                .as_ref()
+                .map(|x| x.span.start)
+                .unwrap_or(it.value.params.span.start);
+            self.blank_marker(it.key.span().end, end, '?');
+        }
+        walk::walk_method_definition(self, it);
+    }
+    fn visit_accessor_property(&mut self, it: &AccessorProperty<'a>) {
+        let start = it.decorators.last().map(|x| x.span.end).unwrap_or(it.span.start);
+        self.blank_modifiers(start, it.key.span().start);
+        if let (true, Some(annotation)) = (it.definite, it.type_annotation.as_ref()) {
+            self.blank_marker(it.key.span().end, annotation.span.start, '!');
+        }
+        walk::walk_accessor_property(self, it);
+    }
+    // —— TYPES ———————————————————————————————————————————————————————————————
+    fn visit_ts_type_annotation(&mut self, it: &TSTypeAnnotation<'a>) {
+        self.blank(it.span);
+    }
+    fn visit_ts_type_parameter_declaration(&mut self, it: &TSTypeParameterDeclaration<'a>) {
+        self.blank(it.span);
+    }
+    fn visit_ts_type_parameter_instantiation(&mut self, it: &TSTypeParameterInstantiation<'a>) {
+        self.blank(it.span);
+    }
+    // —— EXPRESSIONS —————————————————————————————————————————————————————————
+    fn visit_ts_as_expression(&mut self, it: &TSAsExpression<'a>) {
+        self.visit_expression(&it.expression);
+        self.blank(Span::new(it.expression.span().end, it.span.end));
+    }
+    fn visit_ts_satisfies_expression(&mut self, it: &TSSatisfiesExpression<'a>) {
+        self.visit_expression(&it.expression);
+        self.blank(Span::new(it.expression.span().end, it.span.end));
+    }
+    fn visit_ts_non_null_expression(&mut self, it: &TSNonNullExpression<'a>) {
+        self.visit_expression(&it.expression);
+        self.blank(Span::new(it.expression.span().end, it.span.end));
+    }
+    fn visit_ts_type_assertion(&mut self, it: &TSTypeAssertion<'a>) {
+        self.blank(Span::new(it.span.start, it.expression.span().start));
+        self.visit_expression(&it.expression);
+    }
+}
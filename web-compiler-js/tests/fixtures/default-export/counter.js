@@ -0,0 +1,6 @@
+export default class {
+    value = 0;
+    add() {
+        this.value += 1;
+    }
+}
@@ -0,0 +1,5 @@
+export const punctuation = "!";
+
+export default function greet(name) {
+    return `hello ${name}`;
+}
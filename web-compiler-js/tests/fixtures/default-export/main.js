@@ -0,0 +1,7 @@
+import greet, { punctuation } from "./greet.js";
+import Counter from "./counter.js";
+import answer from "./answer.js";
+
+const counter = new Counter();
+counter.add();
+console.log(greet("world") + punctuation, counter.value, answer);
@@ -0,0 +1,5 @@
+export function circle(r) {
+    return Math.round(Math.PI * r * r);
+}
+
+export default "circle";
@@ -0,0 +1,4 @@
+import * as shapes from "./shapes.js";
+import { circle } from "./shapes.js";
+
+console.log(Object.keys(shapes).sort().join(","), circle(1));
@@ -0,0 +1,3 @@
+export function square(side) {
+    return side * side;
+}
@@ -0,0 +1,3 @@
+export function assetUrl(name) {
+    return new URL(name, import.meta.url).href;
+}
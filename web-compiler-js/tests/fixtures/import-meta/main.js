@@ -0,0 +1,3 @@
+import { assetUrl } from "./assets.js";
+
+document.body.style.backgroundImage = `url(${assetUrl("bg.png")})`;
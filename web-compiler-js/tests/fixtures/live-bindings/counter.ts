@@ -0,0 +1,5 @@
+export let count: number = 0;
+
+export function increment(): void {
+    count += 1;
+}
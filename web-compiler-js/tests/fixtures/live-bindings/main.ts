@@ -0,0 +1,5 @@
+import { count, increment } from "./counter";
+
+increment();
+increment();
+console.log(count);
@@ -0,0 +1,30 @@
+
+import {             describe } from "/lib/describe.js";
+
+export          class Shape                         {
+                     name        ;
+              size         ;
+
+           static          count         = 0;
+    constructor(name        ) {
+        this.name = name;
+    }
+
+    label(prefix         )         {
+        return describe(prefix ?? "", this.name          );
+    }
+}
+
+export class Square extends Shape                  {
+             side        ;
+    constructor(side        ) {
+        super("square");
+        this.side = side;
+    }
+    area()         {
+        return this.side ** 2;
+    }
+}
+
+const square = new Square(3)                ;
+console.log(square.label?.("a "), square.area() , Shape.count);
@@ -0,0 +1,30 @@
+import type { Named } from "./types";
+import { type Sized, describe } from "/lib/describe.js";
+
+export abstract class Shape implements Named, Sized {
+    private readonly name: string;
+    protected size!: number;
+    declare kind?: string;
+    public static override count: number = 0;
+    constructor(name: string) {
+        this.name = name;
+    }
+    abstract area(): number;
+    label(prefix?: string): string {
+        return describe(prefix ?? "", this.name as string);
+    }
+}
+
+export class Square extends Shape implements Sized {
+    readonly side: number;
+    constructor(side: number) {
+        super("square");
+        this.side = side;
+    }
+    area(): number {
+        return this.side ** 2;
+    }
+}
+
+const square = new Square(3) satisfies Shape;
+console.log(square.label?.("a "), square.area()!, Shape.count);
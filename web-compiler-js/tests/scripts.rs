@@ -0,0 +1,127 @@
+//! Script compilation against the projects in `tests/fixtures`: TypeScript
+//! type stripping and the linking of bundled modules.
+use std::path::{Path, PathBuf};
+
+use web_compiler_js::{compile_script, CompiledScript, ScriptOptions};
+
+// ————————————————————————————————————————————————————————————————————————————
+// HELPERS
+// ————————————————————————————————————————————————————————————————————————————
+
+fn fixture(path: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(path)
+}
+
+fn compile(entry: &str) -> CompiledScript {
+    compile_script(&fixture(entry), ScriptOptions::default())
+        .unwrap_or_else(|error| panic!("{error}"))
+}
+
+fn module_names(compiled: &CompiledScript) -> Vec<String> {
+    compiled.modules
+        .iter()
+        .map(|x| x.file_name().unwrap().to_string_lossy().to_string())
+        .collect()
+}
+
+/// Lines with trailing whitespace (what blanked out types leave) removed.
+fn trimmed_lines(code: &str) -> Vec<&str> {
+    code.lines().map(str::trim_end).collect()
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// TYPE STRIPPING
+// ————————————————————————————————————————————————————————————————————————————
+
+#[test]
+fn strips_types_in_place() {
+    let compiled = compile("strip/shapes.ts");
+    let expected = std::fs::read_to_string(fixture("strip/shapes.expected.js")).unwrap();
+    assert_eq!(trimmed_lines(&compiled.code), trimmed_lines(&expected));
+    // `import type` is erased, so `./types` is never bundled.
+    assert_eq!(module_names(&compiled), ["shapes.ts"]);
+    assert!(compiled.is_module);
+}
+
+#[test]
+fn rejects_parameter_properties() {
+    let error = compile_script(&fixture("parameter-property/point.ts"), ScriptOptions::default())
+        .err()
+        .unwrap();
+    assert_eq!(error.location, Some((2, 17)));
+    assert!(error.message.contains("parameter properties"), "{}", error.message);
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// LINKING
+// ————————————————————————————————————————————————————————————————————————————
+
+#[test]
+fn keeps_imported_bindings_live() {
+    let compiled = compile("live-bindings/main.ts");
+    assert_eq!(module_names(&compiled), ["main.ts", "counter.ts"]);
+    // Read through a getter on every access, so `count` reflects `increment()`.
+    assert!(compiled.code.contains(r#"__web_compiler_export(__web_compiler_exports, { "count": () => count, "increment": () => increment });"#));
+    assert!(compiled.code.contains("__web_compiler_import_1.increment();\n__web_compiler_import_1.increment();"));
+    assert!(compiled.code.contains("console.log(__web_compiler_import_1.count);"));
+    assert!(compiled.code.contains("count += 1;"));
+    assert!(!compiled.is_module);
+}
+
+#[test]
+fn links_import_cycles() {
+    let compiled = compile("cycle/main.js");
+    assert_eq!(module_names(&compiled), ["main.js", "even.js", "odd.js"]);
+    // Each module of the cycle is defined once and reached through `require`,
+    // which returns the (partially initialized) exports object on re-entry.
+    assert_eq!(compiled.code.matches("__web_compiler_define(1,").count(), 1);
+    assert_eq!(compiled.code.matches("__web_compiler_define(2,").count(), 1);
+    assert!(compiled.code.contains("return n === 0 ? true : __web_compiler_import_2.isOdd(n - 1);"));
+    assert!(compiled.code.contains("return n === 0 ? false : __web_compiler_import_1.isEven(n - 1);"));
+    assert!(compiled.code.contains("console.log(__web_compiler_import_1.isEven(10), __web_compiler_import_1.isEven(7));"));
+}
+
+#[test]
+fn reexports_star_exports() {
+    let compiled = compile("export-star/main.js");
+    assert_eq!(module_names(&compiled), ["main.js", "shapes.js", "circle.js", "square.js"]);
+    assert!(compiled.code.contains("__web_compiler_export_star(__web_compiler_exports, __web_compiler_import_2);"));
+    assert!(compiled.code.contains("__web_compiler_export_star(__web_compiler_exports, __web_compiler_import_3);"));
+    // A namespace import and a named import of the same module share its
+    // exports object.
+    assert_eq!(compiled.code.matches("__web_compiler_require(1);").count(), 1);
+    assert!(compiled.code.contains("Object.keys(__web_compiler_import_1).sort()"));
+    assert!(compiled.code.contains("__web_compiler_import_1.circle(1)"));
+}
+
+#[test]
+fn links_default_exports() {
+    let compiled = compile("default-export/main.js");
+    assert_eq!(module_names(&compiled), ["main.js", "greet.js", "counter.js", "answer.js"]);
+    // A named declaration keeps its name.
+    assert!(compiled.code.contains(r#"{ "punctuation": () => punctuation, "default": () => greet }"#));
+    // Anonymous classes and expressions are bound to a local.
+    assert!(compiled.code.contains("const __web_compiler_default = class {"));
+    assert!(compiled.code.contains("const __web_compiler_default = 6 * 7;"));
+    assert!(compiled.code.contains("new __web_compiler_import_2.default()"));
+    assert!(compiled.code.contains(r#"__web_compiler_import_1.default("world") + __web_compiler_import_1.punctuation"#));
+    assert!(compiled.code.contains("__web_compiler_import_3.default);"));
+}
+
+#[test]
+fn rejects_import_meta_in_bundled_modules() {
+    let error = compile_script(&fixture("import-meta/main.js"), ScriptOptions::default())
+        .err()
+        .unwrap();
+    assert!(error.path.ends_with("import-meta/assets.js"), "{:?}", error.path);
+    assert_eq!(error.location, Some((2, 26)));
+    assert!(error.message.contains("`import.meta` is not supported"), "{}", error.message);
+}
+
+#[test]
+fn keeps_import_meta_in_a_standalone_module() {
+    let compiled = compile("import-meta/standalone.js");
+    assert_eq!(module_names(&compiled), ["standalone.js"]);
+    assert!(compiled.code.contains("import.meta.url"));
+    assert!(compiled.is_module);
+}
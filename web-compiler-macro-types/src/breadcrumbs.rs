@@ -1,88 +1,178 @@
 use std::collections::BTreeMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use serde::{Deserialize, Serialize};
-use xml_ast::TagBuf;
+use xml_ast::{Node, TagBuf};
 use crate::project::{FileInput, ProjectContext};
+use crate::source_cache::ParsedSource;
 
+/// Parents and titles declared by a site tree file, keyed by page source.
+/// A declared page without a parent is a root of the site.
+#[derive(Debug, Clone, Default)]
+pub struct DeclaredHierarchy {
+    pub pages: BTreeMap<PathBuf, DeclaredPage>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DeclaredPage {
+    pub parent: Option<PathBuf>,
+    /// Overrides the title read from the page.
+    pub title: Option<String>,
+}
+
+/// Where the title of a page in the site tree (breadcrumbs, `nav`) is read
+/// from when the site tree doesn't declare one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TitleSource {
+    /// `<define-title>`.
+    DefineTitle,
+    /// The text of the first `<h1>`.
+    H1,
+    /// The file name, or the directory name for index pages, with dashes
+    /// and underscores as spaces (`getting-started.html` → “Getting started”).
+    Filename,
+}
+
+impl TitleSource {
+    pub const DEFAULT_ORDER: &'static [TitleSource] = &[Self::DefineTitle, Self::H1, Self::Filename];
+}
+
+/// Every page of the site and where it sits: declared pages where the
+/// [`DeclaredHierarchy`] puts them, the others under the closest page whose
+/// output path is a parent directory of theirs (`docs/index.html` for
+/// `docs/cli/commands.html`). Children are in source order.
 #[derive(Debug, Clone, Default)]
 pub struct SiteTreeLayout {
-    breadcrumb_path_map: BTreeMap::<PathBuf, SystemBreadcrumbPath>,
+    pages: Vec<SiteTreePage>,
+    page_indices: BTreeMap<PathBuf, usize>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SiteTreePage {
+    pub source: FileInput,
+    pub title: String,
+    pub parent: Option<usize>,
+    pub children: Vec<usize>,
 }
 
 impl SiteTreeLayout {
-    pub fn lookup_for(&self, input: &FileInput) -> Option<&SystemBreadcrumbPath> {
-        self.breadcrumb_path_map.get(&input.source)
+    /// The breadcrumb trail of a page, from its root down to the page itself.
+    pub fn lookup_for(&self, input: &FileInput) -> Option<SystemBreadcrumbPath> {
+        let index = *self.page_indices.get(&input.source)?;
+        let components = self
+            .ancestors_and_self(index)
+            .into_iter()
+            .map(|index| {
+                let page = &self.pages[index];
+                SystemBreadcrumbComponent { source: page.source.clone(), title: page.title.clone() }
+            })
+            .collect::<Vec<_>>();
+        Some(SystemBreadcrumbPath {
+            file_input: input.to_owned(),
+            components,
+        })
     }
-    pub fn compute(input_rules: &[FileInput], project_context: &ProjectContext) -> Self {
-        let mut mapping = BTreeMap::<PathBuf, FileInput>::default();
-        let mut title_map = BTreeMap::<PathBuf, String>::new();
-        let mut breadcrumb_path_map = BTreeMap::<PathBuf, SystemBreadcrumbPath>::default();
-        // - -
-        for input in input_rules {
-            let mut public_path = input.resolved_public_path(project_context);
-            let public_file_name = public_path.file_name().unwrap().to_str().unwrap();
-            if public_file_name == "index.html" {
-                public_path = public_path.parent().unwrap().to_path_buf();
+    pub fn page_index(&self, input: &FileInput) -> Option<usize> {
+        self.page_indices.get(&input.source).copied()
+    }
+    pub fn page(&self, index: usize) -> &SiteTreePage {
+        &self.pages[index]
+    }
+    /// The pages without a parent, in source order.
+    pub fn roots(&self) -> Vec<usize> {
+        (0..self.pages.len())
+            .filter(|index| self.pages[*index].parent.is_none())
+            .collect()
+    }
+    /// Every page, depth first: each page followed by its children.
+    pub fn reading_order(&self) -> Vec<usize> {
+        fn visit(layout: &SiteTreeLayout, index: usize, order: &mut Vec<usize>) {
+            order.push(index);
+            for child in layout.pages[index].children.iter() {
+                visit(layout, *child, order);
             }
-            mapping.insert(public_path, input.clone());
         }
-        // - -
-        for input in input_rules {
-            if !title_map.contains_key(input.source_file()) {
-                let title = get_title(input);
-                let title = match title {
-                    Some(x) => x,
-                    None => continue
-                };
-                title_map.insert(input.source.clone(), title);
+        let mut order = Vec::with_capacity(self.pages.len());
+        for root in self.roots() {
+            visit(self, root, &mut order);
+        }
+        order
+    }
+    /// From the root down to `index`.
+    pub fn ancestors_and_self(&self, index: usize) -> Vec<usize> {
+        let mut trail = vec![index];
+        while let Some(parent) = self.pages[*trail.last().unwrap()].parent {
+            if trail.contains(&parent) {
+                break
             }
+            trail.push(parent);
+        }
+        trail.reverse();
+        trail
+    }
+    /// Titles are the declared ones, or else read from the first of
+    /// `title_sources` that has one (falling back to the file name).
+    pub fn compute(
+        input_rules: &[FileInput],
+        declared: &DeclaredHierarchy,
+        title_sources: &[TitleSource],
+        project_context: &ProjectContext,
+    ) -> Self {
+        let page_indices = input_rules
+            .iter()
+            .enumerate()
+            .map(|(index, input)| (input.source.clone(), index))
+            .collect::<BTreeMap<_, _>>();
+        // Pages by output directory (`docs` for `docs/index.html`).
+        let mut directory_pages = BTreeMap::<PathBuf, usize>::new();
+        for (index, input) in input_rules.iter().enumerate() {
+            directory_pages.insert(directory_key(&input.resolved_public_path(project_context)), index);
         }
         // - -
-        'outer: for input in input_rules {
-            let public_path = input.resolved_public_path(project_context);
-            let components = public_path
-                .components()
-                .map(|x| x.as_os_str().to_str().unwrap().to_string())
-                .collect::<Vec<_>>();
-            // - -
-            let mut breadcrumbs = Vec::<SystemBreadcrumbComponent>::new();
-            let mut leading = PathBuf::new();
-            for component in components.clone() {
-                let input = match mapping.get(&leading) {
-                    Some(x) => x,
-                    None => continue 'outer
-                };
-                let title = match title_map.get(input.source_file()) {
-                    Some(x) => x.to_string(),
-                    None => continue 'outer
+        let mut pages = input_rules
+            .iter()
+            .enumerate()
+            .map(|(index, input)| {
+                let declared = declared.pages.get(&input.source);
+                let parent = match declared {
+                    Some(declared) => declared.parent
+                        .as_ref()
+                        .and_then(|parent| page_indices.get(parent).copied()),
+                    None => {
+                        let key = directory_key(&input.resolved_public_path(project_context));
+                        key.ancestors()
+                            .skip(1)
+                            .find_map(|ancestor| directory_pages.get(ancestor).copied())
+                            .filter(|parent| *parent != index)
+                    }
                 };
-                breadcrumbs.push(SystemBreadcrumbComponent {
-                    source: input.to_owned(),
+                let title = declared
+                    .and_then(|declared| declared.title.clone())
+                    .unwrap_or_else(|| page_title(input, title_sources, project_context));
+                SiteTreePage {
+                    source: input.clone(),
                     title,
-                });
-                leading.push(component);
-            }
-            // - -
-            if let Some(finale) = mapping.get(&leading) {
-                let title = title_map.get(finale.source_file()).unwrap().to_string();
-                breadcrumbs.push(SystemBreadcrumbComponent {
-                    source: finale.to_owned(),
-                    title,
-                });
-            }
-            // - -
-            let breadcrumb_path = SystemBreadcrumbPath {
-                file_input: input.to_owned(),
-                components: breadcrumbs,
-            };
-            breadcrumb_path_map.insert(input.source.clone(), breadcrumb_path);
-        }
+                    parent,
+                    children: Vec::new(),
+                }
+            })
+            .collect::<Vec<_>>();
         // - -
-        Self {
-            breadcrumb_path_map: breadcrumb_path_map,
+        for index in 0..pages.len() {
+            if let Some(parent) = pages[index].parent {
+                pages[parent].children.push(index);
+            }
         }
+        Self { pages, page_indices }
+    }
+}
+
+/// Index pages stand for their directory.
+fn directory_key(public_path: &Path) -> PathBuf {
+    if public_path.file_name().is_some_and(|x| x == "index.html") {
+        return public_path.parent().unwrap_or(Path::new("")).to_path_buf()
     }
+    public_path.to_path_buf()
 }
 
 #[derive(Debug, Clone)]
@@ -98,20 +188,50 @@ pub struct SystemBreadcrumbComponent {
     pub title: String,
 }
 
-fn get_title(file_input: &FileInput) -> Option<String> {
-    let source = file_input.load_source_file().unwrap();
-    let source_tree = {
-        let output = xml_ast::parse_str_auto(&source);
-        if !output.errors.is_empty() {
-            let errors = output.errors.join("\n");
-            eprintln!("TODO: {errors}");
+/// The page title declared with `<define-title>`, if any.
+pub fn get_title(file_input: &FileInput, project_context: &ProjectContext) -> Option<String> {
+    let parsed = load_title_source(file_input, project_context)?;
+    define_title(&parsed.tree)
+}
+
+/// The title from the first of `sources` that has one, or else the file
+/// name.
+pub fn page_title(file_input: &FileInput, sources: &[TitleSource], project_context: &ProjectContext) -> String {
+    let mut parsed = None;
+    let mut load = || {
+        parsed
+            .get_or_insert_with(|| load_title_source(file_input, project_context))
+            .clone()
+    };
+    sources
+        .iter()
+        .find_map(|source| match source {
+            TitleSource::DefineTitle => load().and_then(|x| define_title(&x.tree)),
+            TitleSource::H1 => load().and_then(|x| first_heading(&x.tree)),
+            TitleSource::Filename => Some(filename_title(file_input.source_file())),
+        })
+        .unwrap_or_else(|| filename_title(file_input.source_file()))
+}
+
+fn load_title_source(file_input: &FileInput, project_context: &ProjectContext) -> Option<std::sync::Arc<ParsedSource>> {
+    let parsed = match file_input.load_source_tree(project_context) {
+        Ok(x) => x,
+        Err(error) => {
+            eprintln!("⚠️ {:?} failed to read page title: {error}", file_input.source_file());
             return None
         }
-        output.output
     };
+    // Parser errors are reported when the page itself is compiled.
+    if !parsed.errors.is_empty() {
+        return None
+    }
+    Some(parsed)
+}
+
+fn define_title(source_tree: &Node) -> Option<String> {
     let target_tag = TagBuf::from("define-title");
     let title = source_tree.find_first(&target_tag)?;
-    let title = title.as_element().unwrap();
+    let title = title.as_element()?;
     let body_text = title.text_contents().join("");
     let body_text = body_text.trim().to_string();
     let override_value = title.attributes
@@ -121,6 +241,27 @@ fn get_title(file_input: &FileInput) -> Option<String> {
     Some(override_value.unwrap_or(body_text))
 }
 
+fn first_heading(source_tree: &Node) -> Option<String> {
+    let heading = source_tree.find_first(&TagBuf::from("h1"))?;
+    let text = heading.as_element()?.text_contents().join("");
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    Some(text).filter(|x| !x.is_empty())
+}
+
+fn filename_title(source_file: &Path) -> String {
+    let name = match source_file.file_stem() {
+        Some(stem) if stem == "index" => source_file.parent().and_then(|x| x.file_name()).unwrap_or(stem),
+        Some(stem) => stem,
+        None => return String::default(),
+    };
+    let name = name.to_string_lossy().replace(['-', '_'], " ");
+    let mut chars = name.trim().chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::default(),
+    }
+}
+
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 #[serde(transparent)]
@@ -132,3 +273,34 @@ pub struct BreadcrumbComponentValue {
     pub href: String,
     pub title: String,
 }
+
+/// The `nav` binding: where the page being compiled sits in the site tree.
+///
+/// `parent`, `previous` and `next` hold at most one page, so they can be
+/// rendered with `<enumerate>` and render nothing when there is none.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NavigationValue {
+    pub current: NavigationPageValue,
+    pub parent: Vec<NavigationPageValue>,
+    /// From the root down to the parent.
+    pub ancestors: Vec<NavigationPageValue>,
+    pub children: Vec<NavigationPageValue>,
+    /// The parent's other children (or the other roots).
+    pub siblings: Vec<NavigationPageValue>,
+    /// The pages before and after this one in reading order (depth first).
+    pub previous: Vec<NavigationPageValue>,
+    pub next: Vec<NavigationPageValue>,
+    /// The whole site tree.
+    pub roots: Vec<NavigationPageValue>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NavigationPageValue {
+    /// Encoded virtual path.
+    pub href: String,
+    pub title: String,
+    pub is_current: bool,
+    pub is_ancestor: bool,
+    pub children: Vec<NavigationPageValue>,
+}
+
@@ -0,0 +1,79 @@
+//! Responsive image variants processed during a build.
+//!
+//! Each source image is decoded and encoded once per build however many
+//! pages use it. Entries are keyed by the source, the output directory and
+//! the settings that shape the variants, so another target (or another
+//! build in the same process) writes its own. Only the entry being encoded
+//! is locked; other images are processed concurrently.
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, OnceLock};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ResponsiveImageFormat {
+    Avif,
+    Webp,
+    /// The source image's own format.
+    Original,
+}
+
+impl ResponsiveImageFormat {
+    pub const ALL: [ResponsiveImageFormat; 3] = [Self::Avif, Self::Webp, Self::Original];
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ImageCacheKey {
+    pub source: PathBuf,
+    pub output_dir: PathBuf,
+    pub widths: Vec<u32>,
+    pub formats: Vec<ResponsiveImageFormat>,
+    pub quality: u8,
+}
+
+#[derive(Debug, Clone)]
+pub struct ProcessedImage {
+    pub width: u32,
+    pub height: u32,
+    pub variants: Vec<ImageVariant>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ImageVariant {
+    pub format: ResponsiveImageFormat,
+    pub width: u32,
+    /// Relative to the output directory; `None` for the source image itself.
+    pub public: Option<PathBuf>,
+}
+
+type ImageCacheEntry = Arc<OnceLock<Result<ProcessedImage, String>>>;
+
+#[derive(Clone, Default)]
+pub struct ImageCache {
+    entries: Arc<Mutex<HashMap<ImageCacheKey, ImageCacheEntry>>>,
+}
+
+impl std::fmt::Debug for ImageCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ImageCache")
+            .field("entries", &self.entries.lock().unwrap().len())
+            .finish()
+    }
+}
+
+impl ImageCache {
+    /// The processed image for `key`, running `process` only the first time
+    /// it is asked for.
+    pub fn get_or_process(
+        &self,
+        key: ImageCacheKey,
+        process: impl FnOnce() -> Result<ProcessedImage, String>,
+    ) -> Result<ProcessedImage, String> {
+        let entry = self.entries
+            .lock()
+            .unwrap()
+            .entry(key)
+            .or_default()
+            .clone();
+        entry.get_or_init(process).clone()
+    }
+}
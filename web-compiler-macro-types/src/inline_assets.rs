@@ -0,0 +1,53 @@
+//! Embedding small assets into the referencing document as `data:` URIs.
+use std::path::Path;
+
+use base64::Engine;
+
+/// Limits for automatic inlining (the manifest's `[inline]` section).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct InlineAssetOptions {
+    /// Raster images and CSS `url()` targets up to this many bytes are
+    /// embedded as data URIs; `None` disables automatic inlining.
+    pub max_size: Option<u64>,
+}
+
+impl InlineAssetOptions {
+    /// Whether `path` is small enough (and of a known type) to be inlined automatically.
+    pub fn should_inline(&self, path: &Path) -> bool {
+        let max_size = match self.max_size {
+            Some(x) => x,
+            None => return false,
+        };
+        if mime_type(path).is_none() {
+            return false
+        }
+        std::fs::metadata(path)
+            .map(|x| x.is_file() && x.len() <= max_size)
+            .unwrap_or(false)
+    }
+}
+
+pub fn mime_type(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    let mime_type = match extension.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "svg" => "image/svg+xml",
+        "ico" => "image/x-icon",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        _ => return None,
+    };
+    Some(mime_type)
+}
+
+/// Reads `path` into a base64 `data:` URI.
+pub fn data_uri(path: &Path) -> std::io::Result<String> {
+    let mime_type = mime_type(path).unwrap_or("application/octet-stream");
+    let bytes = std::fs::read(path)?;
+    let encoded = base64::engine::general_purpose::STANDARD.encode(bytes);
+    Ok(format!("data:{mime_type};base64,{encoded}"))
+}
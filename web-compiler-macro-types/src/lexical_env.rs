@@ -1,10 +1,11 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::path::{Path, PathBuf};
 
 use web_compiler_io_types::IO;
 
 use crate::breadcrumbs::BreadcrumbPathListValue;
 use crate::macro_tag::MacroTagSet;
-use crate::scope::{BinderValue, BindingScope};
+use crate::scope::{BinderValue, BindingScope, LazyBinderValue};
 use crate::context::ContextRegistry;
 
 use crate::project::{DependencyRelation, FileInput, ProjectContext};
@@ -19,7 +20,10 @@ pub use web_compiler_io_types::Effectful;
 #[derive(Debug, Clone)]
 pub struct HostInfo {
     breadcrumbs: Option<BreadcrumbPathListValue>,
+    /// The `nav` binding, built the first time a scope of the page reads it.
+    navigation: Option<LazyBinderValue>,
     hoisted: Vec<BinderValue>,
+    hoisted_scripts: Vec<BinderValue>,
 }
 
 impl HostInfo {
@@ -27,52 +31,203 @@ impl HostInfo {
         breadcrumbs: Option<BreadcrumbPathListValue>,
         hoisted: Vec<BinderValue>,
     ) -> Self {
-        Self { breadcrumbs, hoisted }
+        Self { breadcrumbs, navigation: None, hoisted, hoisted_scripts: Vec::new() }
     }
     pub fn breadcrumbs(&self) -> Option<&BreadcrumbPathListValue> {
         self.breadcrumbs.as_ref()
     }
+    pub fn navigation(&self) -> Option<&BinderValue> {
+        self.navigation.as_ref().map(LazyBinderValue::get)
+    }
     pub fn hoisted(&self) -> &[BinderValue] {
         &self.hoisted
     }
+    pub fn hoisted_scripts(&self) -> &[BinderValue] {
+        &self.hoisted_scripts
+    }
 
     pub fn with_chained_state(mut self, chained_state: ChainedState) -> Self {
         self.hoisted = chained_state.hoisted;
+        self.hoisted_scripts = chained_state.hoisted_scripts;
         self
     }
     pub fn with_breadcrumbs(mut self, breadcrumbs: BreadcrumbPathListValue) -> Self {
         self.breadcrumbs = Some(breadcrumbs);
         self
     }
+    pub fn with_navigation(mut self, navigation: Option<LazyBinderValue>) -> Self {
+        self.navigation = navigation;
+        self
+    }
     pub fn with_breadcrumb_opt(mut self, breadcrumbs: Option<BreadcrumbPathListValue>) -> Self {
         self.breadcrumbs = breadcrumbs.or_else(|| self.breadcrumbs);
         self
     }
 }
 
+/// The stack of files currently being expanded through `<include>`.
+#[derive(Debug, Clone)]
+pub struct IncludeChain {
+    files: Vec<PathBuf>,
+    max_depth: usize,
+}
+
+impl Default for IncludeChain {
+    fn default() -> Self {
+        Self { files: Vec::new(), max_depth: Self::DEFAULT_MAX_DEPTH }
+    }
+}
+
+impl IncludeChain {
+    pub const DEFAULT_MAX_DEPTH: usize = 64;
+    /// Starts a chain at the file being compiled (a page or template).
+    pub fn root(file: impl AsRef<Path>) -> Self {
+        Self { files: vec![path_clean::clean(file.as_ref())], ..Default::default() }
+    }
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+    /// Number of nested includes below the root file.
+    pub fn depth(&self) -> usize {
+        self.files.len().saturating_sub(1)
+    }
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+    pub fn contains(&self, file: impl AsRef<Path>) -> bool {
+        let file = path_clean::clean(file.as_ref());
+        self.files.iter().any(|x| x == &file)
+    }
+    /// Returns the chain extended by `file`, or an error describing why
+    /// entering `file` is not allowed.
+    ///
+    /// Re-entering a file that is already being expanded is only permitted
+    /// when `allow_recursion` is set, and is then bounded by the max depth.
+    pub fn enter(&self, file: impl AsRef<Path>, allow_recursion: bool) -> Result<Self, IncludeChainError> {
+        let file = path_clean::clean(file.as_ref());
+        let mut chain = self.files.clone();
+        let is_cycle = self.contains(&file);
+        chain.push(file);
+        if is_cycle && !allow_recursion {
+            return Err(IncludeChainError::Cycle { chain })
+        }
+        if chain.len().saturating_sub(1) > self.max_depth {
+            return Err(IncludeChainError::MaxDepthExceeded { chain, max_depth: self.max_depth })
+        }
+        Ok(Self { files: chain, max_depth: self.max_depth })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum IncludeChainError {
+    Cycle { chain: Vec<PathBuf> },
+    MaxDepthExceeded { chain: Vec<PathBuf>, max_depth: usize },
+}
+
+impl IncludeChainError {
+    pub fn chain(&self) -> &[PathBuf] {
+        match self {
+            Self::Cycle { chain } => chain,
+            Self::MaxDepthExceeded { chain, .. } => chain,
+        }
+    }
+}
+
+impl std::fmt::Display for IncludeChainError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let chain = self.chain()
+            .iter()
+            .map(|x| x.display().to_string())
+            .collect::<Vec<_>>()
+            .join(" → ");
+        match self {
+            Self::Cycle { .. } => {
+                write!(f, "include cycle detected (mark the <include> as `recursive` if intended): {chain}")
+            }
+            Self::MaxDepthExceeded { max_depth, .. } => {
+                write!(f, "maximum include depth of {max_depth} exceeded: {chain}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for IncludeChainError {}
+
+/// A stylesheet Sass failed to compile, or lightningcss failed to parse, lower
+/// or print. It is emitted unchanged and the build carries on.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct StylesheetError {
+    pub file: PathBuf,
+    /// Whether the stylesheet is a `<style>` element of `file`, which the
+    /// line and column are then relative to.
+    pub style_element: bool,
+    /// 1-based, when lightningcss reports a location.
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+    pub message: String,
+}
+
+impl std::fmt::Display for StylesheetError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.file)?;
+        if self.style_element {
+            write!(f, " <style>")?;
+        }
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => write!(f, " line {line}, column {column}")?,
+            (Some(line), None) => write!(f, " line {line}")?,
+            _ => (),
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+impl std::error::Error for StylesheetError {}
+
 #[derive(Debug, Clone)]
 pub struct ProcessScope {
     pub binding_scope: BindingScope,
     pub context_registry: ContextRegistry,
+    pub include_chain: IncludeChain,
     host_info: HostInfo,
 }
 
 impl ProcessScope {
     pub fn new(host_info: HostInfo) -> Self {
         let breadcrumbs = host_info.breadcrumbs.clone();
-        Self {
+        let navigation = host_info.navigation.clone();
+        let mut scope = Self {
             binding_scope: Default::default(),
             context_registry: Default::default(),
+            include_chain: Default::default(),
             host_info,
         }
         .and_insert_binder_value(
             "breadcrumbs",
             BinderValue::json(breadcrumbs)
-        )
+        );
+        match navigation {
+            Some(navigation) => scope.binding_scope.insert_lazy("nav", navigation),
+            None => {
+                scope.binding_scope.insert("nav", BinderValue::json(()));
+            }
+        }
+        scope
     }
     // pub fn fresh(self) -> Self {
     //     Self::new(self.host_info)
     // }
+    /// A fresh scope that keeps the host level state (breadcrumbs, nav, hoisted
+    /// nodes, context profiles) but none of the caller's bindings.
+    pub fn hygienic(&self) -> Self {
+        Self::new(self.host_info.clone())
+            .with_context_registry(self.context_registry.clone())
+            .with_include_chain(self.include_chain.clone())
+    }
     pub fn with_binding_scope(mut self, binding_scope: BindingScope) -> Self {
         self.binding_scope = binding_scope;
         self
@@ -85,6 +240,10 @@ impl ProcessScope {
         self.context_registry = context_registry;
         self
     }
+    pub fn with_include_chain(mut self, include_chain: IncludeChain) -> Self {
+        self.include_chain = include_chain;
+        self
+    }
     pub fn host_info(&self) -> &HostInfo {
         &self.host_info
     }
@@ -97,6 +256,7 @@ impl ProcessScope {
 #[derive(Debug, Clone, Default)]
 pub struct ChainedState {
     hoisted: Vec<BinderValue>,
+    hoisted_scripts: Vec<BinderValue>,
 }
 
 impl ChainedState {
@@ -109,6 +269,9 @@ impl ChainedState {
     pub fn hoisted(&self) -> &[BinderValue] {
         &self.hoisted
     }
+    pub fn hoisted_scripts(&self) -> &[BinderValue] {
+        &self.hoisted_scripts
+    }
 }
 
 // ————————————————————————————————————————————————————————————————————————————
@@ -120,12 +283,24 @@ pub struct AccumulatedEffects {
     pub dependencies: HashSet<DependencyRelation>,
     pub deferred_dependencies: HashSet<DependencyRelation>,
     pub hoisted: Vec<BinderValue>,
+    /// `<script>` elements collected from included fragments, placed at the
+    /// end of `<body>` rather than with the other hoisted markup.
+    pub hoisted_scripts: Vec<BinderValue>,
+    /// The include chain through which each included file was first reached,
+    /// used to report where a link or asset reference came from.
+    pub include_chains: BTreeMap<PathBuf, Vec<PathBuf>>,
+    /// `url()` references found in stylesheets (inline or linked), including
+    /// external ones.
+    pub stylesheet_urls: HashSet<DependencyRelation>,
+    /// Stylesheets that were emitted unchanged because they failed to compile.
+    pub stylesheet_errors: BTreeSet<StylesheetError>,
 }
 
 impl AccumulatedEffects {
     pub fn chained_state(&self) -> ChainedState {
         ChainedState {
             hoisted: self.hoisted.clone(),
+            hoisted_scripts: self.hoisted_scripts.clone(),
         }
     }
 }
@@ -135,6 +310,12 @@ impl Effectful for AccumulatedEffects {
         self.dependencies.extend(other.dependencies);
         self.deferred_dependencies.extend(other.deferred_dependencies);
         self.hoisted.extend(other.hoisted);
+        self.hoisted_scripts.extend(other.hoisted_scripts);
+        for (file, chain) in other.include_chains {
+            self.include_chains.entry(file).or_insert(chain);
+        }
+        self.stylesheet_urls.extend(other.stylesheet_urls);
+        self.stylesheet_errors.extend(other.stylesheet_errors);
     }
 }
 
@@ -215,13 +396,14 @@ impl<'a> SourcePathResolver<'a> {
                 path_clean::clean(&input.source) == target
             })
     }
+    /// Finds the recorded dependency for `relation`, ignoring any `?query` or
+    /// `#fragment` suffix on either side.
     pub fn lookup_dependency(&self, relation: &DependencyRelation) -> Option<&'a DependencyRelation> {
-        let dependency = relation.as_file_dependency();
-        let target = path_clean::clean(dependency.resolved_target_path());
+        let target = path_clean::clean(relation.without_link_suffix().as_file_dependency().resolved_target_path());
         self.dependencies
             .iter()
             .find(|dep| {
-                path_clean::clean(dep.as_file_dependency().resolved_target_path()) == target
+                path_clean::clean(dep.without_link_suffix().as_file_dependency().resolved_target_path()) == target
             })
     }
 }
@@ -9,4 +9,9 @@ pub mod path_utils;
 pub mod macro_tag;
 pub mod tag_rewrite_rule;
 pub mod helpers;
-pub mod breadcrumbs;
\ No newline at end of file
+pub mod breadcrumbs;
+pub mod source_cache;
+pub mod profile;
+pub mod page_meta;
+pub mod inline_assets;
+pub mod image_cache;
\ No newline at end of file
@@ -3,6 +3,7 @@ use std::{collections::{BTreeMap, BTreeSet}, rc::Rc};
 use xml_ast::{transform::ProcessMode, AttributeMap, Element, Fragment, Node};
 
 use crate::lexical_env::{Featureset, ProcessScope, MacroIO, SourceHost};
+use crate::profile::ProfileCategory;
 
 /// Applied during the top-down traversal phase.
 pub trait MacroTag {
@@ -48,6 +49,7 @@ impl<Runtime: SourceHost> MacroTagSet<Runtime> {
         let element_tag_str = tag.as_normalized();
         if self.supported_tags.contains(element_tag_str) {
             if let Some(macro_tag) = self.macros.get(element_tag_str) {
+                let _span = runtime.project().profiler.span(ProfileCategory::MacroTag, macro_tag.tag_name());
                 return macro_tag
                     .apply(attributes, children, scope, runtime)
                     .map(ProcessMode::Manual)
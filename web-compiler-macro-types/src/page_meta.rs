@@ -0,0 +1,94 @@
+//! Per-page metadata declared with `<page-meta>`.
+//!
+//! ```html
+//! <page-meta
+//!     date="2025-03-14"
+//!     description="Spring maintenance checklist"
+//!     tags="blog, maintenance"
+//!     sitemap-priority="0.8"
+//!     sitemap-changefreq="monthly"
+//!     draft>
+//! </page-meta>
+//! ```
+//!
+//! Like `<define-title>` it is read statically from the page source (so it is
+//! available before the page is compiled) and renders to nothing.
+use std::collections::BTreeMap;
+
+use xml_ast::TagBuf;
+
+use crate::project::{FileInput, ProjectContext};
+
+#[derive(Debug, Clone, Default)]
+pub struct PageMetadata {
+    pub attributes: BTreeMap<String, String>,
+}
+
+impl PageMetadata {
+    pub const TAG_NAME: &'static str = "page-meta";
+    pub fn load(file_input: &FileInput, project_context: &ProjectContext) -> Self {
+        let parsed = match file_input.load_source_tree(project_context) {
+            Ok(x) => x,
+            Err(_) => return Self::default(),
+        };
+        let element = parsed.tree
+            .find_first(&TagBuf::from(Self::TAG_NAME))
+            .and_then(|x| x.as_element().cloned());
+        let attributes = element
+            .map(|element| {
+                element.attributes
+                    .iter()
+                    .map(|(key, value)| (key.as_str().to_ascii_lowercase(), value.as_str().trim().to_string()))
+                    .collect::<BTreeMap<_, _>>()
+            })
+            .unwrap_or_default();
+        Self { attributes }
+    }
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.attributes
+            .get(key)
+            .map(|x| x.as_str())
+            .filter(|x| !x.is_empty())
+    }
+    pub fn is_set(&self, key: &str) -> bool {
+        match self.attributes.get(key).map(|x| x.to_ascii_lowercase()) {
+            Some(value) => value != "false" && value != "no",
+            None => false,
+        }
+    }
+    pub fn date(&self) -> Option<&str> {
+        self.get("date")
+    }
+    pub fn description(&self) -> Option<&str> {
+        self.get("description")
+    }
+    /// Comma separated `tags`.
+    pub fn tags(&self) -> Vec<&str> {
+        self.get("tags")
+            .map(|x| {
+                x.split(',')
+                    .map(|x| x.trim())
+                    .filter(|x| !x.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+    /// `draft`: the page is only built by targets with drafts enabled.
+    pub fn is_draft(&self) -> bool {
+        self.is_set("draft")
+    }
+    /// `sitemap="exclude"`, `sitemap="false"` or `noindex`.
+    pub fn is_sitemap_excluded(&self) -> bool {
+        let sitemap = self.get("sitemap").map(|x| x.to_ascii_lowercase());
+        matches!(sitemap.as_deref(), Some("exclude") | Some("false") | Some("no")) || self.is_set("noindex")
+    }
+    /// `sitemap-priority`, when it is a number within `0.0..=1.0`.
+    pub fn sitemap_priority(&self) -> Option<f32> {
+        self.get("sitemap-priority")
+            .and_then(|x| x.parse::<f32>().ok())
+            .filter(|x| (0.0..=1.0).contains(x))
+    }
+    pub fn sitemap_changefreq(&self) -> Option<&str> {
+        self.get("sitemap-changefreq")
+    }
+}
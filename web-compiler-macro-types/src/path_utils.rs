@@ -1,3 +1,5 @@
+use std::path::{Path, PathBuf};
+
 /// Returns true if a link is an external URL.
 ///
 /// # Example
@@ -17,7 +19,23 @@ pub fn is_external_url(href: &str) -> bool {
         || lowered.starts_with("//")
         || lowered.starts_with("mailto:")
         || lowered.starts_with("tel:")
+        || lowered.starts_with("data:")
         || lowered.starts_with("#")
         || lowered.starts_with("noop://")
 }
 
+
+/// The path an asset is emitted under once compiled, e.g. `main.ts` →
+/// `main.js` or `main.scss` → `main.css`; other paths are returned unchanged.
+pub fn compiled_asset_path(path: &Path) -> PathBuf {
+    let extension = path
+        .extension()
+        .and_then(|x| x.to_str())
+        .map(|x| x.to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("ts") => path.with_extension("js"),
+        Some("mts") => path.with_extension("mjs"),
+        Some("scss") => path.with_extension("css"),
+        _ => path.to_path_buf(),
+    }
+}
@@ -0,0 +1,233 @@
+//! Build profiling.
+//!
+//! Records wall-clock spans for compiler phases and for individual macro tags
+//! and rewrite rules, attributed to the page being compiled. Spans nest, so
+//! macro timings are inclusive (an `<include>` span contains the spans of the
+//! macros inside the included file).
+//!
+//! A disabled profiler (the default) records nothing.
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Default)]
+pub struct Profiler {
+    state: Option<Arc<Mutex<ProfilerState>>>,
+}
+
+impl std::fmt::Debug for Profiler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Profiler")
+            .field("enabled", &self.is_enabled())
+            .finish()
+    }
+}
+
+struct ProfilerState {
+    epoch: Instant,
+    current_page: Option<PathBuf>,
+    events: Vec<ProfileEvent>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ProfileCategory {
+    Phase,
+    MacroTag,
+    RewriteRule,
+}
+
+impl ProfileCategory {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Phase => "phase",
+            Self::MacroTag => "macro",
+            Self::RewriteRule => "rule",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ProfileEvent {
+    pub name: String,
+    pub category: ProfileCategory,
+    pub page: Option<PathBuf>,
+    pub start: Duration,
+    pub duration: Duration,
+}
+
+/// Records its span when dropped.
+#[must_use]
+pub struct ProfileSpan {
+    inner: Option<(Arc<Mutex<ProfilerState>>, ProfileEvent, Instant)>,
+}
+
+impl Drop for ProfileSpan {
+    fn drop(&mut self) {
+        if let Some((state, mut event, started)) = self.inner.take() {
+            event.duration = started.elapsed();
+            state.lock().unwrap().events.push(event);
+        }
+    }
+}
+
+/// Restores the previously active page when dropped.
+#[must_use]
+pub struct PageScope {
+    inner: Option<(Arc<Mutex<ProfilerState>>, Option<PathBuf>)>,
+}
+
+impl Drop for PageScope {
+    fn drop(&mut self) {
+        if let Some((state, previous)) = self.inner.take() {
+            state.lock().unwrap().current_page = previous;
+        }
+    }
+}
+
+impl Profiler {
+    pub fn enabled() -> Self {
+        let state = ProfilerState {
+            epoch: Instant::now(),
+            current_page: None,
+            events: Vec::new(),
+        };
+        Self { state: Some(Arc::new(Mutex::new(state))) }
+    }
+    pub fn is_enabled(&self) -> bool {
+        self.state.is_some()
+    }
+    /// Attributes all spans recorded while the guard is alive to `page`.
+    pub fn page_scope(&self, page: impl AsRef<Path>) -> PageScope {
+        let inner = self.state.as_ref().map(|state| {
+            let mut guard = state.lock().unwrap();
+            let previous = guard.current_page.replace(page.as_ref().to_path_buf());
+            (state.clone(), previous)
+        });
+        PageScope { inner }
+    }
+    pub fn span(&self, category: ProfileCategory, name: impl Into<String>) -> ProfileSpan {
+        let inner = self.state.as_ref().map(|state| {
+            let started = Instant::now();
+            let guard = state.lock().unwrap();
+            let event = ProfileEvent {
+                name: name.into(),
+                category,
+                page: guard.current_page.clone(),
+                start: started.duration_since(guard.epoch),
+                duration: Duration::ZERO,
+            };
+            (state.clone(), event, started)
+        });
+        ProfileSpan { inner }
+    }
+    pub fn phase(&self, name: &'static str) -> ProfileSpan {
+        self.span(ProfileCategory::Phase, name)
+    }
+    pub fn events(&self) -> Vec<ProfileEvent> {
+        self.state
+            .as_ref()
+            .map(|state| state.lock().unwrap().events.clone())
+            .unwrap_or_default()
+    }
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// REPORTS
+// ————————————————————————————————————————————————————————————————————————————
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Aggregate {
+    count: usize,
+    total: Duration,
+    max: Duration,
+}
+
+impl Aggregate {
+    fn push(&mut self, duration: Duration) {
+        self.count += 1;
+        self.total += duration;
+        self.max = self.max.max(duration);
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+impl Profiler {
+    /// Human readable summary: phases, macro tags and rewrite rules, and the slowest pages.
+    pub fn summary_table(&self, slowest_pages: usize) -> String {
+        let events = self.events();
+        let mut by_name = BTreeMap::<(ProfileCategory, String), Aggregate>::new();
+        let mut by_page = BTreeMap::<PathBuf, Aggregate>::new();
+        for event in events.iter() {
+            by_name
+                .entry((event.category, event.name.clone()))
+                .or_default()
+                .push(event.duration);
+            if event.category == ProfileCategory::Phase {
+                if let Some(page) = event.page.as_ref() {
+                    by_page.entry(page.clone()).or_default().push(event.duration);
+                }
+            }
+        }
+        let mut lines = Vec::<String>::new();
+        lines.push(format!(
+            "{:<10} {:<28} {:>8} {:>12} {:>12} {:>12}",
+            "KIND", "NAME", "COUNT", "TOTAL (ms)", "MEAN (ms)", "MAX (ms)",
+        ));
+        let mut rows = by_name.into_iter().collect::<Vec<_>>();
+        rows.sort_by(|(left_key, left), (right_key, right)| {
+            left_key.0.cmp(&right_key.0).then(right.total.cmp(&left.total))
+        });
+        for ((category, name), aggregate) in rows {
+            lines.push(format!(
+                "{:<10} {:<28} {:>8} {:>12.2} {:>12.3} {:>12.2}",
+                category.as_str(),
+                name,
+                aggregate.count,
+                millis(aggregate.total),
+                millis(aggregate.total) / aggregate.count.max(1) as f64,
+                millis(aggregate.max),
+            ));
+        }
+        let mut pages = by_page.into_iter().collect::<Vec<_>>();
+        pages.sort_by(|(_, left), (_, right)| right.total.cmp(&left.total));
+        if !pages.is_empty() {
+            lines.push(String::default());
+            lines.push(format!("{:<60} {:>12}", "SLOWEST PAGES", "TOTAL (ms)"));
+            for (page, aggregate) in pages.into_iter().take(slowest_pages) {
+                lines.push(format!("{:<60} {:>12.2}", page.display().to_string(), millis(aggregate.total)));
+            }
+        }
+        lines.join("\n")
+    }
+    /// Chrome trace-event JSON (load in `chrome://tracing` or Perfetto).
+    pub fn chrome_trace(&self) -> serde_json::Value {
+        let events = self
+            .events()
+            .into_iter()
+            .map(|event| {
+                let mut args = serde_json::Map::new();
+                if let Some(page) = event.page.as_ref() {
+                    args.insert("page".to_string(), serde_json::Value::from(page.display().to_string()));
+                }
+                serde_json::json!({
+                    "name": event.name,
+                    "cat": event.category.as_str(),
+                    "ph": "X",
+                    "ts": event.start.as_micros() as u64,
+                    "dur": event.duration.as_micros() as u64,
+                    "pid": 1,
+                    "tid": 1,
+                    "args": args,
+                })
+            })
+            .collect::<Vec<_>>();
+        serde_json::json!({
+            "traceEvents": events,
+            "displayTimeUnit": "ms",
+        })
+    }
+}
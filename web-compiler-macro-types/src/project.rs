@@ -1,7 +1,12 @@
-use std::{collections::HashSet, path::{Path, PathBuf}};
+use std::{collections::{HashMap, HashSet}, path::{Path, PathBuf}};
 
 use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
 
+use crate::image_cache::ImageCache;
+use crate::inline_assets::InlineAssetOptions;
+use crate::profile::Profiler;
+use crate::source_cache::{ParsedSource, SourceTreeCache};
+
 // ————————————————————————————————————————————————————————————————————————————
 // PROJECT CONTEXT
 // ————————————————————————————————————————————————————————————————————————————
@@ -13,6 +18,14 @@ pub struct ProjectContext {
     pub project_root: PathBuf,
     /// Final directory where output files will be emitted.
     pub output_dir: PathBuf,
+    /// Parsed source trees shared by every pipeline in the build.
+    pub source_cache: SourceTreeCache,
+    /// Responsive image variants processed so far in the build.
+    pub image_cache: ImageCache,
+    /// Records phase and macro timings when `--profile` is given.
+    pub profiler: Profiler,
+    /// Size limit for embedding assets as data URIs.
+    pub inline_assets: InlineAssetOptions,
 }
 
 // ————————————————————————————————————————————————————————————————————————————
@@ -74,6 +87,10 @@ impl FileInput {
         let content = std::fs::read_to_string(self.source_file())?;
         Ok(content)
     }
+    /// Parsed source tree, memoized through the project's [`SourceTreeCache`].
+    pub fn load_source_tree(&self, project_context: &ProjectContext) -> Result<std::sync::Arc<ParsedSource>, std::io::Error> {
+        project_context.source_cache.load(self.source_file())
+    }
 }
 
 // ————————————————————————————————————————————————————————————————————————————
@@ -137,6 +154,11 @@ impl DependencyRelation {
     pub fn is_external_target(&self) -> bool {
         crate::path_utils::is_external_url(&self.to)
     }
+    /// The same relation with any `?query` or `#fragment` removed from the target.
+    pub fn without_link_suffix(&self) -> Self {
+        let (to, _) = split_link_suffix(&self.to);
+        Self { from: self.from.clone(), to: to.to_string() }
+    }
 }
 
 const NOT_VIRTUAL_PATH_SAFE: AsciiSet = NON_ALPHANUMERIC
@@ -154,12 +176,28 @@ const NOT_VIRTUAL_PATH_SAFE: AsciiSet = NON_ALPHANUMERIC
 pub struct ResolvedDependencies {
     pub dependency_relations: HashSet<ResolvedDependencyRelation>,
     pub emitted_files: HashSet<PathBuf>,
+    /// Every internal link, `srcset` candidate, stylesheet `url()` and
+    /// fragment anchor seen while post-processing.
+    pub link_references: Vec<LinkReference>,
+    /// Element ids (and `<a name>` anchors) of each emitted page, keyed by
+    /// public path.
+    pub element_ids: HashMap<PathBuf, HashSet<String>>,
+    /// Pages loading each script (by public path) through a classic
+    /// `<script src>`, i.e. without `type="module"`.
+    pub classic_scripts: HashMap<PathBuf, HashSet<PathBuf>>,
 }
 
 impl ResolvedDependencies {
     pub fn extend(&mut self, other: Self) {
         self.dependency_relations.extend(other.dependency_relations);
         self.emitted_files.extend(other.emitted_files);
+        self.link_references.extend(other.link_references);
+        for (page, ids) in other.element_ids {
+            self.element_ids.entry(page).or_default().extend(ids);
+        }
+        for (script, pages) in other.classic_scripts {
+            self.classic_scripts.entry(script).or_default().extend(pages);
+        }
     }
     pub fn include_dependency_relation(&mut self, dependency: ResolvedDependencyRelation) {
         self.dependency_relations.insert(dependency);
@@ -187,6 +225,56 @@ impl ResolvedDependencyRelation {
     }
 }
 
+// ————————————————————————————————————————————————————————————————————————————
+// LINK REFERENCES
+// ————————————————————————————————————————————————————————————————————————————
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LinkKind {
+    /// `href`, `src` and similar attributes.
+    Attribute,
+    SrcsetCandidate,
+    StylesheetUrl,
+}
+
+/// A single reference from a compiled page (or stylesheet) to another file.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LinkReference {
+    pub kind: LinkKind,
+    /// The page (or stylesheet) being compiled.
+    pub page: PathBuf,
+    /// The file the reference was written in; differs from `page` for includes.
+    pub origin: PathBuf,
+    /// Include chain from `page` (or its template) to `origin`, when known.
+    pub include_chain: Vec<PathBuf>,
+    /// The reference as written.
+    pub href: String,
+    /// Public path of the target relative to the output directory, `None` for
+    /// fragment only links (`#section`) which target `page` itself.
+    pub target: Option<PathBuf>,
+    pub fragment: Option<String>,
+}
+
+impl LinkReference {
+    /// Links to other sites (`https://…`, `mailto:…`); fragment-only links are internal.
+    pub fn is_external(&self) -> bool {
+        crate::path_utils::is_external_url(&self.href) && !self.href.trim_start().starts_with('#')
+    }
+}
+
+/// Splits `page.html?q=1#section` into `("page.html", "?q=1#section")`.
+pub fn split_link_suffix(href: &str) -> (&str, &str) {
+    match href.find(|c| c == '?' || c == '#') {
+        Some(index) => href.split_at(index),
+        None => (href, ""),
+    }
+}
+
+/// The fragment of a link suffix, without the leading `#`.
+pub fn link_fragment(suffix: &str) -> Option<&str> {
+    suffix.split_once('#').map(|(_, fragment)| fragment)
+}
+
 // ————————————————————————————————————————————————————————————————————————————
 // RESOLVED ASSET DEPENDENCIES
 // ————————————————————————————————————————————————————————————————————————————
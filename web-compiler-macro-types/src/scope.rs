@@ -1,4 +1,5 @@
 use std::collections::BTreeMap;
+use std::sync::{Arc, LazyLock};
 use serde::Serialize;
 use xml_ast::{Element, Fragment, Node};
 
@@ -35,6 +36,68 @@ pub struct RecordType<Kind> {
     pub fields: BTreeMap<String, Kind>,
 }
 
+impl BinderType {
+    /// Parses a type annotation such as `string`, `boolean` or `fragment`.
+    pub fn parse(input: &str) -> Option<Self> {
+        match input.trim().to_ascii_lowercase().as_str() {
+            "text" => Some(Self::Markup(MarkupBinderType::Text)),
+            "element" => Some(Self::Markup(MarkupBinderType::Element)),
+            "fragment" | "markup" => Some(Self::Markup(MarkupBinderType::Fragment)),
+            "null" => Some(Self::Json(JsonBinderType::Null)),
+            "bool" | "boolean" => Some(Self::Json(JsonBinderType::Bool)),
+            "number" => Some(Self::Json(JsonBinderType::Number)),
+            "string" => Some(Self::Json(JsonBinderType::String)),
+            "array" => Some(Self::Json(JsonBinderType::Array(None))),
+            "object" => Some(Self::Json(JsonBinderType::Object(None))),
+            _ => None,
+        }
+    }
+    /// Structural check; nested array/object element types are not inspected.
+    pub fn admits(&self, value: &BinderValue) -> bool {
+        match (self, value) {
+            (Self::Markup(MarkupBinderType::Text), BinderValue::Markup(MarkupBinderValue(node))) => {
+                node.as_text().is_some()
+            }
+            (Self::Markup(MarkupBinderType::Element), BinderValue::Markup(MarkupBinderValue(node))) => {
+                node.as_element().is_some()
+            }
+            (Self::Markup(MarkupBinderType::Fragment), BinderValue::Markup(_)) => true,
+            (Self::Json(JsonBinderType::String), BinderValue::Markup(MarkupBinderValue(node))) => {
+                node.as_text().is_some()
+            }
+            (Self::Json(kind), BinderValue::Json(value)) => {
+                match (kind, value) {
+                    (JsonBinderType::Null, JsonBinderValue::Null) => true,
+                    (JsonBinderType::Bool, JsonBinderValue::Bool(_)) => true,
+                    (JsonBinderType::Number, JsonBinderValue::Number(_)) => true,
+                    (JsonBinderType::String, JsonBinderValue::String(_)) => true,
+                    (JsonBinderType::Array(_), JsonBinderValue::Array(_)) => true,
+                    (JsonBinderType::Object(_), JsonBinderValue::Object(_)) => true,
+                    _ => false,
+                }
+            }
+            _ => false,
+        }
+    }
+}
+
+impl std::fmt::Display for BinderType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let label = match self {
+            Self::Markup(MarkupBinderType::Text) => "text",
+            Self::Markup(MarkupBinderType::Element) => "element",
+            Self::Markup(MarkupBinderType::Fragment) => "fragment",
+            Self::Json(JsonBinderType::Null) => "null",
+            Self::Json(JsonBinderType::Bool) => "boolean",
+            Self::Json(JsonBinderType::Number) => "number",
+            Self::Json(JsonBinderType::String) => "string",
+            Self::Json(JsonBinderType::Array(_)) => "array",
+            Self::Json(JsonBinderType::Object(_)) => "object",
+        };
+        write!(f, "{label}")
+    }
+}
+
 // ————————————————————————————————————————————————————————————————————————————
 // VALUE PRIMITIVES
 // ————————————————————————————————————————————————————————————————————————————
@@ -198,20 +261,52 @@ impl JsonBinderValue {
 #[derive(Debug, Clone, Default)]
 pub struct BindingScope {
     environment: BTreeMap<String, BinderValue>,
+    lazy_environment: BTreeMap<String, LazyBinderValue>,
 }
 
 impl BindingScope {
     pub fn extend(mut self, other: Self) -> Self {
+        for key in other.environment.keys() {
+            self.lazy_environment.remove(key);
+        }
+        for key in other.lazy_environment.keys() {
+            self.environment.remove(key);
+        }
         self.environment.extend(other.environment);
+        self.lazy_environment.extend(other.lazy_environment);
         self
     }
     pub fn insert(&mut self, key: impl Into<String>, value: impl Into<BinderValue>) -> Option<BinderValue> {
         let key = key.into();
         let value = value.into();
+        self.lazy_environment.remove(&key);
         self.environment.insert(key, value)
     }
+    /// Binds `key` to a value computed the first time it is looked up.
+    pub fn insert_lazy(&mut self, key: impl Into<String>, value: LazyBinderValue) {
+        let key = key.into();
+        self.environment.remove(&key);
+        self.lazy_environment.insert(key, value);
+    }
     pub fn lookup(&self, key: impl AsRef<str>) -> Option<&BinderValue> {
-        self.environment.get(key.as_ref())
+        let key = key.as_ref();
+        self.environment
+            .get(key)
+            .or_else(|| self.lazy_environment.get(key).map(LazyBinderValue::get))
+    }
+}
+
+/// A binding that is expensive to build and often unused (e.g. `nav`). It is
+/// computed on first use and shared by every scope it is copied into.
+#[derive(Debug, Clone)]
+pub struct LazyBinderValue(Arc<LazyLock<BinderValue, Box<dyn FnOnce() -> BinderValue + Send>>>);
+
+impl LazyBinderValue {
+    pub fn new(init: impl FnOnce() -> BinderValue + Send + 'static) -> Self {
+        Self(Arc::new(LazyLock::new(Box::new(init))))
+    }
+    pub fn get(&self) -> &BinderValue {
+        LazyLock::force(&self.0)
     }
 }
 
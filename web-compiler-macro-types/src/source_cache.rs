@@ -0,0 +1,154 @@
+//! Shared cache of parsed source files.
+//!
+//! Fragments such as headers and footers are included by every page, and the
+//! site tree layout reads every page once more to find its title. Entries are
+//! keyed by the cleaned file path and validated against the file's mtime and
+//! length; when those change the contents are re-read and compared by hash
+//! before falling back to a fresh parse.
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use xml_ast::Node;
+
+#[derive(Clone, Default)]
+pub struct SourceTreeCache {
+    state: Arc<Mutex<SourceTreeCacheState>>,
+}
+
+impl std::fmt::Debug for SourceTreeCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SourceTreeCache")
+            .field("stats", &self.stats())
+            .finish()
+    }
+}
+
+#[derive(Default)]
+struct SourceTreeCacheState {
+    entries: HashMap<PathBuf, CacheEntry>,
+    stats: SourceTreeCacheStats,
+}
+
+struct CacheEntry {
+    modified: Option<SystemTime>,
+    len: u64,
+    content_hash: u64,
+    parsed: Arc<ParsedSource>,
+}
+
+/// The parsed tree of a source file along with any parser diagnostics.
+#[derive(Debug)]
+pub struct ParsedSource {
+    pub tree: Node,
+    pub errors: Vec<String>,
+    errors_reported: AtomicBool,
+}
+
+impl ParsedSource {
+    /// The parser diagnostics, the first time they're asked for; a file
+    /// included by every page is reported once, not once per include.
+    pub fn unreported_errors(&self) -> &[String] {
+        if self.errors.is_empty() || self.errors_reported.swap(true, Ordering::Relaxed) {
+            return &[]
+        }
+        &self.errors
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SourceTreeCacheStats {
+    /// Served from cache without touching the file contents.
+    pub hits: usize,
+    /// File metadata changed but the contents hashed the same.
+    pub revalidated: usize,
+    /// Parsed from scratch.
+    pub misses: usize,
+}
+
+impl SourceTreeCacheStats {
+    pub fn lookups(&self) -> usize {
+        self.hits + self.revalidated + self.misses
+    }
+    pub fn hit_rate(&self) -> f64 {
+        let lookups = self.lookups();
+        if lookups == 0 {
+            return 0.0
+        }
+        (self.hits + self.revalidated) as f64 / lookups as f64
+    }
+}
+
+impl std::fmt::Display for SourceTreeCacheStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} lookups, {} hits, {} revalidated, {} parsed ({:.1}% hit rate)",
+            self.lookups(),
+            self.hits,
+            self.revalidated,
+            self.misses,
+            self.hit_rate() * 100.0,
+        )
+    }
+}
+
+impl SourceTreeCache {
+    /// Returns the parsed tree for `path`, parsing it only if it is not cached
+    /// or the file has changed.
+    pub fn load(&self, path: impl AsRef<Path>) -> Result<Arc<ParsedSource>, std::io::Error> {
+        let path = path_clean::clean(path.as_ref());
+        let metadata = std::fs::metadata(&path)?;
+        let modified = metadata.modified().ok();
+        let len = metadata.len();
+        {
+            let mut state = self.state.lock().unwrap();
+            let cached = state.entries
+                .get(&path)
+                .filter(|entry| entry.modified.is_some() && entry.modified == modified && entry.len == len)
+                .map(|entry| entry.parsed.clone());
+            if let Some(parsed) = cached {
+                state.stats.hits += 1;
+                return Ok(parsed)
+            }
+        }
+        let source = std::fs::read_to_string(&path)?;
+        let content_hash = hash_str(&source);
+        let mut state = self.state.lock().unwrap();
+        if let Some(entry) = state.entries.get_mut(&path) {
+            if entry.content_hash == content_hash {
+                entry.modified = modified;
+                entry.len = len;
+                let parsed = entry.parsed.clone();
+                state.stats.revalidated += 1;
+                return Ok(parsed)
+            }
+        }
+        let output = xml_ast::parse_str_auto(&source);
+        let parsed = Arc::new(ParsedSource {
+            tree: output.output,
+            errors: output.errors,
+            errors_reported: AtomicBool::new(false),
+        });
+        state.entries.insert(path, CacheEntry {
+            modified,
+            len,
+            content_hash,
+            parsed: parsed.clone(),
+        });
+        state.stats.misses += 1;
+        Ok(parsed)
+    }
+    pub fn stats(&self) -> SourceTreeCacheStats {
+        self.state.lock().unwrap().stats
+    }
+}
+
+fn hash_str(source: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    source.hash(&mut hasher);
+    hasher.finish()
+}
@@ -4,6 +4,7 @@ use std::rc::Rc;
 use xml_ast::{Element, Node};
 
 use crate::lexical_env::{Featureset, MacroIO, ProcessScope, SourceHostRef};
+use crate::profile::ProfileCategory;
 
 /// Applied during the bottom-up traversal phase.
 pub trait TagRewriteRule {
@@ -61,6 +62,10 @@ impl<Runtime: Featureset> TagRewriteRuleSet<Runtime> {
         let element_tag_str = element.tag.as_normalized();
         if self.supported_tags.contains(element_tag_str) {
             if let Some(macro_tag) = self.macros.get(element_tag_str) {
+                let _span = source_host_ref.project_context.profiler.span(
+                    ProfileCategory::RewriteRule,
+                    format!("{} (post)", macro_tag.tag_name()),
+                );
                 return macro_tag.post_process(element, source_host_ref)
             }
         }
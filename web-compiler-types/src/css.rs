@@ -0,0 +1,14 @@
+//! Production stylesheet optimizations (the manifest's `[css]` section).
+
+#[derive(Debug, Clone, Default)]
+pub struct CssOptions {
+    /// Inline the rules each page uses into a `<style>` in its `<head>` and
+    /// load the full stylesheets asynchronously.
+    pub critical: bool,
+    /// Remove the rules that match no emitted page from every compiled
+    /// stylesheet.
+    pub prune: bool,
+    /// Classes (glob patterns) that scripts add at runtime, whose rules are
+    /// never pruned.
+    pub safelist: Vec<String>,
+}
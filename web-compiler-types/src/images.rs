@@ -0,0 +1,29 @@
+//! Responsive image settings (the manifest's `[images]` section).
+
+pub use macro_types::image_cache::ResponsiveImageFormat;
+
+#[derive(Debug, Clone)]
+pub struct ImageOptions {
+    /// Globs over source image paths that are processed without a
+    /// `responsive` attribute on the `<img>`.
+    pub include: Vec<String>,
+    /// Candidate widths in pixels; widths at or above the source width are skipped.
+    pub widths: Vec<u32>,
+    pub formats: Vec<ResponsiveImageFormat>,
+    /// Encoder quality for lossy formats (`1..=100`).
+    pub quality: u8,
+    /// Default `sizes` attribute, used when the `<img>` has none.
+    pub sizes: String,
+}
+
+impl Default for ImageOptions {
+    fn default() -> Self {
+        Self {
+            include: Vec::default(),
+            widths: vec![480, 960, 1440, 1920],
+            formats: ResponsiveImageFormat::ALL.to_vec(),
+            quality: 75,
+            sizes: String::from("100vw"),
+        }
+    }
+}
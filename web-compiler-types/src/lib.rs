@@ -3,5 +3,11 @@ extern crate web_compiler_xml_ast as xml_ast;
 extern crate web_compiler_macro_types as macro_types;
 
 mod system;
+mod site;
+mod images;
+mod css;
 
 pub use system::*;
+pub use site::*;
+pub use images::*;
+pub use css::*;
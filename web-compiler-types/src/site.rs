@@ -0,0 +1,100 @@
+//! Site-wide configuration (the manifest's `[site]` section).
+
+use std::path::PathBuf;
+
+#[derive(Debug, Clone)]
+pub struct SiteConfig {
+    /// Canonical base URL, e.g. `https://example.com` (no trailing slash required).
+    pub base_url: String,
+    pub sitemap: SitemapConfig,
+    pub robots: RobotsConfig,
+}
+
+impl SiteConfig {
+    /// Absolute URL for a public path relative to the output directory;
+    /// `index.html` files map to their directory.
+    pub fn absolute_url(&self, public_path: &str) -> String {
+        let base_url = self.base_url.trim_end_matches('/');
+        let public_path = public_path
+            .replace('\\', "/")
+            .replace(' ', "%20");
+        let public_path = public_path.trim_start_matches("./").trim_start_matches('/');
+        if public_path == "index.html" {
+            return format!("{base_url}/")
+        }
+        match public_path.strip_suffix("/index.html") {
+            Some(directory) => format!("{base_url}/{directory}/"),
+            None => format!("{base_url}/{public_path}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SitemapConfig {
+    pub enabled: bool,
+    pub lastmod: Option<LastModifiedSource>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LastModifiedSource {
+    /// The source file's modification time.
+    FileModified,
+    /// The date of the last commit touching the source file (falls back to the mtime).
+    GitCommit,
+}
+
+#[derive(Debug, Clone)]
+pub struct RobotsConfig {
+    pub enabled: bool,
+    pub user_agent: String,
+    pub allow: Vec<String>,
+    pub disallow: Vec<String>,
+    /// Appended verbatim.
+    pub extra: Option<String>,
+}
+
+impl Default for RobotsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            user_agent: String::from("*"),
+            allow: Vec::default(),
+            disallow: Vec::default(),
+            extra: None,
+        }
+    }
+}
+
+/// A `[[feeds]]` entry: a collection of pages published as RSS, Atom and/or JSON Feed.
+#[derive(Debug, Clone)]
+pub struct FeedConfig {
+    pub title: String,
+    pub description: Option<String>,
+    /// Glob matched against page source paths.
+    pub pages: Option<String>,
+    /// Only pages whose `<page-meta tags>` include this tag.
+    pub tag: Option<String>,
+    /// Output directory (relative to the site root) for the feed files.
+    pub output: PathBuf,
+    pub formats: Vec<FeedFormat>,
+    /// Maximum number of (most recent) items.
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeedFormat {
+    Rss,
+    Atom,
+    Json,
+}
+
+impl FeedFormat {
+    pub const ALL: [FeedFormat; 3] = [FeedFormat::Rss, FeedFormat::Atom, FeedFormat::Json];
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            Self::Rss => "rss.xml",
+            Self::Atom => "atom.xml",
+            Self::Json => "feed.json",
+        }
+    }
+}
@@ -2,11 +2,15 @@
 
 use std::path::{Path, PathBuf};
 
+use macro_types::breadcrumbs::{DeclaredHierarchy, TitleSource};
 use macro_types::lexical_env::{Featureset, SourceHostRef, SourceHost};
 use macro_types::macro_tag::MacroTagSet;
 use macro_types::project::{FileInput, ProjectContext};
 use macro_types::tag_rewrite_rule::TagRewriteRuleSet;
 
+use crate::{CssOptions, FeedConfig, ImageOptions, SiteConfig};
+
+#[derive(Debug, Clone)]
 pub struct CompilerInputRule {
     pub source: FileInput,
     /// Will override the global template.
@@ -41,9 +45,34 @@ impl CompilationMode {
 
 pub struct CompilerInputs {
     pub compilation_mode: CompilationMode,
+    /// Minify emitted scripts and stylesheets.
+    pub minify: bool,
+    /// Build pages marked `<page-meta draft>`; otherwise they are skipped.
+    pub drafts: bool,
     pub global_template: Option<PathBuf>,
     pub sources: Vec<CompilerInputRule>,
+    /// Parents and titles declared by the site tree file, if any.
+    pub site_hierarchy: DeclaredHierarchy,
+    /// Where the titles of undeclared pages are read from, in order.
+    pub title_sources: Vec<TitleSource>,
     pub project: ProjectContext,
+    /// Upper bound for nested `<include>` expansion; defaults to `IncludeChain::DEFAULT_MAX_DEPTH`.
+    pub max_include_depth: Option<usize>,
+    /// Site-wide settings (canonical base URL, sitemap, robots.txt).
+    pub site: Option<SiteConfig>,
+    pub feeds: Vec<FeedConfig>,
+    pub headings: HeadingOptions,
+    pub images: ImageOptions,
+    pub css: CssOptions,
+}
+
+/// Heading `id` generation (the manifest's `[headings]` section).
+#[derive(Debug, Clone, Default)]
+pub struct HeadingOptions {
+    /// Give every heading without an `id` a slug of its text.
+    pub auto_ids: bool,
+    /// Append a self-link anchor to every heading with an `id`.
+    pub anchors: bool,
 }
 
 #[derive(Clone)]
@@ -1,7 +1,11 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use clap::{Parser, Subcommand};
+use web_compiler_core::check::external::{check_external_links, ExternalLinkCheckOptions};
+use web_compiler_core::error::CompilerError;
 use web_compiler_core::system::web_publishing_compiler_featureset;
+use web_compiler_macro_types::profile::Profiler;
+use web_compiler_macro_types::project::ProjectContext;
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
@@ -13,6 +17,12 @@ pub struct CommandLineInterface {
 #[derive(Subcommand, Debug)]
 enum SubCommand {
     Build(BuildCli),
+    /// Build, then validate internal links, assets and fragment anchors.
+    Check(CheckCli),
+    /// Validate the manifest without building.
+    CheckManifest(CheckManifestCli),
+    /// Print the manifest's JSON Schema (for editor completion).
+    ManifestSchema(ManifestSchemaCli),
 }
 
 #[derive(Parser, Debug)]
@@ -23,29 +33,193 @@ struct BuildCli {
     /// Execute a specfic target.
     #[arg(long)]
     pub target: Option<String>,
+    /// Time each build phase and each macro tag / rewrite rule per page.
+    #[arg(long)]
+    pub profile: bool,
+    /// Where to write the Chrome trace-event JSON when profiling
+    /// (relative to the manifest directory).
+    #[arg(long, default_value = ".web-compiler/profile.trace.json")]
+    pub profile_output: PathBuf,
+    /// Validate internal links, `srcset` candidates, CSS `url()`s and
+    /// `#fragment` anchors after building; exits non-zero when any are broken.
+    #[arg(long)]
+    pub check: bool,
+    #[command(flatten)]
+    pub external: ExternalLinkArgs,
+}
+
+#[derive(Parser, Debug)]
+struct CheckCli {
+    /// Path to the manifest file.
+    pub manifest: PathBuf,
+    /// Check a specfic target.
+    #[arg(long)]
+    pub target: Option<String>,
+    #[command(flatten)]
+    pub external: ExternalLinkArgs,
+}
+
+#[derive(Parser, Debug)]
+struct CheckManifestCli {
+    /// Path to the manifest file.
+    pub manifest: PathBuf,
+}
+
+#[derive(Parser, Debug)]
+struct ManifestSchemaCli {
+    /// Write the schema to this file instead of stdout.
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(clap::Args, Debug)]
+struct ExternalLinkArgs {
+    /// Also verify external links over HTTP (opt-in; results are cached in
+    /// `.web-compiler/external-links.json`).
+    #[arg(long)]
+    pub check_external: bool,
+    /// Maximum number of concurrent external requests.
+    #[arg(long, default_value_t = 8)]
+    pub external_concurrency: usize,
+    /// Retries for transient failures (network errors, 429, 5xx).
+    #[arg(long, default_value_t = 2)]
+    pub external_retries: usize,
+}
+
+impl ExternalLinkArgs {
+    fn options(&self) -> ExternalLinkCheckOptions {
+        ExternalLinkCheckOptions {
+            concurrency: self.external_concurrency,
+            retries: self.external_retries,
+            ..Default::default()
+        }
+    }
 }
 
 impl CommandLineInterface {
     pub fn load() -> Self {
         Self::parse()
     }
+    /// Runs the command, exiting non-zero when the build fails or a check
+    /// finds problems.
     pub fn execute(self) {
-        match self.command {
+        let result = match self.command {
             SubCommand::Build(build) => build.execute(),
+            SubCommand::Check(check) => check.execute(),
+            SubCommand::CheckManifest(check) => check.execute(),
+            SubCommand::ManifestSchema(schema) => Ok(schema.execute()),
+        };
+        match result {
+            Ok(true) => (),
+            Ok(false) => std::process::exit(1),
+            Err(error) => {
+                web_compiler_core::common::log::log_error(&error, None, None);
+                std::process::exit(1);
+            }
         }
     }
 }
 
 impl BuildCli {
-    pub fn execute(self) {
-        let manifest = crate::manifest::Manifest::load(self.manifest)
-            .expect("web-compiler manifest file");
+    /// Whether every requested check passed.
+    pub fn execute(self) -> Result<bool, CompilerError> {
+        let manifest = crate::manifest::Manifest::load(self.manifest)?;
         manifest.navigate_to_working_dir();
-        let compiler_pipeline = manifest.to_compiler_pipeline(
+        let mut compiler_pipeline = manifest.to_compiler_pipeline(
             self.target.as_ref(),
             web_publishing_compiler_featureset()
-        );
-        web_compiler_core::system::execute_compiler_pipeline(compiler_pipeline);
+        )?;
+        if self.profile {
+            compiler_pipeline.inputs.project.profiler = Profiler::enabled();
+        }
+        let project_context = compiler_pipeline.inputs.project.clone();
+        let resolved_dependencies = web_compiler_core::system::execute_compiler_pipeline(compiler_pipeline);
+        if project_context.profiler.is_enabled() {
+            write_profile_report(&project_context, &self.profile_output);
+        }
+        let resolved_dependencies = resolved_dependencies?;
+        let mut is_ok = true;
+        if self.check {
+            let report = web_compiler_core::check::check_links(&resolved_dependencies, &project_context);
+            report.print();
+            is_ok &= report.is_ok();
+        }
+        if self.external.check_external {
+            let report = check_external_links(&resolved_dependencies, &self.external.options());
+            report.print();
+            is_ok &= report.is_ok();
+        }
+        Ok(is_ok)
+    }
+}
+
+impl CheckCli {
+    pub fn execute(self) -> Result<bool, CompilerError> {
+        let build = BuildCli {
+            manifest: self.manifest,
+            target: self.target,
+            profile: false,
+            profile_output: PathBuf::default(),
+            check: true,
+            external: self.external,
+        };
+        build.execute()
+    }
+}
+
+impl CheckManifestCli {
+    /// Whether the manifest is free of problems.
+    pub fn execute(self) -> Result<bool, CompilerError> {
+        let manifest = crate::manifest::Manifest::load(&self.manifest)?;
+        manifest.navigate_to_working_dir();
+        let problems = manifest.validate();
+        for problem in problems.iter() {
+            eprintln!("{problem}");
+        }
+        if problems.is_empty() {
+            println!("> checked {:?}: targets [{}], no problems", self.manifest, manifest.target_ids().join(", "));
+        } else {
+            let noun = if problems.len() == 1 { "problem" } else { "problems" };
+            eprintln!("❌ checked {:?}: {} {noun}", self.manifest, problems.len());
+        }
+        Ok(problems.is_empty())
+    }
+}
+
+impl ManifestSchemaCli {
+    pub fn execute(self) -> bool {
+        let schema = serde_json::to_string_pretty(&crate::manifest::Manifest::json_schema()).unwrap();
+        let Some(output) = self.output else {
+            println!("{schema}");
+            return true
+        };
+        if let Some(parent) = output.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        match std::fs::write(&output, schema) {
+            Ok(()) => {
+                println!("> writing manifest schema {output:?}");
+                true
+            }
+            Err(error) => {
+                eprintln!("⚠️ failed to write manifest schema {output:?}: {error}");
+                false
+            }
+        }
+    }
+}
+
+fn write_profile_report(project_context: &ProjectContext, trace_path: &Path) {
+    let profiler = &project_context.profiler;
+    println!("{}", profiler.summary_table(10));
+    println!("> parse cache: {}", project_context.source_cache.stats());
+    let trace = serde_json::to_string(&profiler.chrome_trace()).unwrap();
+    if let Some(parent) = trace_path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    match std::fs::write(trace_path, trace) {
+        Ok(()) => println!("> writing profile trace {trace_path:?}"),
+        Err(error) => eprintln!("⚠️ failed to write profile trace {trace_path:?}: {error}"),
     }
 }
 
@@ -1,24 +1,105 @@
+use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use web_compiler_types::{CompilationMode, CompilerFeatureset, CompilerInputRule, CompilerInputs, CompilerPipeline};
+use web_compiler_types::{CssOptions, FeedConfig, FeedFormat, HeadingOptions, ImageOptions, ResponsiveImageFormat, LastModifiedSource, RobotsConfig, SiteConfig, SitemapConfig};
+use web_compiler_macro_types::breadcrumbs::TitleSource;
+use web_compiler_macro_types::inline_assets::InlineAssetOptions;
 use web_compiler_macro_types::project::{FileInput, ProjectContext};
+use web_compiler_core::error::ManifestError;
+use web_compiler_core::site::tree::SiteTree;
 
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 struct ProjectSpec {
     output: PathBuf,
     template: PathBuf,
+    /// A site tree file (`site.html`) declaring pages as a `<page>`
+    /// hierarchy; used with or instead of `[[sources]]`.
+    #[serde(default)]
+    site_tree: Option<PathBuf>,
+    /// Maximum nesting depth for `<include>` (guards recursive fragments).
+    #[serde(default)]
+    max_include_depth: Option<usize>,
+    /// Where breadcrumb and `nav` titles are read from, in order, for pages
+    /// the site tree doesn't give a `<title>`; the file name is the last
+    /// resort. Defaults to `["define-title", "h1", "filename"]`.
+    #[serde(default)]
+    title_sources: Option<Vec<TitleSourceSpec>>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 struct TargetSpec {
-    r#type: String,
     id: String,
-    output: PathBuf,
+    #[serde(rename = "type")]
+    mode: TargetMode,
+    /// Overrides `project.output`.
+    #[serde(default)]
+    output: Option<PathBuf>,
+    /// Overrides `site.base_url`, e.g. for a staging deployment.
+    #[serde(default)]
+    base_url: Option<String>,
+    /// Minify scripts and stylesheets; defaults to on for production targets.
+    #[serde(default)]
+    minify: Option<bool>,
+    /// Build pages marked `<page-meta draft>`; defaults to on for dev targets.
+    #[serde(default)]
+    drafts: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+enum TargetMode {
+    Production,
+    Dev,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Case-insensitive and ignoring surrounding whitespace (`"Production"`), as
+/// target types always have been.
+impl<'de> Deserialize<'de> for TargetMode {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        match value.trim().to_ascii_lowercase().as_str() {
+            "production" => Ok(Self::Production),
+            "dev" => Ok(Self::Dev),
+            _ => Err(serde::de::Error::unknown_variant(&value, &["production", "dev"])),
+        }
+    }
+}
+
+impl TargetMode {
+    fn compilation_mode(self) -> CompilationMode {
+        match self {
+            Self::Production => CompilationMode::Production,
+            Self::Dev => CompilationMode::Dev,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+enum TitleSourceSpec {
+    DefineTitle,
+    H1,
+    Filename,
+}
+
+impl TitleSourceSpec {
+    fn to_title_source(self) -> TitleSource {
+        match self {
+            Self::DefineTitle => TitleSource::DefineTitle,
+            Self::H1 => TitleSource::H1,
+            Self::Filename => TitleSource::Filename,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 struct SourceSpec {
     input: String,
     #[serde(default)]
@@ -28,10 +109,259 @@ struct SourceSpec {
     template: Option<PathBuf>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct SiteSpec {
+    /// Canonical base URL, e.g. `https://example.com`.
+    base_url: String,
+    #[serde(default = "default_true")]
+    sitemap: bool,
+    /// `"mtime"` or `"git"`; omit to leave out `<lastmod>`.
+    #[serde(default)]
+    lastmod: Option<LastModifiedSpec>,
+    #[serde(default)]
+    robots: RobotsSpec,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+enum LastModifiedSpec {
+    Mtime,
+    Git,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct RobotsSpec {
+    #[serde(default = "default_true")]
+    enabled: bool,
+    #[serde(default = "default_user_agent")]
+    user_agent: String,
+    #[serde(default)]
+    allow: Vec<String>,
+    #[serde(default)]
+    disallow: Vec<String>,
+    /// Appended verbatim to the generated file.
+    #[serde(default)]
+    extra: Option<String>,
+}
+
+impl Default for RobotsSpec {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            user_agent: default_user_agent(),
+            allow: Vec::default(),
+            disallow: Vec::default(),
+            extra: None,
+        }
+    }
+}
+
+fn default_true() -> bool { true }
+
+fn default_user_agent() -> String { String::from("*") }
+
+impl SiteSpec {
+    fn to_site_config(&self) -> SiteConfig {
+        SiteConfig {
+            base_url: self.base_url.clone(),
+            sitemap: SitemapConfig {
+                enabled: self.sitemap,
+                lastmod: self.lastmod.map(|x| match x {
+                    LastModifiedSpec::Mtime => LastModifiedSource::FileModified,
+                    LastModifiedSpec::Git => LastModifiedSource::GitCommit,
+                }),
+            },
+            robots: RobotsConfig {
+                enabled: self.robots.enabled,
+                user_agent: self.robots.user_agent.clone(),
+                allow: self.robots.allow.clone(),
+                disallow: self.robots.disallow.clone(),
+                extra: self.robots.extra.clone(),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct ImagesSpec {
+    /// Source image globs made responsive without a `responsive` attribute.
+    #[serde(default)]
+    include: Vec<String>,
+    #[serde(default)]
+    widths: Option<Vec<u32>>,
+    /// Any of `"avif"`, `"webp"`, `"original"`; defaults to all three.
+    #[serde(default)]
+    formats: Option<Vec<ImageFormatSpec>>,
+    #[serde(default)]
+    quality: Option<u8>,
+    #[serde(default)]
+    sizes: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+enum ImageFormatSpec {
+    Avif,
+    Webp,
+    Original,
+}
+
+impl ImagesSpec {
+    fn to_image_options(&self) -> ImageOptions {
+        let defaults = ImageOptions::default();
+        let formats = self.formats
+            .as_ref()
+            .map(|formats| {
+                formats
+                    .iter()
+                    .map(|x| match x {
+                        ImageFormatSpec::Avif => ResponsiveImageFormat::Avif,
+                        ImageFormatSpec::Webp => ResponsiveImageFormat::Webp,
+                        ImageFormatSpec::Original => ResponsiveImageFormat::Original,
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or(defaults.formats);
+        ImageOptions {
+            include: self.include.clone(),
+            widths: self.widths.clone().unwrap_or(defaults.widths),
+            formats,
+            quality: self.quality.unwrap_or(defaults.quality),
+            sizes: self.sizes.clone().unwrap_or(defaults.sizes),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct CssSpec {
+    /// Inline each page's critical CSS and load stylesheets asynchronously
+    /// (production targets only).
+    #[serde(default)]
+    critical: bool,
+    /// Remove rules that match no emitted page (production targets only).
+    #[serde(default)]
+    prune: bool,
+    /// Classes added by scripts at runtime (`"is-open"`, `"js-*"`) that pruning keeps.
+    #[serde(default)]
+    safelist: Vec<String>,
+}
+
+impl CssSpec {
+    fn to_css_options(&self) -> CssOptions {
+        CssOptions {
+            critical: self.critical,
+            prune: self.prune,
+            safelist: self.safelist.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct InlineSpec {
+    /// Embed raster images and CSS `url()` targets up to this many bytes as data URIs.
+    #[serde(default)]
+    max_size: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct HeadingsSpec {
+    /// Generate slug `id`s for headings that lack one.
+    #[serde(default)]
+    ids: bool,
+    /// Insert self-link anchors into headings (implies `ids`).
+    #[serde(default)]
+    anchors: bool,
+}
+
+impl HeadingsSpec {
+    fn to_heading_options(&self) -> HeadingOptions {
+        HeadingOptions {
+            auto_ids: self.ids || self.anchors,
+            anchors: self.anchors,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct FeedSpec {
+    title: String,
+    #[serde(default)]
+    description: Option<String>,
+    /// Glob matched against page source paths.
+    #[serde(default)]
+    pages: Option<String>,
+    /// Select pages tagged via `<page-meta tags="...">`.
+    #[serde(default)]
+    tag: Option<String>,
+    /// Output directory for `rss.xml`, `atom.xml` and `feed.json`.
+    #[serde(default)]
+    output: PathBuf,
+    /// Any of `"rss"`, `"atom"`, `"json"`; defaults to all three.
+    #[serde(default)]
+    formats: Option<Vec<FeedFormatSpec>>,
+    #[serde(default)]
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+enum FeedFormatSpec {
+    Rss,
+    Atom,
+    Json,
+}
+
+impl FeedSpec {
+    fn to_feed_config(&self) -> FeedConfig {
+        let formats = self.formats
+            .as_ref()
+            .map(|formats| {
+                formats
+                    .iter()
+                    .map(|x| match x {
+                        FeedFormatSpec::Rss => FeedFormat::Rss,
+                        FeedFormatSpec::Atom => FeedFormat::Atom,
+                        FeedFormatSpec::Json => FeedFormat::Json,
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_else(|| FeedFormat::ALL.to_vec());
+        FeedConfig {
+            title: self.title.clone(),
+            description: self.description.clone(),
+            pages: self.pages.clone(),
+            tag: self.tag.clone(),
+            output: self.output.clone(),
+            formats,
+            limit: self.limit,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct ManifestSpec {
     project: ProjectSpec,
     #[serde(default)]
+    site: Option<SiteSpec>,
+    #[serde(default)]
+    feeds: Vec<FeedSpec>,
+    #[serde(default)]
+    headings: HeadingsSpec,
+    #[serde(default)]
+    images: ImagesSpec,
+    #[serde(default)]
+    css: CssSpec,
+    #[serde(default)]
+    inline: InlineSpec,
+    #[serde(default)]
     targets: Vec<TargetSpec>,
     #[serde(default)]
     sources: Vec<SourceSpec>,
@@ -45,7 +375,7 @@ pub struct Manifest {
 
 impl Manifest {
     pub fn navigate_to_working_dir(&self) {
-        let directory = self.file_path.parent().expect("path should have a parent");
+        let directory = self.directory();
         let _ = std::env::set_current_dir(directory)
             .inspect_err(|_| {
                 if !directory.as_os_str().is_empty() {
@@ -53,83 +383,191 @@ impl Manifest {
                 }
             });
     }
-    pub fn load(file_path: impl AsRef<Path>) -> Result<Self, Box<dyn std::error::Error>> {
+    fn directory(&self) -> &Path {
+        self.file_path.parent().unwrap_or(Path::new(""))
+    }
+    pub fn load(file_path: impl AsRef<Path>) -> Result<Self, ManifestError> {
         let file_path = file_path.as_ref();
-        let text = std::fs::read_to_string(file_path)?;
-        let spec = toml::from_str::<ManifestSpec>(&text)?;
+        let text = std::fs::read_to_string(file_path).map_err(|error| {
+            ManifestError::Read { path: file_path.to_path_buf(), error }
+        })?;
+        let spec = toml::from_str::<ManifestSpec>(&text).map_err(|error| {
+            ManifestError::Parse {
+                path: file_path.to_path_buf(),
+                position: error.span().map(|span| line_and_column(&text, span.start)),
+                message: error.message().trim_end().to_string(),
+            }
+        })?;
         Ok(Manifest {
             spec,
             file_path: file_path.to_path_buf(),
         })
     }
-    pub fn to_compiler_pipeline(&self, target: Option<&String>, featureset: CompilerFeatureset) -> CompilerPipeline {
-        let sources = self.spec.sources
+    /// The JSON Schema of the manifest format, for editor completion and
+    /// validation.
+    pub fn json_schema() -> serde_json::Value {
+        serde_json::to_value(schemars::schema_for!(ManifestSpec)).unwrap()
+    }
+    pub fn target_ids(&self) -> Vec<&str> {
+        self.spec.targets.iter().map(|x| x.id.as_str()).collect()
+    }
+    fn lookup_target(&self, target_id: &str) -> Result<&TargetSpec, ManifestError> {
+        self.spec.targets
             .iter()
-            .flat_map(|source| {
-                web_compiler_core::common::path_utils::resolve_file_path_patern(&source.input)
-                    .unwrap()
-                    .into_iter()
-                    .map(|src_path| {
-                        let public_path = source.strip_prefix
-                            .as_ref()
-                            .map(|prefix| {
-                                src_path.strip_prefix(prefix).ok().unwrap().to_path_buf()
-                            });
-                        CompilerInputRule {
-                            source: FileInput {
-                                source: src_path,
-                                public: public_path,
-                            },
-                            local_template: source.template.clone(),
-                        }
-                    })
-                    .collect::<Vec<_>>()
+            .find(|x| x.id.as_str() == target_id)
+            .ok_or_else(|| ManifestError::TargetNotFound {
+                target: target_id.to_string(),
+                available: self.spec.targets.iter().map(|x| x.id.clone()).collect(),
             })
-            .collect::<Vec<_>>();
-        let project = ProjectContext {
-            project_root: self.file_path.parent().unwrap().to_path_buf(),
-            output_dir: {
-                target
-                    .map(|target_id| {
-                        let target_spec = self.spec.targets
-                            .iter()
-                            .find(|x| x.id.as_str() == target_id)
-                            .expect("target not found");
-                        target_spec.output.clone()
-                    })
-                    .unwrap_or_else(|| self.spec.project.output.clone())
-            },
-        };
-        if let Some(target_id) = target {
-            let target = self.spec.targets
-                .iter()
-                .find(|x| x.id.as_str() == target_id)
-                .expect("target not found");
-            let mode = match target.r#type.to_lowercase().trim() {
-                "production" => CompilationMode::Production,
-                "dev" => CompilationMode::Dev,
-                _ => panic!("target type not valid"),
-            };
-            return CompilerPipeline {
-                featureset,
-                inputs: CompilerInputs {
-                    compilation_mode: mode,
-                    sources,
-                    project,
-                    global_template: Some(self.spec.project.template.clone()),
-                },
+    }
+    fn load_site_tree(&self) -> Result<SiteTree, ManifestError> {
+        match self.spec.project.site_tree.as_ref() {
+            Some(path) => SiteTree::load(path),
+            None => Ok(SiteTree::default()),
+        }
+    }
+    /// The site tree's pages, then the `[[sources]]` it doesn't list.
+    fn resolve_sources(&self) -> Result<SiteTree, ManifestError> {
+        let mut site_tree = self.load_site_tree()?;
+        for source in self.spec.sources.iter() {
+            let rules = resolve_source(source)?
+                .into_iter()
+                .filter(|x| !site_tree.contains(&x.source.source))
+                .collect::<Vec<_>>();
+            site_tree.sources.extend(rules);
+        }
+        Ok(site_tree)
+    }
+    fn title_sources(&self) -> Vec<TitleSource> {
+        match self.spec.project.title_sources.as_ref() {
+            Some(sources) => sources.iter().map(|x| x.to_title_source()).collect(),
+            None => TitleSource::DEFAULT_ORDER.to_vec(),
+        }
+    }
+    /// `[site]`, with the target's `base_url` override applied.
+    fn site_config(&self, target: Option<&TargetSpec>) -> Result<Option<SiteConfig>, ManifestError> {
+        let base_url = target.and_then(|x| x.base_url.as_ref());
+        match (self.spec.site.as_ref(), base_url) {
+            (Some(site), base_url) => {
+                let mut site_config = site.to_site_config();
+                if let Some(base_url) = base_url {
+                    site_config.base_url = base_url.clone();
+                }
+                Ok(Some(site_config))
+            }
+            (None, Some(_)) => Err(ManifestError::BaseUrlWithoutSite {
+                target: target.map(|x| x.id.clone()).unwrap_or_default(),
+            }),
+            (None, None) => Ok(None),
+        }
+    }
+    /// Every problem that would fail a build of any target, plus sources and
+    /// templates that don't exist. Expects to run from the manifest directory
+    /// (see [`Self::navigate_to_working_dir`]).
+    pub fn validate(&self) -> Vec<ManifestError> {
+        let mut problems = Vec::new();
+        let mut target_ids = HashSet::new();
+        for target in self.spec.targets.iter() {
+            if !target_ids.insert(target.id.as_str()) {
+                problems.push(ManifestError::DuplicateTarget { target: target.id.clone() });
+            }
+            if let Err(error) = self.site_config(Some(target)) {
+                problems.push(error);
             }
         }
-        CompilerPipeline {
+        let templates = std::iter::once(("project.template", &self.spec.project.template))
+            .chain(self.spec.sources.iter().filter_map(|x| x.template.as_ref().map(|template| ("sources.template", template))));
+        for (key, template) in templates {
+            if !template.is_file() {
+                problems.push(ManifestError::MissingFile { key, path: template.clone() });
+            }
+        }
+        if let Err(error) = self.load_site_tree() {
+            problems.push(error);
+        }
+        for source in self.spec.sources.iter() {
+            match resolve_source(source) {
+                Ok(rules) if rules.is_empty() => {
+                    problems.push(ManifestError::NoMatchingSources { pattern: source.input.clone() });
+                }
+                Ok(_) => (),
+                Err(error) => problems.push(error),
+            }
+        }
+        problems
+    }
+    pub fn to_compiler_pipeline(&self, target: Option<&String>, featureset: CompilerFeatureset) -> Result<CompilerPipeline, ManifestError> {
+        let SiteTree { sources, hierarchy } = self.resolve_sources()?;
+        let target = target
+            .map(|target_id| self.lookup_target(target_id))
+            .transpose()?;
+        let project = ProjectContext {
+            project_root: self.directory().to_path_buf(),
+            output_dir: target
+                .and_then(|target| target.output.clone())
+                .unwrap_or_else(|| self.spec.project.output.clone()),
+            source_cache: Default::default(),
+            image_cache: Default::default(),
+            profiler: Default::default(),
+            inline_assets: InlineAssetOptions { max_size: self.spec.inline.max_size },
+        };
+        let compilation_mode = target
+            .map(|target| target.mode.compilation_mode())
+            .unwrap_or_default();
+        Ok(CompilerPipeline {
             featureset,
             inputs: CompilerInputs {
-                compilation_mode: CompilationMode::default(),
+                compilation_mode,
+                minify: target.and_then(|x| x.minify).unwrap_or(compilation_mode.is_production()),
+                drafts: target.and_then(|x| x.drafts).unwrap_or(compilation_mode.is_dev()),
                 sources,
+                site_hierarchy: hierarchy,
+                title_sources: self.title_sources(),
                 project,
                 global_template: Some(self.spec.project.template.clone()),
+                max_include_depth: self.spec.project.max_include_depth,
+                site: self.site_config(target)?,
+                feeds: self.spec.feeds.iter().map(FeedSpec::to_feed_config).collect(),
+                headings: self.spec.headings.to_heading_options(),
+                images: self.spec.images.to_image_options(),
+                css: self.spec.css.to_css_options(),
             },
-        }
+        })
     }
 }
 
+fn resolve_source(source: &SourceSpec) -> Result<Vec<CompilerInputRule>, ManifestError> {
+    let paths = web_compiler_core::common::path_utils::resolve_file_path_patern(&source.input)
+        .map_err(|error| ManifestError::InvalidSourcePattern {
+            pattern: source.input.clone(),
+            message: error.to_string(),
+        })?;
+    let mut rules = Vec::new();
+    for src_path in paths {
+        let public_path = match source.strip_prefix.as_ref() {
+            Some(prefix) => match src_path.strip_prefix(prefix) {
+                Ok(public_path) => Some(public_path.to_path_buf()),
+                Err(_) => {
+                    return Err(ManifestError::SourceOutsidePrefix { source: src_path, prefix: prefix.clone() })
+                }
+            },
+            None => None,
+        };
+        rules.push(CompilerInputRule {
+            source: FileInput {
+                source: src_path,
+                public: public_path,
+            },
+            local_template: source.template.clone(),
+        });
+    }
+    Ok(rules)
+}
 
+/// 1-based line and column of a byte offset.
+fn line_and_column(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset.min(text.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().unwrap_or_default().chars().count() + 1;
+    (line, column)
+}